@@ -0,0 +1,117 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: raga-cli fmt [--check] [FILE...]";
+
+/// Formats the given files in place, or stdin to stdout when no files are
+/// given. With `--check` nothing is written; the exit code is 1 if any input
+/// is not formatted yet and 2 if any input could not be parsed.
+pub(crate) fn run(args: &[String]) -> ExitCode {
+    let mut check = false;
+    let mut paths = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            flag if flag.starts_with("--") => {
+                eprintln!("unknown flag '{}'\n{}", flag, USAGE);
+                return ExitCode::from(2);
+            }
+            path => paths.push(path),
+        }
+    }
+
+    let outcome = if paths.is_empty() {
+        format_stdin(check)
+    } else {
+        paths
+            .into_iter()
+            .map(|path| format_file(path, check))
+            .fold(Outcome::Formatted, Outcome::max)
+    };
+
+    ExitCode::from(outcome as u8)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Outcome {
+    Formatted = 0,
+    NotFormatted = 1,
+    Failed = 2,
+}
+
+fn format_stdin(check: bool) -> Outcome {
+    let mut input = String::new();
+    if let Err(err) = io::stdin().read_to_string(&mut input) {
+        eprintln!("failed to read stdin: {}", err);
+        return Outcome::Failed;
+    }
+
+    let formatted = match format_source("<stdin>", &input) {
+        Some(formatted) => formatted,
+        None => return Outcome::Failed,
+    };
+
+    if check {
+        return if formatted == input {
+            Outcome::Formatted
+        } else {
+            eprintln!("<stdin> is not formatted");
+            Outcome::NotFormatted
+        };
+    }
+
+    match io::stdout().write_all(formatted.as_bytes()) {
+        Ok(()) => Outcome::Formatted,
+        Err(err) => {
+            eprintln!("failed to write stdout: {}", err);
+            Outcome::Failed
+        }
+    }
+}
+
+fn format_file(path: &str, check: bool) -> Outcome {
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            return Outcome::Failed;
+        }
+    };
+
+    let formatted = match format_source(path, &input) {
+        Some(formatted) => formatted,
+        None => return Outcome::Failed,
+    };
+
+    if formatted == input {
+        return Outcome::Formatted;
+    }
+
+    if check {
+        eprintln!("{} is not formatted", path);
+        return Outcome::NotFormatted;
+    }
+
+    match fs::write(path, formatted) {
+        Ok(()) => Outcome::Formatted,
+        Err(err) => {
+            eprintln!("failed to write {}: {}", path, err);
+            Outcome::Failed
+        }
+    }
+}
+
+fn format_source(name: &str, input: &str) -> Option<String> {
+    match raga::format(input) {
+        Ok(formatted) => Some(formatted),
+        Err(errors) => {
+            for error in errors {
+                let (line, col) = error.span.line_col(input);
+                eprintln!("{}:{}:{}: {}", name, line, col, error);
+            }
+            None
+        }
+    }
+}
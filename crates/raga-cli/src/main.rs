@@ -1,40 +1,48 @@
-use std::io;
-use std::io::Write;
-
-fn main() -> io::Result<()> {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
-    let mut stderr = io::stderr();
-    let mut input = String::new();
-
-    let mut env = raga::Env::default();
-
-    loop {
-        write!(stdout, "-> ")?;
-        stdout.flush()?;
-
-        stdin.read_line(&mut input)?;
-
-        match run(input.trim(), &mut env) {
-            Ok(Some(val)) => writeln!(stdout, "{}", val)?,
-            Ok(None) => {}
-            Err(msg) => writeln!(stderr, "{}", msg)?
+mod fmt;
+mod repl;
+mod script;
+
+use std::env;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: raga-cli [FILE | -e CODE | -]
+       raga-cli check FILE
+       raga-cli fmt [--check] [FILE...]
+
+With no arguments an interactive session is started. FILE runs a script,
+-e runs CODE given on the command line and - runs a script read from stdin.
+check reports syntax, name and type errors in FILE without running it.";
+
+/// Exit codes of `raga-cli` when running a program, following sysexits.h.
+const EXIT_USAGE: u8 = 64;
+const EXIT_PARSE_ERROR: u8 = 65;
+const EXIT_RUNTIME_ERROR: u8 = 70;
+const EXIT_IO_ERROR: u8 = 74;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let arg_strs: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match arg_strs.as_slice() {
+        [] => repl::run(),
+        ["fmt", ..] => fmt::run(&args[1..]),
+        ["check", path] => script::check_file(path),
+        ["-h" | "--help"] => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        ["-e", code] => script::run("-e", code),
+        ["-"] => script::run_stdin(),
+        [path] if !path.starts_with('-') => script::run_file(path),
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(EXIT_USAGE)
         }
-
-        input.clear();
     }
 }
 
-fn run(input: &str, env: &mut raga::Env) -> Result<Option<raga::Val>, String> {
-    let parse = raga::parse(input).map_err(|msg| format!("Parse error: {}", msg))?;
-
-    let evaluated = parse
-        .eval(env)
-        .map_err(|msg| format!("Evaluation error: {}", msg))?;
-
-    if evaluated == raga::Val::Unit {
-        Ok(None)
-    } else {
-        Ok(Some(evaluated))
-    }
+/// The engine that scripts and the REPL run code with, so that both get
+/// the same limits and settings.
+fn engine() -> raga::Engine {
+    raga::Engine::new()
 }
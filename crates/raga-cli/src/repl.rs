@@ -0,0 +1,264 @@
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Instant;
+
+const HELP: &str = ":env          list the bindings and functions defined so far
+:ast <code>   print <code> as it parses, with only the parentheses it needs
+:type <code>  evaluate <code> without keeping its bindings and print the type
+:load <file>  evaluate a file into the session
+:reset        forget every binding and function
+:time <code>  evaluate <code> and print how long that took
+:help         print this list";
+
+const COMMANDS: &[&str] = &[":env", ":ast", ":type", ":load", ":reset", ":time", ":help"];
+
+pub(crate) fn run() -> ExitCode {
+    match repl() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::from(crate::EXIT_IO_ERROR)
+        }
+    }
+}
+
+fn repl() -> rustyline::Result<()> {
+    let mut editor = Editor::<ReplHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ReplHelper::default()));
+
+    let history_path = history_path();
+    if let Some(path) = &history_path {
+        // There is no history yet the first time the REPL is started.
+        let _ = editor.load_history(path);
+    }
+
+    let mut engine = crate::engine();
+    let mut input = String::new();
+
+    loop {
+        let prompt = if input.is_empty() { "-> " } else { ".. " };
+
+        let at_eof = match editor.readline(prompt) {
+            Ok(line) if input.is_empty() && line.trim_start().starts_with(':') => {
+                editor.add_history_entry(line.trim())?;
+                run_command(line.trim(), &mut engine);
+                update_names(&mut editor, engine.env());
+                continue;
+            }
+            Ok(line) => {
+                input.push_str(&line);
+                input.push('\n');
+                false
+            }
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => true,
+            Err(err) => return Err(err),
+        };
+        let parse = raga::parse(&input);
+
+        // Keep reading lines until the statement is complete, e.g. until an
+        // open `{` is closed. At the end of input whatever was typed so far
+        // is evaluated, so the errors of an unfinished statement get shown.
+        if parse.is_incomplete() && !at_eof {
+            continue;
+        }
+
+        if !input.trim().is_empty() {
+            editor.add_history_entry(input.trim_end())?;
+        }
+
+        print_result(engine.eval(&input), &input);
+        update_names(&mut editor, engine.env());
+
+        if at_eof {
+            println!();
+            break;
+        }
+
+        input.clear();
+    }
+
+    if let Some(path) = &history_path {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        editor.save_history(path)?;
+    }
+
+    Ok(())
+}
+
+/// Runs a `:command` typed at the prompt.
+fn run_command(line: &str, engine: &mut raga::Engine) {
+    let (command, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let arg = arg.trim();
+
+    match (command, arg) {
+        (":env", "") => print_env(engine.env()),
+        (":ast", code) if !code.is_empty() => {
+            let parse = raga::parse(code);
+            if parse.errors().is_empty() {
+                for stmt in parse.stmts() {
+                    println!("{}", stmt);
+                }
+            } else {
+                report(raga::Error::Parse(parse.errors().to_vec()), code);
+            }
+        }
+        (":type", code) if !code.is_empty() => match engine.eval_scratch(code) {
+            Ok(val) => println!("{}", val.type_name()),
+            Err(err) => report(err, code),
+        },
+        (":load", path) if !path.is_empty() => match fs::read_to_string(path) {
+            Ok(code) => print_result(engine.eval(&code), &code),
+            Err(err) => eprintln!("Failed to read {}: {}", path, err),
+        },
+        (":reset", "") => *engine = crate::engine(),
+        (":time", code) if !code.is_empty() => {
+            let start = Instant::now();
+            let result = engine.eval(code);
+            let elapsed = start.elapsed();
+
+            print_result(result, code);
+            println!("took {:?}", elapsed);
+        }
+        (":help", "") => println!("{}", HELP),
+        _ if COMMANDS.contains(&command) => eprintln!("Wrong arguments for {}, see :help", command),
+        _ => eprintln!("Unknown command {}, see :help", command),
+    }
+}
+
+/// Prints the value of a successful run unless it is `Unit`, or else its
+/// errors.
+fn print_result(result: Result<raga::Val, raga::Error>, input: &str) {
+    match result {
+        Ok(raga::Val::Unit) => {}
+        Ok(val) => println!("{}", val),
+        Err(err) => report(err, input),
+    }
+}
+
+/// Prints the errors of a failed run, pointing into `input`.
+fn report(err: raga::Error, input: &str) {
+    match err {
+        raga::Error::Parse(errors) => {
+            for error in errors {
+                let (line, col) = error.span.line_col(input);
+                eprintln!("Parse error at {}:{}: {}", line, col, error);
+            }
+        }
+        raga::Error::Resolve(errors) | raga::Error::Type(errors) => {
+            for error in errors {
+                let (line, col) = error.span.line_col(input);
+                eprintln!("Error at {}:{}: {}", line, col, error);
+            }
+        }
+        raga::Error::Eval(err) => eprintln!("Evaluation error: {}", err),
+        err => eprintln!("Error: {}", err),
+    }
+}
+
+fn print_env(env: &raga::Env) {
+    let mut named: Vec<_> = env.iter().collect();
+    named.sort_by_key(|(name, _)| *name);
+
+    for (name, info) in named {
+        match info {
+            raga::NamedInfo::Binding(val) => println!("{} = {}", name, val),
+            raga::NamedInfo::Func { params, signature, body } => {
+                print!("fn {}", name);
+                for (idx, param) in params.iter().enumerate() {
+                    print!(" {}", param);
+                    if let Some(ty) = signature.as_ref().and_then(|signature| signature.params[idx].as_ref()) {
+                        print!(": {}", ty);
+                    }
+                }
+                if let Some(ty) = signature.as_ref().and_then(|signature| signature.ret.as_ref()) {
+                    print!(" -> {}", ty);
+                }
+                println!(" => {}", body);
+            }
+            raga::NamedInfo::NativeFunc(_) => println!("fn {} <native>", name),
+            raga::NamedInfo::Struct(def) => println!("{}", def),
+            raga::NamedInfo::Enum(def) => println!("{}", def),
+        }
+    }
+}
+
+fn update_names(editor: &mut Editor<ReplHelper, DefaultHistory>, env: &raga::Env) {
+    if let Some(helper) = editor.helper_mut() {
+        helper.names = env.names().into_iter().map(str::to_string).collect();
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("raga").join("history"))
+}
+
+/// Completes `:commands`, keywords and the names of bindings and functions
+/// defined in the session so far.
+#[derive(Default)]
+struct ReplHelper {
+    names: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let prefix_len: usize = line[..pos]
+            .chars()
+            .rev()
+            .take_while(|c| c.is_alphanumeric())
+            .map(char::len_utf8)
+            .sum();
+        let start = pos - prefix_len;
+        let prefix = &line[start..pos];
+
+        if line[..pos].starts_with(':') && start == 1 {
+            let candidates = COMMANDS
+                .iter()
+                .filter(|command| command[1..].starts_with(prefix))
+                .map(|command| command.to_string())
+                .collect();
+            return Ok((0, candidates));
+        }
+
+        if prefix.is_empty() {
+            return Ok((start, Vec::new()));
+        }
+
+        let mut candidates: Vec<String> = raga::lexer::KEYWORDS
+            .iter()
+            .copied()
+            .chain(self.names.iter().map(String::as_str))
+            .filter(|candidate| candidate.starts_with(prefix))
+            .map(str::to_string)
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
@@ -0,0 +1,87 @@
+use std::fs;
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+pub(crate) fn run_file(path: &str) -> ExitCode {
+    match fs::read_to_string(path) {
+        Ok(input) => run(path, &input),
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            ExitCode::from(crate::EXIT_IO_ERROR)
+        }
+    }
+}
+
+/// Reports every syntax, name and type error in the script at `path`
+/// without running it.
+pub(crate) fn check_file(path: &str) -> ExitCode {
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            return ExitCode::from(crate::EXIT_IO_ERROR);
+        }
+    };
+
+    let parse = raga::parse(&input);
+    let env = raga::Env::default();
+    let (kind, errors) = if !parse.errors().is_empty() {
+        ("parse error", parse.errors().to_vec())
+    } else {
+        let mut errors = parse.name_errors(&env);
+        errors.extend(parse.type_errors(&env));
+        errors.sort_by_key(|error| error.span.start);
+        ("error", errors)
+    };
+
+    if errors.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    for error in errors {
+        let (line, col) = error.span.line_col(&input);
+        eprintln!("{}:{}:{}: {}: {}", path, line, col, kind, error);
+    }
+    ExitCode::from(crate::EXIT_PARSE_ERROR)
+}
+
+pub(crate) fn run_stdin() -> ExitCode {
+    let mut input = String::new();
+    match io::stdin().read_to_string(&mut input) {
+        Ok(_) => run("<stdin>", &input),
+        Err(err) => {
+            eprintln!("failed to read stdin: {}", err);
+            ExitCode::from(crate::EXIT_IO_ERROR)
+        }
+    }
+}
+
+/// Evaluates `input` as a whole program, printing its value unless it is
+/// `Unit`. Errors are reported against `name`, the source the input came
+/// from.
+pub(crate) fn run(name: &str, input: &str) -> ExitCode {
+    match crate::engine().eval(input) {
+        Ok(raga::Val::Unit) => ExitCode::SUCCESS,
+        Ok(val) => {
+            println!("{}", val);
+            ExitCode::SUCCESS
+        }
+        Err(raga::Error::Parse(errors)) => {
+            for error in errors {
+                let (line, col) = error.span.line_col(input);
+                eprintln!("{}:{}:{}: parse error: {}", name, line, col, error);
+            }
+            ExitCode::from(crate::EXIT_PARSE_ERROR)
+        }
+        Err(raga::Error::Resolve(errors)) => {
+            for error in errors {
+                let (line, col) = error.span.line_col(input);
+                eprintln!("{}:{}:{}: error: {}", name, line, col, error);
+            }
+            ExitCode::from(crate::EXIT_PARSE_ERROR)
+        }
+        Err(err) => {
+            eprintln!("{}: {}", name, err);
+            ExitCode::from(crate::EXIT_RUNTIME_ERROR)
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Defining and calling functions with large bodies, and cloning large
+//! values. None of these should get slower as the body or value grows, as
+//! both are shared rather than copied.
+//!
+//! Run with `cargo bench -p raga`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use raga::{Env, Val};
+
+const SIZES: [usize; 3] = [10, 100, 1000];
+
+/// An expression adding up `terms` uses of `x`.
+fn sum_of_x(terms: usize) -> String {
+    vec!["x"; terms].join(" + ")
+}
+
+fn define_function(c: &mut Criterion) {
+    let mut group = c.benchmark_group("define function");
+    for terms in SIZES {
+        let parse = raga::parse(&format!("fn f x => {}", sum_of_x(terms)));
+        group.bench_with_input(BenchmarkId::from_parameter(terms), &parse, |b, parse| {
+            let mut env = Env::default();
+            b.iter(|| parse.eval(&mut env).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Every call of `outer` defines `inner` anew, whose body is the one that
+/// grows.
+fn call_function_defining_another(c: &mut Criterion) {
+    let mut group = c.benchmark_group("call function defining another");
+    for terms in SIZES {
+        let mut env = Env::default();
+        raga::parse(&format!("fn outer => {{\n    fn inner x => {}\n    1\n}}", sum_of_x(terms)))
+            .eval(&mut env)
+            .unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(terms), &env, |b, env| {
+            b.iter(|| env.call_func("outer", vec![]).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn clone_list(c: &mut Criterion) {
+    let mut group = c.benchmark_group("clone list");
+    for len in [10, 1000, 100_000] {
+        let list = Val::from(vec![1; len]);
+        group.bench_with_input(BenchmarkId::from_parameter(len), &list, |b, list| {
+            b.iter(|| black_box(list).clone())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, define_function, call_function_defining_another, clone_list);
+criterion_main!(benches);
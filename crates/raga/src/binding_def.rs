@@ -1,37 +1,77 @@
+use crate::enum_def;
 use crate::env::Env;
+use crate::error::EvalError;
 use crate::expr::Expr;
-use crate::utils;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::type_ann::{Subject, TypeAnn};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct BindingDef {
     pub name: String,
+    pub ty: Option<TypeAnn>,
     pub val: Expr,
+    /// The slot the resolver gave the binding in its scope. Bindings
+    /// without one, such as globals, are stored by name.
+    pub slot: Option<usize>,
 }
 
 impl BindingDef {
     pub fn new(s: &str) -> Result<(&str, Self), String> {
-        let s = utils::tag("let", s)?;
-        let (s, _) = utils::extract_whitespace(s)?;
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
 
-        let (s, name) = utils::extract_ident(s)?;
-        let (s, _) = utils::skip_whitespace(s);
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::BindingDef);
+        p.expect(TokenKind::LetKw, "let")?;
+        let name = p.expect(TokenKind::Ident, "identifier")?;
+        enum_def::check_def_name(p, name);
+        if p.at(TokenKind::Colon) {
+            TypeAnn::parse(p, TokenKind::Colon, ":")?;
+        }
+        p.expect(TokenKind::Equals, "=")?;
+        Expr::parse(p)?;
+        p.finish_node();
+        Ok(())
+    }
 
-        let s = utils::tag("=", s)?;
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let name = node.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+        let ty = node
+            .children()
+            .find(|child| child.kind() == NodeKind::TypeAnn)
+            .and_then(|ty| TypeAnn::lower(&ty));
+        let val = node.children().find(|child| child.kind() != NodeKind::TypeAnn)?;
 
-        let (s, _) = utils::skip_whitespace(s);
-        let (s, val) = Expr::new(s)?;
-        Ok((
-            s,
-            Self {
-                name: name.to_string(),
-                val,
-            },
-        ))
+        Some(Self {
+            name: name.text().to_string(),
+            ty,
+            val: Expr::lower(&val)?,
+            slot: None,
+        })
     }
 
-    pub fn eval(&self, env: &mut Env) -> Result<(), String> {
-        env.store_binding(self.name.clone(), self.val.eval(env)?);
-        Ok(())
+    pub fn eval(&self, env: &mut Env) -> Result<(), EvalError> {
+        let val = self.val.eval(env)?;
+        if let Some(ty) = &self.ty {
+            ty.check(&val, || Subject::Binding(self.name.clone()))?;
+        }
+        match self.slot {
+            Some(slot) => env.store_local(slot, val),
+            None => env.bind(self.name.clone(), val),
+        }
+    }
+}
+
+impl fmt::Display for BindingDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "let {}", self.name)?;
+        if let Some(ty) = &self.ty {
+            write!(f, ": {}", ty)?;
+        }
+        write!(f, " = {}", self.val)
     }
 }
 
@@ -48,11 +88,13 @@ mod tests {
                 "",
                 BindingDef {
                     name: "a".to_string(),
+                    ty: None,
                     val: Expr::Operation {
                         lhs: Box::new(Expr::Number(Number(10))),
                         rhs: Box::new(Expr::Number(Number(2))),
                         op: Op::Div
-                    }
+                    },
+                    slot: None,
                 }
             ))
         )
@@ -62,7 +104,7 @@ mod tests {
     fn can_not_parse_binding_def_without_space_after_let() {
         assert_eq!(
             BindingDef::new("letaa= 10 / 2"),
-            Err("expected a space".to_string())
+            Err("expected let".to_string())
         )
     }
 
@@ -74,9 +116,34 @@ mod tests {
                 "",
                 BindingDef {
                     name: "aa".to_string(),
-                    val: Expr::Number(Number(20))
+                    ty: None,
+                    val: Expr::Number(Number(20)),
+                    slot: None,
                 }
             ))
         )
     }
+
+    #[test]
+    fn parse_annotated_binding_def() {
+        let (_, binding_def) = BindingDef::new("let x: Int = 5").unwrap();
+        assert_eq!(
+            binding_def.ty,
+            Some(TypeAnn {
+                name: "Int".to_string(),
+            })
+        );
+        assert_eq!(binding_def.to_string(), "let x: Int = 5");
+    }
+
+    #[test]
+    fn refuse_value_of_other_type_than_annotated() {
+        let (_, binding_def) = BindingDef::new("let x: Int = {}").unwrap();
+        let mut env = Env::default();
+        assert_eq!(
+            binding_def.eval(&mut env),
+            Err("expected Int for binding 'x', found Unit".into())
+        );
+        assert!(env.get_binding("x").is_err());
+    }
 }
@@ -0,0 +1,129 @@
+use crate::error::EvalError;
+use crate::Limits;
+use std::cell::Cell;
+use std::time::Instant;
+
+/// How many ticks pass between two looks at the clock, as reading it is far
+/// more expensive than evaluating a node.
+const CLOCK_CHECK_INTERVAL: u32 = 64;
+
+/// The work an evaluation may still do: fuel is used up one unit per
+/// evaluated statement or expression, the deadline is when evaluation has to
+/// stop regardless of fuel, and memory is charged for the values the script
+/// holds on to. `None` means unlimited.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Budget {
+    fuel: Cell<Option<u64>>,
+    deadline: Option<Instant>,
+    ticks: Cell<u32>,
+    max_memory: Option<usize>,
+    memory_used: Cell<usize>,
+}
+
+impl Budget {
+    /// A full budget according to `limits`, with the deadline counted from
+    /// now.
+    pub(crate) fn new(limits: &Limits) -> Self {
+        Self {
+            fuel: Cell::new(limits.fuel),
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            ticks: Cell::new(0),
+            max_memory: limits.max_memory,
+            memory_used: Cell::new(0),
+        }
+    }
+
+    /// Spends one unit of fuel, failing once there is none left or the
+    /// deadline has passed.
+    pub(crate) fn tick(&self) -> Result<(), EvalError> {
+        if let Some(fuel) = self.fuel.get() {
+            if fuel == 0 {
+                return Err(EvalError::OutOfFuel);
+            }
+            self.fuel.set(Some(fuel - 1));
+        }
+
+        if let Some(deadline) = self.deadline {
+            let ticks = self.ticks.get();
+            self.ticks.set(ticks.wrapping_add(1));
+            if ticks.is_multiple_of(CLOCK_CHECK_INTERVAL) && Instant::now() >= deadline {
+                return Err(EvalError::Timeout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records that `bytes` more are held, failing if that takes the total
+    /// past the memory limit. Memory is never given back during a single
+    /// evaluation, so this limits how much an evaluation allocates, which
+    /// overestimates what it holds but cannot be cheated. Callers charge
+    /// only memory that is not shared with values already held, so cloning
+    /// a value is not counted as allocating its contents again.
+    pub(crate) fn charge(&self, bytes: usize) -> Result<(), EvalError> {
+        let used = self.memory_used.get().saturating_add(bytes);
+        self.memory_used.set(used);
+
+        match self.max_memory {
+            Some(max) if used > max => Err(EvalError::MemoryLimit),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn tick_forever_without_limits() {
+        let budget = Budget::default();
+        for _ in 0..1000 {
+            assert_eq!(budget.tick(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn run_out_of_fuel() {
+        let budget = Budget::new(&Limits {
+            fuel: Some(2),
+            ..Limits::default()
+        });
+        assert_eq!(budget.tick(), Ok(()));
+        assert_eq!(budget.tick(), Ok(()));
+        assert_eq!(budget.tick(), Err(EvalError::OutOfFuel));
+        assert_eq!(budget.tick(), Err(EvalError::OutOfFuel));
+    }
+
+    #[test]
+    fn stop_at_deadline() {
+        let budget = Budget::new(&Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        });
+        assert_eq!(budget.tick(), Err(EvalError::Timeout));
+    }
+
+    #[test]
+    fn keep_going_before_deadline() {
+        let budget = Budget::new(&Limits {
+            timeout: Some(Duration::from_secs(60)),
+            ..Limits::default()
+        });
+        for _ in 0..1000 {
+            assert_eq!(budget.tick(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn exceed_memory_limit() {
+        let budget = Budget::new(&Limits {
+            max_memory: Some(100),
+            ..Limits::default()
+        });
+        assert_eq!(budget.charge(60), Ok(()));
+        assert_eq!(budget.charge(40), Ok(()));
+        assert_eq!(budget.charge(1), Err(EvalError::MemoryLimit));
+    }
+}
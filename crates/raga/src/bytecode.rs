@@ -0,0 +1,156 @@
+//! The instructions the compiler produces and the virtual machine runs.
+//!
+//! Each function gets a frame of local slots, holding its parameters first
+//! and then the bindings of its blocks. Bindings and functions defined at
+//! the top level of a program are globals and live in the [`Env`] instead,
+//! so that they outlive the program and can be shared with the
+//! tree-walking evaluator.
+//!
+//! [`Env`]: crate::Env
+
+use crate::enum_def::EnumDef;
+use crate::expr::Op;
+use crate::func_def::FuncDef;
+use crate::pattern::Pattern;
+use crate::struct_def::StructDef;
+use crate::type_ann::{Subject, TypeAnn};
+use crate::val::Val;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Instr {
+    /// Pushes the constant at the given index of the chunk's pool.
+    Const(usize),
+    Unit,
+    Pop,
+    LoadLocal(usize),
+    /// Pops a value into a slot of the current frame.
+    StoreLocal(usize),
+    /// Pushes a slot of the frame of an enclosing function, found by
+    /// following `depth` static links.
+    LoadOuter { depth: usize, slot: usize },
+    /// Pushes the global binding with the name at the given index.
+    LoadGlobal(usize),
+    /// Pops a value into the global binding with the name at the given
+    /// index.
+    StoreGlobal(usize),
+    /// Defines the global function at the given index of
+    /// [`Program::globals`].
+    DefineGlobal(usize),
+    /// Defines the global struct at the given index of the chunk's structs.
+    DefineStruct(usize),
+    /// Defines the global enum at the given index of the chunk's enums.
+    DefineEnum(usize),
+    /// Fails unless the value on top of the stack, which is left there, has
+    /// the type of the annotation at the given index of the chunk's checks.
+    CheckType(usize),
+    /// Pops two operands and pushes the result of applying the operator.
+    Op(Op),
+    /// Calls a function of the current program with the `argc` values on
+    /// top of the stack. Its frame is linked to the frame of the function it
+    /// was defined in, `depth` static links up from the current one.
+    Call { func: usize, depth: usize, argc: usize },
+    /// Calls the global or native function with the name at index `name`.
+    CallGlobal { name: usize, argc: usize },
+    GetField(usize),
+    CallMethod { name: usize, argc: usize },
+    /// Builds a value of the struct literal at the given index of the
+    /// chunk's literals, popping its base if it has one and then the values
+    /// of its fields.
+    MakeStruct(usize),
+    /// Builds the variant at the given index of the chunk's variants from
+    /// the values on top of the stack.
+    MakeVariant(usize),
+    /// Pops the given number of values into a tuple.
+    MakeTuple(usize),
+    /// Matches the value on top of the stack, which is left there, against
+    /// the pattern at index `pattern` of the chunk's patterns. Its bindings
+    /// are stored in their slots if it matches, and otherwise execution
+    /// continues at `otherwise`.
+    Match { pattern: usize, otherwise: usize },
+    /// Pops the value of a match guard, continuing at the given instruction
+    /// if it is false.
+    Guard(usize),
+    Jump(usize),
+    /// Replaces the `Some` or `Ok` on top of the stack with the value it
+    /// holds and continues at the given instruction. A `None` or `Err` is
+    /// left there for the instructions that follow to return.
+    Unwrap(usize),
+    /// Fails because no arm matches the value on top of the stack.
+    NoMatch,
+    /// Fails because the name at the given index refers to a function where
+    /// a binding was expected.
+    NotABinding(usize),
+    /// Fails because the name at the given index refers to a binding where a
+    /// function was expected.
+    NotAFunction(usize),
+    /// Fails because the name at the given index refers to a function where
+    /// a struct was expected.
+    NotAStruct(usize),
+    /// Fails because the name at the given index refers to something other
+    /// than an enum where an enum was expected.
+    NotAnEnum(usize),
+    /// Pops the result of the current function and returns it to the
+    /// caller.
+    Return,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Chunk {
+    pub(crate) code: Vec<Instr>,
+    pub(crate) consts: Vec<Val>,
+    pub(crate) names: Vec<String>,
+    pub(crate) checks: Vec<(TypeAnn, Subject)>,
+    pub(crate) structs: Vec<Rc<StructDef>>,
+    pub(crate) literals: Vec<StructShape>,
+    pub(crate) enums: Vec<Rc<EnumDef>>,
+    pub(crate) variants: Vec<VariantShape>,
+    pub(crate) patterns: Vec<Pattern>,
+}
+
+/// What [`Instr::MakeStruct`] needs to know about a struct literal.
+#[derive(Debug, PartialEq)]
+pub(crate) struct StructShape {
+    pub(crate) name: String,
+    /// The struct, if it is defined in a function. Global structs are
+    /// looked up by name when the literal is evaluated.
+    pub(crate) def: Option<Rc<StructDef>>,
+    pub(crate) fields: Vec<String>,
+    pub(crate) has_base: bool,
+}
+
+/// What [`Instr::MakeVariant`] needs to know about a variant.
+#[derive(Debug, PartialEq)]
+pub(crate) struct VariantShape {
+    pub(crate) enum_name: String,
+    pub(crate) variant: String,
+    /// The enum, if it is defined in a function, like
+    /// [`StructShape::def`].
+    pub(crate) def: Option<Rc<EnumDef>>,
+    pub(crate) argc: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Function {
+    pub(crate) name: String,
+    pub(crate) arity: usize,
+    pub(crate) num_slots: usize,
+    pub(crate) chunk: Chunk,
+}
+
+/// A compiled program or global function. Its entry point is the function
+/// at index 0, and [`Instr::Call`] refers to the functions nested in it by
+/// their index.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Program {
+    pub(crate) funcs: Vec<Function>,
+    pub(crate) globals: Vec<GlobalFunc>,
+}
+
+/// A function defined at the top level of a program, kept both as source
+/// for the environment and compiled for the virtual machine.
+#[derive(Debug, PartialEq)]
+pub(crate) struct GlobalFunc {
+    pub(crate) def: FuncDef,
+    pub(crate) program: Rc<Program>,
+}
@@ -0,0 +1,697 @@
+//! Compiles statements into bytecode for the virtual machine.
+//!
+//! Names are resolved while compiling: a name refers to the closest binding
+//! or function defined before it in an enclosing block or function, and
+//! only names that are not found there are looked up among the globals at
+//! runtime. Function bodies can also call functions defined later in an
+//! enclosing block, as the resolver allows.
+
+use crate::bytecode::{Chunk, Function, GlobalFunc, Instr, Program, StructShape, VariantShape};
+use crate::enum_def::EnumDef;
+use crate::expr::{Expr, Match, StructLit, Try, VariantLit};
+use crate::func_def::{FuncDef, Signature};
+use crate::stmt::Stmt;
+use crate::struct_def::StructDef;
+use crate::type_ann::{Subject, TypeAnn};
+use crate::val::Val;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Compiles a whole program. Its top-level bindings and functions become
+/// globals.
+pub(crate) fn compile(stmts: &[Stmt]) -> Program {
+    let mut compiler = Compiler::new(true);
+    let idx = compiler.reserve_func();
+    compiler.start_func(idx, "<script>", &[]);
+    compiler.stmts(stmts);
+    compiler.finish_func();
+    compiler.finish()
+}
+
+/// Compiles a global function, so that it can be called on its own.
+pub(crate) fn compile_func(name: &str, params: &[String], signature: Option<&Signature>, body: &Stmt) -> Program {
+    let mut compiler = Compiler::new(false);
+    let idx = compiler.reserve_func();
+    compiler.func(idx, name, params, signature, body);
+    compiler.finish()
+}
+
+#[derive(Debug, Clone)]
+enum Symbol {
+    Local(usize),
+    Func(usize),
+    Struct(Rc<StructDef>),
+    Enum(Rc<EnumDef>),
+}
+
+/// The names a block or function defines so far.
+#[derive(Default)]
+struct Scope {
+    names: HashMap<String, Symbol>,
+    /// Indices reserved for functions the block defines further on, which
+    /// the bodies of functions in the block may already call.
+    funcs: HashMap<String, usize>,
+}
+
+/// A function that is being compiled, with the names defined by each of
+/// the blocks it is in the middle of.
+struct FuncState {
+    idx: usize,
+    name: String,
+    arity: usize,
+    scopes: Vec<Scope>,
+    num_slots: usize,
+    /// The type the result of the function is annotated with, which is
+    /// checked wherever it returns.
+    ret: Option<TypeAnn>,
+    chunk: Chunk,
+}
+
+struct Compiler {
+    /// Whether the top-level scope of the outermost function defines
+    /// globals rather than locals.
+    top_level_globals: bool,
+    funcs: Vec<Option<Function>>,
+    globals: Vec<GlobalFunc>,
+    states: Vec<FuncState>,
+}
+
+impl Compiler {
+    fn new(top_level_globals: bool) -> Self {
+        Self {
+            top_level_globals,
+            funcs: Vec::new(),
+            globals: Vec::new(),
+            states: Vec::new(),
+        }
+    }
+
+    fn finish(self) -> Program {
+        Program {
+            funcs: self.funcs.into_iter().map(|func| func.expect("function was compiled")).collect(),
+            globals: self.globals,
+        }
+    }
+
+    /// Reserves the index of a function that is compiled later.
+    fn reserve_func(&mut self) -> usize {
+        self.funcs.push(None);
+        self.funcs.len() - 1
+    }
+
+    /// Starts compiling the function at the reserved `idx`, giving its
+    /// parameters the first slots.
+    fn start_func(&mut self, idx: usize, name: &str, params: &[String]) {
+        let mut state = FuncState {
+            idx,
+            name: name.to_string(),
+            arity: params.len(),
+            scopes: vec![Scope::default()],
+            num_slots: 0,
+            ret: None,
+            chunk: Chunk::default(),
+        };
+        for param in params {
+            declare_local(&mut state, param);
+        }
+
+        self.states.push(state);
+    }
+
+    /// Compiles a function, checking the annotated types of its arguments
+    /// on entry and of its result on return.
+    fn func(&mut self, idx: usize, name: &str, params: &[String], signature: Option<&Signature>, body: &Stmt) {
+        self.start_func(idx, name, params);
+        self.state().ret = signature.and_then(|signature| signature.ret.clone());
+
+        if let Some(signature) = signature {
+            for (slot, (param, ty)) in params.iter().zip(&signature.params).enumerate() {
+                if let Some(ty) = ty {
+                    self.emit(Instr::LoadLocal(slot));
+                    self.check_type(ty, Subject::Param {
+                        func: name.to_string(),
+                        param: param.clone(),
+                    });
+                    self.emit(Instr::Pop);
+                }
+            }
+        }
+
+        self.stmts(std::slice::from_ref(body));
+        self.finish_func();
+    }
+
+    fn finish_func(&mut self) {
+        self.emit_return();
+        let state = self.states.pop().expect("a function is being compiled");
+        self.funcs[state.idx] = Some(Function {
+            name: state.name,
+            arity: state.arity,
+            num_slots: state.num_slots,
+            chunk: state.chunk,
+        });
+    }
+
+    fn state(&mut self) -> &mut FuncState {
+        self.states.last_mut().expect("a function is being compiled")
+    }
+
+    fn at_global_scope(&self) -> bool {
+        self.top_level_globals && self.states.len() == 1 && self.states[0].scopes.len() == 1
+    }
+
+    fn emit(&mut self, instr: Instr) {
+        self.state().chunk.code.push(instr);
+    }
+
+    fn constant(&mut self, val: Val) -> usize {
+        let consts = &mut self.state().chunk.consts;
+        consts.push(val);
+        consts.len() - 1
+    }
+
+    fn check_type(&mut self, ty: &TypeAnn, subject: Subject) {
+        let checks = &mut self.state().chunk.checks;
+        checks.push((ty.clone(), subject));
+        let idx = checks.len() - 1;
+        self.emit(Instr::CheckType(idx));
+    }
+
+    fn name(&mut self, name: &str) -> usize {
+        let names = &mut self.state().chunk.names;
+        match names.iter().position(|existing| existing == name) {
+            Some(idx) => idx,
+            None => {
+                names.push(name.to_string());
+                names.len() - 1
+            }
+        }
+    }
+
+    /// Finds what `name` refers to, along with how many functions out from
+    /// the current one it was defined in.
+    fn resolve(&self, name: &str) -> Option<(usize, Symbol)> {
+        self.states.iter().rev().enumerate().find_map(|(depth, state)| {
+            state
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| match scope.names.get(name) {
+                    Some(symbol) => Some(symbol.clone()),
+                    None if depth > 0 => scope.funcs.get(name).map(|&idx| Symbol::Func(idx)),
+                    None => None,
+                })
+                .map(|symbol| (depth, symbol))
+        })
+    }
+
+    /// Compiles a sequence of statements, leaving the value of the last one
+    /// on the stack.
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        if stmts.is_empty() {
+            self.emit(Instr::Unit);
+            return;
+        }
+
+        for (idx, stmt) in stmts.iter().enumerate() {
+            let is_last = idx == stmts.len() - 1;
+            match stmt {
+                Stmt::BindingDef(binding_def) => {
+                    self.expr(&binding_def.val);
+                    if let Some(ty) = &binding_def.ty {
+                        self.check_type(ty, Subject::Binding(binding_def.name.clone()));
+                    }
+                    self.store(&binding_def.name);
+                }
+                Stmt::FuncDef(func_def) => self.func_def(func_def),
+                Stmt::StructDef(struct_def) => self.struct_def(struct_def),
+                Stmt::EnumDef(enum_def) => self.enum_def(enum_def),
+                Stmt::Expr(expr) => self.expr(expr),
+            }
+
+            // Every statement but an expression leaves nothing on the stack,
+            // and its value is `Unit`.
+            match (stmt, is_last) {
+                (Stmt::Expr(_), false) => self.emit(Instr::Pop),
+                (Stmt::Expr(_), true) => {}
+                (_, false) => {}
+                (_, true) => self.emit(Instr::Unit),
+            }
+        }
+    }
+
+    fn store(&mut self, name: &str) {
+        if self.at_global_scope() {
+            let name = self.name(name);
+            self.emit(Instr::StoreGlobal(name));
+            return;
+        }
+
+        let slot = declare_local(self.state(), name);
+        self.emit(Instr::StoreLocal(slot));
+    }
+
+    fn func_def(&mut self, func_def: &FuncDef) {
+        if self.at_global_scope() {
+            let program = compile_func(
+                &func_def.name,
+                &func_def.params,
+                func_def.signature.as_deref(),
+                &func_def.body,
+            );
+            self.globals.push(GlobalFunc {
+                def: func_def.clone(),
+                program: Rc::new(program),
+            });
+            let idx = self.globals.len() - 1;
+            self.emit(Instr::DefineGlobal(idx));
+            return;
+        }
+
+        // The function is in scope inside its own body, so it can call
+        // itself. Functions defined before it in the block may have been
+        // compiled to call it already.
+        let scope = self.state().scopes.last_mut().expect("function has a scope");
+        let idx = match scope.funcs.remove(&func_def.name) {
+            Some(idx) => idx,
+            None => self.reserve_func(),
+        };
+        let scope = self.state().scopes.last_mut().expect("function has a scope");
+        scope.names.insert(func_def.name.clone(), Symbol::Func(idx));
+
+        self.func(
+            idx,
+            &func_def.name,
+            &func_def.params,
+            func_def.signature.as_deref(),
+            &func_def.body,
+        );
+    }
+
+    /// Defines a global struct when the program runs, while a struct
+    /// defined in a function is only known to the compiler.
+    fn struct_def(&mut self, struct_def: &StructDef) {
+        let struct_def = Rc::new(struct_def.clone());
+        if self.at_global_scope() {
+            let structs = &mut self.state().chunk.structs;
+            structs.push(struct_def);
+            let idx = structs.len() - 1;
+            self.emit(Instr::DefineStruct(idx));
+            return;
+        }
+
+        let scope = self.state().scopes.last_mut().expect("function has a scope");
+        scope.names.insert(struct_def.name.clone(), Symbol::Struct(struct_def));
+    }
+
+    fn struct_lit(&mut self, struct_lit: &StructLit) {
+        for (_, val) in &struct_lit.fields {
+            self.expr(val);
+        }
+        if let Some(base) = &struct_lit.base {
+            self.expr(base);
+        }
+
+        let def = match self.resolve(&struct_lit.name) {
+            Some((_, Symbol::Struct(def))) => Some(def),
+            Some((_, Symbol::Func(_) | Symbol::Enum(_))) => {
+                let name = self.name(&struct_lit.name);
+                self.emit(Instr::NotAStruct(name));
+                return;
+            }
+            // Bindings are not kept by name, so they do not hide a global
+            // struct.
+            Some((_, Symbol::Local(_))) | None => None,
+        };
+        let literals = &mut self.state().chunk.literals;
+        literals.push(StructShape {
+            name: struct_lit.name.clone(),
+            def,
+            fields: struct_lit.fields.iter().map(|(name, _)| name.clone()).collect(),
+            has_base: struct_lit.base.is_some(),
+        });
+        let idx = literals.len() - 1;
+        self.emit(Instr::MakeStruct(idx));
+    }
+
+    /// Defines a global enum when the program runs, like
+    /// [`Compiler::struct_def`].
+    fn enum_def(&mut self, enum_def: &EnumDef) {
+        let enum_def = Rc::new(enum_def.clone());
+        if self.at_global_scope() {
+            let enums = &mut self.state().chunk.enums;
+            enums.push(enum_def);
+            let idx = enums.len() - 1;
+            self.emit(Instr::DefineEnum(idx));
+            return;
+        }
+
+        let scope = self.state().scopes.last_mut().expect("function has a scope");
+        scope.names.insert(enum_def.name.clone(), Symbol::Enum(enum_def));
+    }
+
+    fn variant_lit(&mut self, variant_lit: &VariantLit) {
+        for arg in &variant_lit.args {
+            self.expr(arg);
+        }
+
+        let def = match self.resolve(&variant_lit.enum_name) {
+            Some((_, Symbol::Enum(def))) => Some(def),
+            Some((_, Symbol::Func(_) | Symbol::Struct(_))) => {
+                let name = self.name(&variant_lit.enum_name);
+                self.emit(Instr::NotAnEnum(name));
+                return;
+            }
+            Some((_, Symbol::Local(_))) | None => None,
+        };
+        let variants = &mut self.state().chunk.variants;
+        variants.push(VariantShape {
+            enum_name: variant_lit.enum_name.clone(),
+            variant: variant_lit.variant.clone(),
+            def,
+            argc: variant_lit.args.len(),
+        });
+        let idx = variants.len() - 1;
+        self.emit(Instr::MakeVariant(idx));
+    }
+
+    /// Compiles each arm into a test of its pattern and guard, which jumps
+    /// to the next arm if either fails, followed by its body. The scrutinee
+    /// stays on the stack until an arm is taken.
+    fn match_expr(&mut self, match_expr: &Match) {
+        self.expr(&match_expr.scrutinee);
+
+        let mut ends = Vec::new();
+        for arm in &match_expr.arms {
+            self.state().scopes.push(Scope::default());
+            let mut pattern = arm.pattern.clone();
+            for binding in pattern.bindings_mut() {
+                binding.slot = Some(declare_local(self.state(), &binding.name));
+            }
+            let patterns = &mut self.state().chunk.patterns;
+            patterns.push(pattern);
+            let pattern = patterns.len() - 1;
+
+            let mut tests = vec![self.emit_jump(Instr::Match { pattern, otherwise: 0 })];
+            if let Some(guard) = &arm.guard {
+                self.expr(guard);
+                tests.push(self.emit_jump(Instr::Guard(0)));
+            }
+            self.emit(Instr::Pop);
+            self.expr(&arm.body);
+            ends.push(self.emit_jump(Instr::Jump(0)));
+            self.state().scopes.pop();
+
+            for test in tests {
+                self.patch_jump(test);
+            }
+        }
+
+        self.emit(Instr::NoMatch);
+        for end in ends {
+            self.patch_jump(end);
+        }
+    }
+
+    /// Compiles `value?` into a test that unwraps a `Some` or `Ok` and skips
+    /// past a return of anything else.
+    fn try_expr(&mut self, try_expr: &Try) {
+        self.expr(&try_expr.inner);
+        let unwrap = self.emit_jump(Instr::Unwrap(0));
+        self.emit_return();
+        self.patch_jump(unwrap);
+    }
+
+    /// Returns the value on top of the stack, checking it against the
+    /// annotated result type first.
+    fn emit_return(&mut self) {
+        let state = self.state();
+        if let Some(ty) = state.ret.clone() {
+            let name = state.name.clone();
+            self.check_type(&ty, Subject::Result(name));
+        }
+        self.emit(Instr::Return);
+    }
+
+    /// Emits a jump, returning where it is so that its target can be
+    /// patched once it is known.
+    fn emit_jump(&mut self, instr: Instr) -> usize {
+        self.emit(instr);
+        self.state().chunk.code.len() - 1
+    }
+
+    /// Makes the jump at `idx` continue at the next instruction emitted.
+    fn patch_jump(&mut self, idx: usize) {
+        let code = &mut self.state().chunk.code;
+        let here = code.len();
+        match &mut code[idx] {
+            Instr::Match { otherwise: target, .. }
+            | Instr::Guard(target)
+            | Instr::Jump(target)
+            | Instr::Unwrap(target) => *target = here,
+            instr => unreachable!("{:?} is not a jump", instr),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(number) => {
+                let idx = self.constant(Val::Number(number.0));
+                self.emit(Instr::Const(idx));
+            }
+            Expr::Bool(b) => {
+                let idx = self.constant(Val::Bool(b.0));
+                self.emit(Instr::Const(idx));
+            }
+            Expr::Operation { lhs, rhs, op } => {
+                self.expr(lhs);
+                self.expr(rhs);
+                self.emit(Instr::Op(op.clone()));
+            }
+            Expr::BindingUsage(binding_usage) => {
+                let instr = match self.resolve(&binding_usage.name) {
+                    Some((0, Symbol::Local(slot))) => Instr::LoadLocal(slot),
+                    Some((depth, Symbol::Local(slot))) => Instr::LoadOuter { depth, slot },
+                    Some((_, Symbol::Func(_) | Symbol::Struct(_) | Symbol::Enum(_))) => {
+                        Instr::NotABinding(self.name(&binding_usage.name))
+                    }
+                    None => Instr::LoadGlobal(self.name(&binding_usage.name)),
+                };
+                self.emit(instr);
+            }
+            Expr::Block(block) => {
+                if block.stmts.is_empty() {
+                    self.emit(Instr::Unit);
+                    return;
+                }
+
+                let mut scope = Scope::default();
+                for stmt in &block.stmts {
+                    if let Stmt::FuncDef(func_def) = stmt {
+                        if !scope.funcs.contains_key(&func_def.name) {
+                            scope.funcs.insert(func_def.name.clone(), self.reserve_func());
+                        }
+                    }
+                }
+                self.state().scopes.push(scope);
+                self.stmts(&block.stmts);
+                self.state().scopes.pop();
+            }
+            Expr::FuncCall(func_call) => {
+                for arg in &func_call.args {
+                    self.expr(arg);
+                }
+
+                let argc = func_call.args.len();
+                let instr = match self.resolve(&func_call.callee) {
+                    Some((depth, Symbol::Func(func))) => Instr::Call { func, depth, argc },
+                    Some((_, Symbol::Local(_) | Symbol::Struct(_) | Symbol::Enum(_))) => {
+                        Instr::NotAFunction(self.name(&func_call.callee))
+                    }
+                    None => Instr::CallGlobal {
+                        name: self.name(&func_call.callee),
+                        argc,
+                    },
+                };
+                self.emit(instr);
+            }
+            Expr::FieldAccess(field_access) => {
+                self.expr(&field_access.target);
+                let name = self.name(&field_access.field);
+                self.emit(Instr::GetField(name));
+            }
+            Expr::MethodCall(method_call) => {
+                self.expr(&method_call.target);
+                for arg in &method_call.args {
+                    self.expr(arg);
+                }
+                let name = self.name(&method_call.method);
+                self.emit(Instr::CallMethod {
+                    name,
+                    argc: method_call.args.len(),
+                });
+            }
+            Expr::StructLit(struct_lit) => self.struct_lit(struct_lit),
+            Expr::VariantLit(variant_lit) => self.variant_lit(variant_lit),
+            Expr::Tuple(tuple) => {
+                for item in &tuple.items {
+                    self.expr(item);
+                }
+                self.emit(Instr::MakeTuple(tuple.items.len()));
+            }
+            Expr::Match(match_expr) => self.match_expr(match_expr),
+            Expr::Try(try_expr) => self.try_expr(try_expr),
+        }
+    }
+}
+
+/// Gives `name` a slot in the innermost scope of `state`. Defining a name
+/// again in the same scope reuses its slot, just like it replaces the
+/// binding in an environment.
+fn declare_local(state: &mut FuncState, name: &str) -> usize {
+    let scope = state.scopes.last_mut().expect("function has a scope");
+    if let Some(Symbol::Local(slot)) = scope.names.get(name) {
+        return *slot;
+    }
+
+    let slot = state.num_slots;
+    state.num_slots += 1;
+    scope.names.insert(name.to_string(), Symbol::Local(slot));
+    slot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Op;
+
+    fn compile_src(src: &str) -> Program {
+        let parse = crate::parse(src);
+        assert!(parse.errors().is_empty(), "{:?}", parse.errors());
+        compile(parse.stmts())
+    }
+
+    #[test]
+    fn compile_arithmetic() {
+        let program = compile_src("1 + 2 * 3");
+        let chunk = &program.funcs[0].chunk;
+
+        assert_eq!(
+            chunk.code,
+            vec![
+                Instr::Const(0),
+                Instr::Const(1),
+                Instr::Const(2),
+                Instr::Op(Op::Mul),
+                Instr::Op(Op::Add),
+                Instr::Return,
+            ]
+        );
+        assert_eq!(chunk.consts, vec![Val::Number(1), Val::Number(2), Val::Number(3)]);
+    }
+
+    #[test]
+    fn compile_top_level_bindings_as_globals() {
+        let program = compile_src("let a = 1\na");
+        let chunk = &program.funcs[0].chunk;
+
+        assert_eq!(
+            chunk.code,
+            vec![Instr::Const(0), Instr::StoreGlobal(0), Instr::LoadGlobal(0), Instr::Return]
+        );
+        assert_eq!(chunk.names, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn compile_block_bindings_into_slots() {
+        let program = compile_src("{\n    let a = 1\n    let b = a\n    let a = b\n    a\n}");
+        let script = &program.funcs[0];
+
+        assert_eq!(script.num_slots, 2);
+        assert_eq!(
+            script.chunk.code,
+            vec![
+                Instr::Const(0),
+                Instr::StoreLocal(0),
+                Instr::LoadLocal(0),
+                Instr::StoreLocal(1),
+                Instr::LoadLocal(1),
+                Instr::StoreLocal(0),
+                Instr::LoadLocal(0),
+                Instr::Return,
+            ]
+        );
+    }
+
+    #[test]
+    fn compile_nested_functions() {
+        let program = compile_src("fn outer x => {\n    fn inner => x\n    inner()\n}");
+        assert_eq!(program.funcs.len(), 1);
+        assert_eq!(program.funcs[0].chunk.code, vec![Instr::DefineGlobal(0), Instr::Unit, Instr::Return]);
+
+        let outer = &program.globals[0].program;
+        assert_eq!(outer.funcs[0].arity, 1);
+        assert_eq!(
+            outer.funcs[0].chunk.code,
+            vec![Instr::Call { func: 1, depth: 0, argc: 0 }, Instr::Return]
+        );
+        assert_eq!(
+            outer.funcs[1].chunk.code,
+            vec![Instr::LoadOuter { depth: 1, slot: 0 }, Instr::Return]
+        );
+    }
+
+    #[test]
+    fn compile_match_arms_into_tests_and_jumps() {
+        let program = compile_src("match x {\n    (a, 1) if t => a,\n    _ => 2,\n}");
+        let script = &program.funcs[0];
+
+        assert_eq!(script.num_slots, 1);
+        assert_eq!(
+            script.chunk.code,
+            vec![
+                Instr::LoadGlobal(0),
+                Instr::Match { pattern: 0, otherwise: 7 },
+                Instr::LoadGlobal(1),
+                Instr::Guard(7),
+                Instr::Pop,
+                Instr::LoadLocal(0),
+                Instr::Jump(12),
+                Instr::Match { pattern: 1, otherwise: 11 },
+                Instr::Pop,
+                Instr::Const(0),
+                Instr::Jump(12),
+                Instr::NoMatch,
+                Instr::Return,
+            ]
+        );
+    }
+
+    #[test]
+    fn compile_try_into_unwrap_and_return() {
+        let program = compile_src("fn f x -> Option => Some(x?)");
+        let func = &program.globals[0].program.funcs[0];
+
+        assert_eq!(
+            func.chunk.code,
+            vec![
+                Instr::LoadLocal(0),
+                Instr::Unwrap(4),
+                Instr::CheckType(0),
+                Instr::Return,
+                Instr::MakeVariant(0),
+                Instr::CheckType(1),
+                Instr::Return,
+            ]
+        );
+    }
+
+    #[test]
+    fn compile_unknown_names_as_global_lookups() {
+        let program = compile_src("f(x)");
+        assert_eq!(
+            program.funcs[0].chunk.code,
+            vec![Instr::LoadGlobal(0), Instr::CallGlobal { name: 1, argc: 1 }, Instr::Return]
+        );
+    }
+}
@@ -0,0 +1,762 @@
+//! Embedding API.
+//!
+//! An [`Engine`] owns the global environment that code evaluated through it
+//! shares, so bindings and functions defined by one call to
+//! [`Engine::eval`] are visible to the next.
+
+use crate::budget::Budget;
+use crate::bytecode::Program;
+use crate::compiler;
+use crate::vm::Vm;
+use crate::{Env, Error, IntoNativeFunc, NativeFunc, Val};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Bounds on the work an [`Engine`] accepts. `None` means unlimited.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits {
+    /// Longest source text, in bytes, that `eval` will parse.
+    pub max_source_len: Option<usize>,
+    /// Statements and expressions a single call to `eval`, `call` or
+    /// `call_fn` may evaluate, or instructions it may run with
+    /// [`Backend::Vm`], before failing with
+    /// [`EvalError::OutOfFuel`](crate::EvalError::OutOfFuel).
+    pub fuel: Option<u64>,
+    /// How long a single call to `eval`, `call` or `call_fn` may run before
+    /// failing with [`EvalError::Timeout`](crate::EvalError::Timeout). Time
+    /// spent inside native functions is only noticed once they return.
+    pub timeout: Option<Duration>,
+    /// Approximate bytes of values a single call to `eval`, `call` or
+    /// `call_fn` may allocate, counting global bindings, before failing with
+    /// [`EvalError::MemoryLimit`](crate::EvalError::MemoryLimit). Memory is
+    /// not given back when values are dropped, but cloning a value only
+    /// counts the clone, not the contents it shares.
+    pub max_memory: Option<usize>,
+}
+
+/// How an [`Engine`] runs code. Both give the same results, so they can be
+/// checked against each other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Evaluates the syntax tree directly.
+    #[default]
+    TreeWalker,
+    /// Compiles to bytecode first and runs that on a virtual machine.
+    Vm,
+}
+
+#[derive(Debug, Default)]
+pub struct Engine {
+    env: Env<'static>,
+    limits: Limits,
+    backend: Backend,
+    optimize: bool,
+    type_check: bool,
+    /// Bytecode of the global functions in `env`, by name. Every change to a
+    /// global has to remove its entry, so that it never goes stale.
+    compiled: HashMap<String, Rc<Program>>,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limits(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Whether code is optimized before it is run, see [`Parse::optimize`].
+    /// Off by default.
+    ///
+    /// [`Parse::optimize`]: crate::Parse::optimize
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    pub fn optimize(&self) -> bool {
+        self.optimize
+    }
+
+    /// Whether code is type checked before it is run, see
+    /// [`Parse::type_errors`]. Off by default.
+    ///
+    /// [`Parse::type_errors`]: crate::Parse::type_errors
+    pub fn set_type_check(&mut self, type_check: bool) {
+        self.type_check = type_check;
+    }
+
+    pub fn type_check(&self) -> bool {
+        self.type_check
+    }
+
+    /// Parses and evaluates `source`, returning the value of its last
+    /// statement. Nothing is evaluated if there are syntax errors, names
+    /// that are not defined anywhere or, with type checking on, type errors.
+    /// The bindings and functions it defines only become globals if
+    /// evaluation succeeds, so a failed or interrupted script leaves no
+    /// trace.
+    pub fn eval(&mut self, source: &str) -> Result<Val, Error> {
+        self.eval_keeping(source, true)
+    }
+
+    /// Like [`Engine::eval`], but the bindings and functions `source`
+    /// defines are forgotten even if evaluation succeeds.
+    pub fn eval_scratch(&mut self, source: &str) -> Result<Val, Error> {
+        self.eval_keeping(source, false)
+    }
+
+    fn eval_keeping(&mut self, source: &str, keep: bool) -> Result<Val, Error> {
+        if let Some(max) = self.limits.max_source_len {
+            if source.len() > max {
+                return Err(Error::SourceTooLong {
+                    len: source.len(),
+                    max,
+                });
+            }
+        }
+
+        let mut parse = crate::parse(source);
+        if !parse.errors().is_empty() {
+            return Err(Error::Parse(parse.errors().to_vec()));
+        }
+        let name_errors = parse.name_errors(&self.env);
+        if !name_errors.is_empty() {
+            return Err(Error::Resolve(name_errors));
+        }
+        if self.type_check {
+            let type_errors = parse.type_errors(&self.env);
+            if !type_errors.is_empty() {
+                return Err(Error::Type(type_errors));
+            }
+        }
+        if self.optimize {
+            parse.optimize();
+        }
+
+        self.start_budget()?;
+        let mut scratch = self.env.create_child();
+        let result = match self.backend {
+            Backend::TreeWalker => parse.eval(&mut scratch).map(|val| (val, HashMap::new())),
+            Backend::Vm => {
+                let program = Rc::new(compiler::compile(parse.stmts()));
+                let mut vm = Vm::new(&mut scratch, &self.compiled);
+                vm.run(program).map(|val| (val, vm.into_compiled()))
+            }
+        };
+        let named = scratch.take_named();
+
+        let (val, compiled) = result?;
+        if !keep {
+            return Ok(val);
+        }
+        for name in named.keys() {
+            self.compiled.remove(name);
+        }
+        self.env.extend(named);
+        self.compiled.extend(compiled);
+        Ok(val)
+    }
+
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Val, Error> {
+        let source = fs::read_to_string(path)?;
+        self.eval(&source)
+    }
+
+    /// Defines a global binding, replacing any binding or function of the
+    /// same name.
+    pub fn set_global(&mut self, name: impl Into<String>, val: Val) {
+        let name = name.into();
+        self.compiled.remove(&name);
+        self.env.store_binding(name, val);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Val> {
+        self.env.get_binding(name).ok()
+    }
+
+    /// Makes a Rust function callable from scripts. It receives the
+    /// arguments as they are and has to check them itself.
+    pub fn register_fn(
+        &mut self,
+        name: impl Into<String>,
+        func: impl Fn(&[Val]) -> Result<Val, Error> + 'static,
+    ) {
+        let name = name.into();
+        self.compiled.remove(&name);
+        self.env.store_native_func(name, NativeFunc::new(func));
+    }
+
+    /// Like [`Engine::register_fn`], but for a function with typed
+    /// parameters, such as `|a: i32, b: i32| a + b`. Scripts calling it with
+    /// the wrong number or types of arguments get an evaluation error.
+    pub fn register_typed_fn<Args>(&mut self, name: impl Into<String>, func: impl IntoNativeFunc<Args>) {
+        let name = name.into();
+        self.compiled.remove(&name);
+        self.env.store_native_func(name, func.into_native_func());
+    }
+
+    /// Calls a function defined by previously evaluated code.
+    pub fn call(&mut self, name: &str, args: Vec<Val>) -> Result<Val, Error> {
+        self.start_budget()?;
+        match self.backend {
+            Backend::TreeWalker => self.env.call_func(name, args).map_err(Error::Eval),
+            Backend::Vm => {
+                let mut vm = Vm::new(&mut self.env, &self.compiled);
+                let result = vm.call(name, args);
+                let compiled = vm.into_compiled();
+                self.compiled.extend(compiled);
+                Ok(result?)
+            }
+        }
+    }
+
+    /// Calls a function defined by previously evaluated code with Rust
+    /// arguments, given as a tuple, and converts its result to `R`.
+    pub fn call_fn<R>(&mut self, name: &str, args: impl FuncArgs) -> Result<R, Error>
+    where
+        R: TryFrom<Val>,
+        R::Error: fmt::Display,
+    {
+        let result = self.call(name, args.into_args())?;
+        R::try_from(result)
+            .map_err(|err| Error::Eval(format!("result of '{}': {}", name, err).into()))
+    }
+
+    pub fn env(&self) -> &Env<'static> {
+        &self.env
+    }
+
+    /// Gives the global environment a full budget according to the limits,
+    /// charged up front for the global bindings.
+    fn start_budget(&mut self) -> Result<(), Error> {
+        let budget = Budget::new(&self.limits);
+        budget.charge(self.env.binding_size())?;
+        self.env.set_budget(budget);
+        Ok(())
+    }
+}
+
+/// Arguments for [`Engine::call_fn`]: tuples of values convertible into
+/// [`Val`], or a `Vec<Val>` that is passed on as it is.
+pub trait FuncArgs {
+    fn into_args(self) -> Vec<Val>;
+}
+
+impl FuncArgs for Vec<Val> {
+    fn into_args(self) -> Vec<Val> {
+        self
+    }
+}
+
+macro_rules! impl_func_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: Into<Val>),*> FuncArgs for ($($arg,)*) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Val> {
+                let ($($arg,)*) = self;
+                vec![$($arg.into()),*]
+            }
+        }
+    };
+}
+
+impl_func_args!();
+impl_func_args!(A);
+impl_func_args!(A, B);
+impl_func_args!(A, B, C);
+impl_func_args!(A, B, C, D);
+impl_func_args!(A, B, C, D, E);
+impl_func_args!(A, B, C, D, E, G);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EvalError;
+
+    #[test]
+    fn keep_globals_between_evals() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.eval("let a = 2").unwrap(), Val::Unit);
+        assert_eq!(engine.eval("a * 21").unwrap(), Val::Number(42));
+    }
+
+    #[test]
+    fn set_and_get_globals() {
+        let mut engine = Engine::new();
+        engine.set_global("limit", Val::Number(10));
+        engine.eval("let doubled = limit * 2").unwrap();
+
+        assert_eq!(engine.get_global("doubled"), Some(Val::Number(20)));
+        assert_eq!(engine.get_global("missing"), None);
+    }
+
+    #[test]
+    fn call_script_function() {
+        let mut engine = Engine::new();
+        engine.eval("fn add x y => x + y").unwrap();
+
+        assert_eq!(
+            engine.call("add", vec![Val::Number(1), Val::Number(2)]).unwrap(),
+            Val::Number(3)
+        );
+        assert!(matches!(engine.call("sub", vec![]), Err(Error::Eval(_))));
+    }
+
+    #[test]
+    fn call_native_functions_from_script() {
+        let mut engine = Engine::new();
+        engine.register_fn("count", |args| Ok(Val::Number(args.len() as i32)));
+        engine.register_typed_fn("add", |a: i32, b: i32| a + b);
+
+        assert_eq!(engine.eval("add(count(1, 2, 3), 4)").unwrap(), Val::Number(7));
+        assert_eq!(
+            engine.eval("add(1)").unwrap_err().to_string(),
+            "evaluation error: function 'add' takes 2 argument(s) but 1 were given"
+        );
+        assert_eq!(
+            engine.eval("add(1, {})").unwrap_err().to_string(),
+            "evaluation error: add: argument 2: expected Number but found Unit"
+        );
+    }
+
+    #[test]
+    fn call_script_functions_from_script() {
+        let mut engine = Engine::new();
+        engine.eval("fn square x => x * x\nfn sumOfSquares a b => square(a) + square(b)").unwrap();
+        assert_eq!(engine.eval("sumOfSquares(3, 4)").unwrap(), Val::Number(25));
+    }
+
+    #[test]
+    fn call_script_function_with_typed_args() {
+        let mut engine = Engine::new();
+        engine.eval("fn score x y => x * 10 + y\nfn nothing => {}").unwrap();
+
+        assert_eq!(engine.call_fn::<i32>("score", (4, 2)).unwrap(), 42);
+        assert_eq!(
+            engine.call_fn::<Val>("score", vec![Val::Number(1), Val::Number(0)]).unwrap(),
+            Val::Number(10)
+        );
+        engine.call_fn::<()>("nothing", ()).unwrap();
+    }
+
+    #[test]
+    fn report_result_of_wrong_type() {
+        let mut engine = Engine::new();
+        engine.eval("fn nothing => {}").unwrap();
+
+        assert_eq!(
+            engine.call_fn::<i32>("nothing", ()).unwrap_err().to_string(),
+            "evaluation error: result of 'nothing': expected Number but found Unit"
+        );
+    }
+
+    #[test]
+    fn refuse_code_with_syntax_errors() {
+        let mut engine = Engine::new();
+        match engine.eval("let a = 1\nlet = 2") {
+            Err(Error::Parse(errors)) => assert_eq!(errors.len(), 1),
+            other => panic!("expected parse error, got {:?}", other),
+        }
+        assert_eq!(engine.get_global("a"), None);
+    }
+
+    #[test]
+    fn refuse_code_with_undefined_names() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut engine = Engine::new();
+            engine.set_backend(backend);
+            engine.set_global("limit", Val::Number(10));
+            engine.register_fn("log", |_| Ok(Val::Unit));
+
+            assert_eq!(
+                engine.eval("let a = 1\nlog(limit + a)\nmissing + b").unwrap_err().to_string(),
+                "resolve error: undefined binding 'missing', undefined binding 'b'"
+            );
+            assert_eq!(engine.get_global("a"), None);
+
+            assert_eq!(
+                engine.eval("fn f => 1\nlet g = 2\nlog(f + 1)\ng()").unwrap_err().to_string(),
+                "resolve error: 'f' is a function, not a binding, 'g' is a binding, not a function"
+            );
+            assert_eq!(engine.get_global("g"), None);
+
+            engine.eval("fn f => g()\nfn g => limit").unwrap();
+            assert_eq!(engine.eval("f()").unwrap(), Val::Number(10));
+        }
+    }
+
+    #[test]
+    fn refuse_ill_typed_code_only_with_type_checking() {
+        let src = "fn nothing => {}\nlet a = 1\n1 + nothing()";
+        let mut engine = Engine::new();
+        assert_eq!(
+            engine.eval(src).unwrap_err().to_string(),
+            "evaluation error: cannot evaluate operation whose left-hand side and right-hand side are not both numbers"
+        );
+
+        engine.set_type_check(true);
+        assert_eq!(
+            engine.eval(src).unwrap_err().to_string(),
+            "type error: expected Number, found Unit"
+        );
+        assert_eq!(engine.get_global("a"), None);
+    }
+
+    #[test]
+    fn refuse_source_over_limit() {
+        let mut engine = Engine::with_limits(Limits {
+            max_source_len: Some(5),
+            ..Limits::default()
+        });
+        assert_eq!(engine.eval("1 + 2").unwrap(), Val::Number(3));
+        assert!(matches!(
+            engine.eval("1 + 23"),
+            Err(Error::SourceTooLong { len: 6, max: 5 })
+        ));
+    }
+
+    #[test]
+    fn run_out_of_fuel() {
+        let mut engine = Engine::with_limits(Limits {
+            fuel: Some(100),
+            ..Limits::default()
+        });
+        engine.eval("fn fib n => fib(n - 1) + fib(n - 2)").unwrap();
+
+        assert!(matches!(
+            engine.eval("fib(30)"),
+            Err(Error::Eval(EvalError::OutOfFuel))
+        ));
+        assert!(matches!(
+            engine.call("fib", vec![Val::Number(30)]),
+            Err(Error::Eval(EvalError::OutOfFuel))
+        ));
+    }
+
+    #[test]
+    fn refill_fuel_for_every_eval() {
+        let mut engine = Engine::with_limits(Limits {
+            fuel: Some(10),
+            ..Limits::default()
+        });
+        for _ in 0..5 {
+            assert_eq!(engine.eval("1 + 2 + 3").unwrap(), Val::Number(6));
+        }
+    }
+
+    #[test]
+    fn stop_at_timeout() {
+        let mut engine = Engine::with_limits(Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        });
+        assert!(matches!(
+            engine.eval("1 + 2"),
+            Err(Error::Eval(EvalError::Timeout))
+        ));
+    }
+
+    #[test]
+    fn keep_globals_unchanged_after_failed_eval() {
+        let mut engine = Engine::with_limits(Limits {
+            fuel: Some(20),
+            ..Limits::default()
+        });
+        engine.eval("let a = 1").unwrap();
+
+        assert!(engine.eval("let a = 2\nlet b = 3\n1+1+1+1+1+1+1+1+1+1+1+1+1+1+1+1+1+1").is_err());
+        assert_eq!(engine.get_global("a"), Some(Val::Number(1)));
+        assert_eq!(engine.get_global("b"), None);
+
+        assert!(engine.eval("let c = 4\nc()").is_err());
+        assert_eq!(engine.get_global("c"), None);
+    }
+
+    #[test]
+    fn forget_globals_of_scratch_eval() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut engine = Engine::new();
+            engine.set_backend(backend);
+            engine.eval("let a = 1\nfn f => a").unwrap();
+
+            assert_eq!(engine.eval_scratch("let a = 2\nfn f => 3\nf() + a").unwrap(), Val::Number(5));
+            assert_eq!(engine.get_global("a"), Some(Val::Number(1)));
+            assert_eq!(engine.call("f", vec![]).unwrap(), Val::Number(1));
+            assert_eq!(engine.eval("f()").unwrap(), Val::Number(1));
+        }
+    }
+
+    #[test]
+    fn exceed_memory_limit() {
+        let mut engine = Engine::with_limits(Limits {
+            max_memory: Some(4096),
+            ..Limits::default()
+        });
+        engine.register_typed_fn("repeat", |n: i32| "x".repeat(n as usize));
+
+        engine.eval("let a = repeat(1000)").unwrap();
+        assert!(matches!(
+            engine.eval("let b = repeat(1000)\nlet c = repeat(1000)\nlet d = repeat(1000)"),
+            Err(Error::Eval(EvalError::MemoryLimit))
+        ));
+        assert_eq!(engine.get_global("b"), None);
+        assert!(matches!(
+            engine.eval("repeat(5000)"),
+            Err(Error::Eval(EvalError::MemoryLimit))
+        ));
+    }
+
+    #[test]
+    fn charge_clones_without_their_shared_contents() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut engine = Engine::with_limits(Limits {
+                max_memory: Some(8192),
+                ..Limits::default()
+            });
+            engine.set_backend(backend);
+            engine.set_global("big", Val::Str("x".repeat(2000).into()));
+            engine.eval("fn keep s n => match n {\n    0 => s,\n    n => keep(s, n - 1),\n}").unwrap();
+
+            assert_eq!(engine.eval("keep(big, 50)").unwrap(), engine.get_global("big").unwrap());
+            let src = (0..50).map(|idx| format!("let c{} = big", idx)).collect::<Vec<_>>().join("\n");
+            assert!(engine.eval(&src).is_ok());
+        }
+    }
+
+    #[test]
+    fn count_globals_against_memory_limit() {
+        let mut engine = Engine::with_limits(Limits {
+            max_memory: Some(1000),
+            ..Limits::default()
+        });
+        engine.set_global("big", Val::Str("x".repeat(2000).into()));
+
+        assert!(matches!(
+            engine.eval("1"),
+            Err(Error::Eval(EvalError::MemoryLimit))
+        ));
+    }
+
+    #[test]
+    fn run_code_on_vm() {
+        let mut engine = Engine::with_limits(Limits {
+            fuel: Some(200),
+            ..Limits::default()
+        });
+        engine.set_backend(Backend::Vm);
+        engine.register_typed_fn("add", |a: i32, b: i32| a + b);
+        engine.eval("let base = 10\nfn score x => add(x, base)").unwrap();
+
+        assert_eq!(engine.eval("score(5)").unwrap(), Val::Number(15));
+        assert_eq!(engine.call_fn::<i32>("score", (1,)).unwrap(), 11);
+        assert!(matches!(
+            engine.eval("fn fib n => fib(n - 1) + fib(n - 2)\nfib(20)"),
+            Err(Error::Eval(EvalError::OutOfFuel))
+        ));
+        assert!(matches!(
+            engine.call("fib", vec![]),
+            Err(Error::Eval(EvalError::Runtime(_)))
+        ));
+    }
+
+    #[test]
+    fn check_annotated_types_on_both_backends() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut engine = Engine::new();
+            engine.set_backend(backend);
+            engine.eval("fn add a: Int b: Int -> Int => a + b").unwrap();
+
+            assert_eq!(engine.eval("add(1, 2)").unwrap(), Val::Number(3));
+            assert_eq!(
+                engine.eval("add(1, {})").unwrap_err().to_string(),
+                "evaluation error: expected Int for parameter 'b' of 'add', found Unit"
+            );
+            assert_eq!(
+                engine.eval("let x: Unit = add(1, 2)").unwrap_err().to_string(),
+                "evaluation error: expected Unit for binding 'x', found Number"
+            );
+            assert_eq!(
+                engine
+                    .eval("{\n    fn nothing -> Number => {}\n    nothing()\n}")
+                    .unwrap_err()
+                    .to_string(),
+                "evaluation error: expected Number for result of 'nothing', found Unit"
+            );
+        }
+    }
+
+    #[test]
+    fn build_and_update_structs_on_both_backends() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut engine = Engine::new();
+            engine.set_backend(backend);
+            engine.eval("struct Point { x: Int, y: Int }
+let p = Point { y: 2, x: 1 }").unwrap();
+
+            assert_eq!(engine.eval("p.x + p.y").unwrap(), Val::Number(3));
+            let moved = engine.eval("Point { x: 5, ..p }").unwrap();
+            assert_eq!(moved.to_string(), "Point { x: 5, y: 2 }");
+            assert_eq!(moved, engine.eval("Point { x: 5, y: 2 }").unwrap());
+            assert_ne!(moved, engine.eval("p").unwrap());
+
+            // Structs defined in functions are only known there.
+            let src = "fn pair a b => {\n    struct Pair { a, b }\n    Pair { a: a, b: b }\n}\npair(1, {})";
+            assert_eq!(engine.eval(src).unwrap().to_string(), "Pair { a: 1, b: Unit }");
+            assert_eq!(
+                engine.eval("fn f => Pair { a: 1, b: 2 }\nf()").unwrap_err().to_string(),
+                "evaluation error: struct with name 'Pair' does not exist"
+            );
+
+            assert_eq!(
+                engine.eval("Point { x: 1 }").unwrap_err().to_string(),
+                "evaluation error: missing field 'y' in Point"
+            );
+
+            // A capitalized name at the end of a line is not a struct literal.
+            assert_eq!(engine.eval("let B = 1\nlet a = B\n{}\na").unwrap(), Val::Number(1));
+            assert_eq!(
+                engine.eval("Point { x: 1, y: {} }").unwrap_err().to_string(),
+                "evaluation error: expected Int for field 'y' of 'Point', found Unit"
+            );
+            assert_eq!(
+                engine.eval("p.z").unwrap_err().to_string(),
+                "evaluation error: Point has no field 'z'"
+            );
+        }
+    }
+
+    #[test]
+    fn handle_recoverable_failures_on_both_backends() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut engine = Engine::new();
+            engine.set_backend(backend);
+            engine.register_typed_fn("half", |n: i32| {
+                if n % 2 == 0 { Ok(n / 2) } else { Err(format!("{n} is odd")) }
+            });
+            engine.eval("fn quarter n => Ok(half(half(n)?)?)").unwrap();
+
+            assert_eq!(engine.eval("quarter(12)").unwrap().to_string(), "Ok(3)");
+            assert_eq!(engine.eval("quarter(6)").unwrap().to_string(), "Err(\"3 is odd\")");
+            assert_eq!(
+                engine
+                    .eval("match quarter(10) {\n    Ok(n) => n,\n    Err(_) => 0,\n}")
+                    .unwrap(),
+                Val::Number(0)
+            );
+
+            // At the top level, `?` ends the whole program with the failure.
+            assert_eq!(engine.eval("Some(1)? + None?").unwrap().to_string(), "None");
+            assert_eq!(engine.eval("Some(1)? + 1").unwrap(), Val::Number(2));
+            assert_eq!(engine.eval("let a = 1\nNone?\nlet b = 2").unwrap().to_string(), "None");
+            assert_eq!(engine.eval("a").unwrap(), Val::Number(1));
+            assert!(engine.eval("b").is_err());
+            assert_eq!(
+                engine.eval("1?").unwrap_err().to_string(),
+                "evaluation error: expected Option or Result for ?, found Number"
+            );
+        }
+    }
+
+    #[test]
+    fn compare_in_match_guards_on_both_backends() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut engine = Engine::new();
+            engine.set_backend(backend);
+            engine.set_type_check(true);
+            let src = "enum Shape { Circle(r: Int), Rect(w: Int, h: Int) }
+fn area s => match s {
+    Shape::Circle(r) => 3 * r * r,
+    Shape::Rect(w, h) if w > 0 => w * h,
+    _ => 0,
+}";
+            engine.eval(src).unwrap();
+
+            assert_eq!(engine.eval("area(Shape::Rect(2, 3))").unwrap(), Val::Number(6));
+            assert_eq!(engine.eval("area(Shape::Rect(-2, 3))").unwrap(), Val::Number(0));
+            assert_eq!(engine.eval("1 + 1 == 2").unwrap(), Val::Bool(true));
+            assert_eq!(engine.eval("Shape::Circle(1) != Shape::Circle(1)").unwrap(), Val::Bool(false));
+            assert_eq!(engine.eval("3 <= 2 == false").unwrap(), Val::Bool(true));
+            assert_eq!(
+                engine.eval("match 1 { n if n => n }").unwrap_err().to_string(),
+                "type error: expected Bool, found Number"
+            );
+        }
+    }
+
+    #[test]
+    fn call_later_block_functions_on_both_backends() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut engine = Engine::new();
+            engine.set_backend(backend);
+
+            assert_eq!(engine.eval("{\n    fn g => h()\n    fn h => 3\n    g()\n}").unwrap(), Val::Number(3));
+            let src = "{
+    fn even n => match n {
+        0 => 1,
+        n => odd(n - 1),
+    }
+    fn odd n => match n {
+        0 => 0,
+        n => even(n - 1),
+    }
+    even(10) + odd(7)
+}";
+            assert_eq!(engine.eval(src).unwrap(), Val::Number(2));
+            engine.set_type_check(true);
+            assert_eq!(engine.eval(src).unwrap(), Val::Number(2));
+            assert_eq!(
+                engine.eval("{\n    h()\n    fn h => 3\n}").unwrap_err().to_string(),
+                "resolve error: undefined function 'h'"
+            );
+        }
+    }
+
+    #[test]
+    fn switch_backends_sharing_globals() {
+        let mut engine = Engine::new();
+        engine.set_backend(Backend::Vm);
+        engine.eval("fn f => 1").unwrap();
+        assert_eq!(engine.eval("f()").unwrap(), Val::Number(1));
+
+        engine.set_backend(Backend::TreeWalker);
+        engine.eval("fn f => 2").unwrap();
+        engine.set_backend(Backend::Vm);
+        assert_eq!(engine.eval("f()").unwrap(), Val::Number(2));
+
+        engine.set_global("f", Val::Number(3));
+        assert_eq!(
+            engine.eval("f()").unwrap_err().to_string(),
+            "evaluation error: function with name 'f' does not exist"
+        );
+        assert_eq!(engine.eval("f").unwrap(), Val::Number(3));
+    }
+
+    #[test]
+    fn report_missing_file() {
+        let mut engine = Engine::new();
+        assert!(matches!(
+            engine.eval_file("/nonexistent/script.raga"),
+            Err(Error::Io(_))
+        ));
+    }
+}
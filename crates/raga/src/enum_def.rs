@@ -0,0 +1,320 @@
+use crate::env::Env;
+use crate::error::EvalError;
+use crate::lexer::{Token, TokenKind};
+use crate::parser::{self, ParseError, Parser};
+use crate::struct_def::FieldDef;
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::type_ann::Subject;
+use crate::val::{EnumVal, Val};
+use std::fmt;
+use std::rc::Rc;
+
+thread_local! {
+    /// The enums every program can use without defining them.
+    static PRELUDE: [Rc<EnumDef>; 2] = [
+        "enum Option { Some(value), None }",
+        "enum Result { Ok(value), Err(error) }",
+    ]
+    .map(|src| Rc::new(EnumDef::new(src).expect("prelude enums parse").1));
+}
+
+/// The built-in enum `name`, unless it is not one.
+pub(crate) fn prelude(name: &str) -> Option<Rc<EnumDef>> {
+    PRELUDE.with(|prelude| prelude.iter().find(|def| def.name == name).cloned())
+}
+
+/// The built-in enum that `variant` belongs to. Its variants can be written
+/// without the enum name, as in `Some(1)` or `None`.
+pub(crate) fn prelude_enum(variant: &str) -> Option<&'static str> {
+    match variant {
+        "Some" | "None" => Some("Option"),
+        "Ok" | "Err" => Some("Result"),
+        _ => None,
+    }
+}
+
+/// Reports a binding, function or parameter named after a built-in
+/// variant, since the name would always be taken for the variant.
+pub(crate) fn check_def_name(p: &mut Parser, name: Token) {
+    if let Some(enum_name) = prelude_enum(name.text) {
+        p.report(ParseError {
+            message: format!("'{}' is a variant of {} and cannot be defined", name.text, enum_name),
+            span: name.span,
+        });
+    }
+}
+
+/// The enum and variant names of a path like `Shape::Rect` in a variant
+/// literal or pattern. A variant of a built-in enum may leave out the enum
+/// name, which is then filled in.
+pub(crate) fn lower_path(node: &SyntaxNode) -> Option<(String, String)> {
+    let mut idents = node
+        .child_tokens()
+        .filter(|token| token.kind() == TokenKind::Ident)
+        .map(|token| token.text().to_string());
+    let first = idents.next()?;
+    match idents.next() {
+        Some(variant) => Some((first, variant)),
+        None => Some((prelude_enum(&first)?.to_string(), first)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDef {
+    pub name: String,
+    pub variants: Vec<VariantDef>,
+}
+
+/// A variant of an enum, like `Rect(w, h)`. Its fields are named for
+/// documentation and error messages, but given and matched by position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantDef {
+    pub name: String,
+    pub fields: Vec<FieldDef>,
+}
+
+impl EnumDef {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::EnumDef);
+        p.expect(TokenKind::EnumKw, "enum")?;
+        p.expect(TokenKind::Ident, "identifier")?;
+        p.expect(TokenKind::LBrace, "{")?;
+
+        let mut names = Vec::new();
+        while p.at(TokenKind::Ident) {
+            p.start_node(NodeKind::VariantDef);
+            let name = p.bump();
+            if p.at(TokenKind::LParen) {
+                p.bump();
+                FieldDef::parse_list(p)?;
+                p.expect(TokenKind::RParen, ", or )")?;
+            }
+            p.finish_node();
+
+            if names.contains(&name.text) {
+                p.report(ParseError {
+                    message: format!("variant '{}' is declared twice", name.text),
+                    span: name.span,
+                });
+            }
+            names.push(name.text);
+
+            if !p.at(TokenKind::Comma) {
+                break;
+            }
+            p.bump();
+        }
+
+        p.expect(TokenKind::RBrace, ", or }")?;
+        p.finish_node();
+        Ok(())
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let name = node.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+        let variants = node
+            .children()
+            .filter(|child| child.kind() == NodeKind::VariantDef)
+            .map(|variant| {
+                let name = variant.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+                Some(VariantDef {
+                    name: name.text().to_string(),
+                    fields: FieldDef::lower_list(&variant)?,
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            name: name.text().to_string(),
+            variants,
+        })
+    }
+
+    pub(crate) fn eval(&self, env: &mut Env) -> Result<(), EvalError> {
+        env.store_enum(self.clone());
+        Ok(())
+    }
+
+    fn variant(&self, name: &str) -> Option<&VariantDef> {
+        self.variants.iter().find(|variant| variant.name == name)
+    }
+
+    /// Builds the variant `variant` of the enum, holding `fields`.
+    pub(crate) fn construct(&self, variant: &str, fields: Vec<Val>) -> Result<Val, EvalError> {
+        let def = self
+            .variant(variant)
+            .ok_or_else(|| format!("{} has no variant '{}'", self.name, variant))?;
+
+        let path = format!("{}::{}", self.name, variant);
+        if def.fields.len() != fields.len() {
+            return Err(format!(
+                "variant '{}' takes {} value(s) but {} were given",
+                path,
+                def.fields.len(),
+                fields.len()
+            )
+            .into());
+        }
+        for (field, val) in def.fields.iter().zip(&fields) {
+            if let Some(ty) = &field.ty {
+                ty.check(val, || Subject::Field {
+                    name: path.clone(),
+                    field: field.name.clone(),
+                })?;
+            }
+        }
+
+        Ok(Val::Enum(Rc::new(EnumVal {
+            name: self.name.clone(),
+            variant: variant.to_string(),
+            fields,
+        })))
+    }
+}
+
+impl fmt::Display for EnumDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.variants.is_empty() {
+            return write!(f, "enum {} {{}}", self.name);
+        }
+
+        write!(f, "enum {} {{ ", self.name)?;
+        for (idx, variant) in self.variants.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", variant)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+/// Prints `Rect(w, h)`, or just the name of a variant without fields.
+impl fmt::Display for VariantDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+
+        write!(f, "(")?;
+        for (idx, field) in self.fields.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", field)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::type_ann::TypeAnn;
+
+    fn shape() -> EnumDef {
+        EnumDef::new("enum Shape { Circle(r: Int), Rect(w, h), Empty }").unwrap().1
+    }
+
+    #[test]
+    fn parse_enum_def() {
+        assert_eq!(
+            shape().variants,
+            vec![
+                VariantDef {
+                    name: "Circle".to_string(),
+                    fields: vec![FieldDef {
+                        name: "r".to_string(),
+                        ty: Some(TypeAnn {
+                            name: "Int".to_string(),
+                        }),
+                    }],
+                },
+                VariantDef {
+                    name: "Rect".to_string(),
+                    fields: vec![
+                        FieldDef {
+                            name: "w".to_string(),
+                            ty: None,
+                        },
+                        FieldDef {
+                            name: "h".to_string(),
+                            ty: None,
+                        },
+                    ],
+                },
+                VariantDef {
+                    name: "Empty".to_string(),
+                    fields: vec![],
+                },
+            ]
+        );
+        assert_eq!(shape().to_string(), "enum Shape { Circle(r: Int), Rect(w, h), Empty }");
+    }
+
+    #[test]
+    fn parse_enum_def_over_several_lines() {
+        let (rest, def) = EnumDef::new("enum Never {}").unwrap();
+        assert_eq!((rest, def.variants.len()), ("", 0));
+
+        let (rest, def) = EnumDef::new("enum Light {\n    Red,\n    Green,\n}").unwrap();
+        assert_eq!((rest, def.to_string()), ("", "enum Light { Red, Green }".to_string()));
+    }
+
+    #[test]
+    fn refuse_variant_or_field_declared_twice() {
+        assert_eq!(
+            EnumDef::new("enum Light { Red, Green, Red }"),
+            Err("variant 'Red' is declared twice".to_string())
+        );
+        assert_eq!(
+            EnumDef::new("enum Shape { Rect(w, w) }"),
+            Err("field 'w' is declared twice".to_string())
+        );
+    }
+
+    #[test]
+    fn construct_variants() {
+        let shape = shape();
+        assert_eq!(
+            shape.construct("Rect", vec![Val::Number(1), Val::Unit]).unwrap().to_string(),
+            "Shape::Rect(1, Unit)"
+        );
+        assert_eq!(shape.construct("Empty", vec![]).unwrap().to_string(), "Shape::Empty");
+    }
+
+    #[test]
+    fn refuse_wrong_variants() {
+        let shape = shape();
+        assert_eq!(
+            shape.construct("Square", vec![]),
+            Err("Shape has no variant 'Square'".into())
+        );
+        assert_eq!(
+            shape.construct("Rect", vec![Val::Number(1)]),
+            Err("variant 'Shape::Rect' takes 2 value(s) but 1 were given".into())
+        );
+        assert_eq!(
+            shape.construct("Circle", vec![Val::Unit]),
+            Err("expected Int for field 'r' of 'Shape::Circle', found Unit".into())
+        );
+    }
+
+    #[test]
+    fn construct_prelude_variants() {
+        let option = prelude("Option").unwrap();
+        assert_eq!(option.construct("Some", vec![Val::Number(1)]).unwrap().to_string(), "Some(1)");
+        assert_eq!(
+            prelude("Result").unwrap().construct("Err", vec![]),
+            Err("variant 'Result::Err' takes 1 value(s) but 0 were given".into())
+        );
+        assert_eq!(prelude("Shape"), None);
+        assert_eq!(prelude_enum("None"), Some("Option"));
+        assert_eq!(prelude_enum("Empty"), None);
+    }
+}
@@ -1,24 +1,35 @@
+use crate::budget::Budget;
+use crate::enum_def::{self, EnumDef};
+use crate::error::EvalError;
+use crate::expr::NameLoc;
+use crate::func_def::{FuncDef, Signature};
+use crate::native::NativeFunc;
 use crate::val::Val;
 use std::collections::HashMap;
+use std::rc::Rc;
 use crate::stmt::Stmt;
+use crate::struct_def::StructDef;
+
+/// How deeply function calls may nest before evaluation is aborted, well
+/// before the interpreter would run out of native stack.
+pub(crate) const MAX_CALL_DEPTH: usize = 128;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum NamedInfo {
     Binding(Val),
-    Func { params: Vec<String>, body: Stmt }
+    Func {
+        params: Vec<String>,
+        signature: Option<Rc<Signature>>,
+        body: Rc<Stmt>,
+    },
+    NativeFunc(NativeFunc),
+    Struct(Rc<StructDef>),
+    Enum(Rc<EnumDef>),
 }
 
 impl NamedInfo {
-    fn into_bindings(self) -> Option<Val> {
-        if let Self::Binding(val) = self {
-            Some(val)
-        } else {
-            None
-        }
-    }
-
-    fn into_func(self) -> Option<(Vec<String>, Stmt)> {
-        if let Self::Func {params, body} = self {
+    fn into_func(self) -> Option<(Vec<String>, Rc<Stmt>)> {
+        if let Self::Func { params, body, .. } = self {
             Some((params, body))
         } else {
             None
@@ -26,38 +37,285 @@ impl NamedInfo {
     }
 }
 
+/// The bindings and functions of one scope. Functions and globals are kept
+/// by name, while the bindings of blocks and function bodies are kept in
+/// the slots the resolver gave them.
 #[derive(Debug, PartialEq, Default)]
 pub struct Env<'parent> {
     named: HashMap<String, NamedInfo>,
+    slots: Vec<Val>,
+    /// Names of the parameters in the first slots, if this is the scope of
+    /// a function call, so that bodies that were never resolved can still
+    /// use them.
+    params: &'parent [String],
     parent: Option<&'parent Self>,
+    call_depth: usize,
+    budget: Rc<Budget>,
 }
 
 impl<'parent> Env<'parent> {
     pub fn create_child(&'parent self) -> Self {
         Self {
             named: HashMap::new(),
+            slots: Vec::new(),
+            params: &[],
             parent: Some(self),
+            call_depth: self.call_depth,
+            budget: Rc::clone(&self.budget),
+        }
+    }
+
+    /// Replaces the budget of this environment. Children created from then
+    /// on share the new one.
+    pub(crate) fn set_budget(&mut self, budget: Budget) {
+        self.budget = Rc::new(budget);
+    }
+
+    /// Spends one unit of the budget, failing if it is used up.
+    pub(crate) fn tick(&self) -> Result<(), EvalError> {
+        self.budget.tick()
+    }
+
+    /// Charges the memory `val` takes up to the budget, leaving out what it
+    /// shares with values that are already held.
+    pub(crate) fn charge(&self, val: &Val) -> Result<(), EvalError> {
+        self.budget.charge(val.unshared_size())
+    }
+
+    /// Like [`Env::store_binding`], but charges the value to the budget
+    /// first.
+    pub(crate) fn bind(&mut self, name: String, val: Val) -> Result<(), EvalError> {
+        self.charge(&val)?;
+        self.store_binding(name, val);
+        Ok(())
+    }
+
+    /// Stores `val` in a slot of this environment, charging it to the budget
+    /// first.
+    pub(crate) fn store_local(&mut self, slot: usize, val: Val) -> Result<(), EvalError> {
+        self.charge(&val)?;
+        if slot >= self.slots.len() {
+            self.slots.resize(slot + 1, Val::Unit);
         }
+        self.slots[slot] = val;
+        Ok(())
+    }
+
+    /// Approximate bytes held by the bindings defined directly in this
+    /// environment.
+    pub(crate) fn binding_size(&self) -> usize {
+        self.named
+            .iter()
+            .map(|(name, info)| match info {
+                NamedInfo::Binding(val) => name.len() + val.approx_size(),
+                _ => 0,
+            })
+            .sum()
     }
 
     pub fn store_binding(&mut self, name: String, val: Val) {
         self.named.insert(name, NamedInfo::Binding(val));
     }
 
-    pub fn store_func(&mut self, name: String, params: Vec<String>, body: Stmt) {
-        self.named.insert(name, NamedInfo::Func {params, body});
+    /// Defines a script function. Its body may be shared with the
+    /// definition it came from, as it is never changed.
+    pub fn store_func(&mut self, name: String, params: Vec<String>, body: impl Into<Rc<Stmt>>) {
+        self.named.insert(
+            name,
+            NamedInfo::Func {
+                params,
+                signature: None,
+                body: body.into(),
+            },
+        );
     }
 
-    pub fn get_binding(&self, name: &str) -> Result<Val, String> {
-        self.get_named_info(name)
-            .and_then(NamedInfo::into_bindings)
-            .ok_or_else(|| format!("binding with name '{}' does not exist", name))
+    /// Stores the function `func_def` defines, keeping its annotations.
+    pub(crate) fn store_func_def(&mut self, func_def: &FuncDef) {
+        self.named.insert(
+            func_def.name.clone(),
+            NamedInfo::Func {
+                params: func_def.params.clone(),
+                signature: func_def.signature.clone(),
+                body: Rc::clone(&func_def.body),
+            },
+        );
+    }
+
+    /// Defines a struct. Its definition may be shared with compiled code
+    /// that builds values of it.
+    pub(crate) fn store_struct(&mut self, struct_def: impl Into<Rc<StructDef>>) {
+        let struct_def = struct_def.into();
+        self.named.insert(struct_def.name.clone(), NamedInfo::Struct(struct_def));
+    }
+
+    /// Defines an enum, sharing its definition like [`Env::store_struct`].
+    pub(crate) fn store_enum(&mut self, enum_def: impl Into<Rc<EnumDef>>) {
+        let enum_def = enum_def.into();
+        self.named.insert(enum_def.name.clone(), NamedInfo::Enum(enum_def));
+    }
+
+    pub fn store_native_func(&mut self, name: String, func: NativeFunc) {
+        self.named.insert(name, NamedInfo::NativeFunc(func));
+    }
+
+    pub fn get_binding(&self, name: &str) -> Result<Val, EvalError> {
+        self.get_binding_at(name, None)
     }
 
-    pub fn get_func(&self, name: &str) -> Result<(Vec<String>, Stmt), String> {
+    /// Gets the binding `name` from where the resolver found it, or looks it
+    /// up by name if `loc` is `None`.
+    pub(crate) fn get_binding_at(&self, name: &str, loc: Option<NameLoc>) -> Result<Val, EvalError> {
+        let val = match loc {
+            None => self.lookup_binding(name),
+            Some(NameLoc::Local { depth, slot }) => {
+                self.ancestor(depth).and_then(|env| env.slots.get(slot)).cloned()
+            }
+            Some(NameLoc::Named { depth }) => self.ancestor(depth).and_then(|env| env.lookup_binding(name)),
+        };
+        val.ok_or_else(|| format!("binding with name '{}' does not exist", name).into())
+    }
+
+    fn lookup_binding(&self, name: &str) -> Option<Val> {
+        if let Some(info) = self.named.get(name) {
+            return match info {
+                NamedInfo::Binding(val) => Some(val.clone()),
+                _ => None,
+            };
+        }
+
+        match self.params.iter().position(|param| param == name) {
+            Some(slot) => self.slots.get(slot).cloned(),
+            None => self.parent.and_then(|parent| parent.lookup_binding(name)),
+        }
+    }
+
+    /// The environment `depth` scopes out from this one.
+    fn ancestor(&self, depth: usize) -> Option<&Self> {
+        let mut env = self;
+        for _ in 0..depth {
+            env = env.parent?;
+        }
+        Some(env)
+    }
+
+    pub fn get_func(&self, name: &str) -> Result<(Vec<String>, Rc<Stmt>), EvalError> {
         self.get_named_info(name)
             .and_then(NamedInfo::into_func)
-            .ok_or_else(|| format!("function with name '{}' does not exist", name))
+            .ok_or_else(|| format!("function with name '{}' does not exist", name).into())
+    }
+
+    pub(crate) fn get_struct(&self, name: &str) -> Result<Rc<StructDef>, EvalError> {
+        match self.find(name) {
+            Some((_, NamedInfo::Struct(struct_def))) => Ok(Rc::clone(struct_def)),
+            _ => Err(format!("struct with name '{}' does not exist", name).into()),
+        }
+    }
+
+    /// Gets the enum `name`, which may be one of the built-in enums unless
+    /// the program defines something of the same name.
+    pub(crate) fn get_enum(&self, name: &str) -> Result<Rc<EnumDef>, EvalError> {
+        let enum_def = match self.find(name) {
+            Some((_, NamedInfo::Enum(enum_def))) => Some(Rc::clone(enum_def)),
+            Some(_) => None,
+            None => enum_def::prelude(name),
+        };
+        enum_def.ok_or_else(|| format!("enum with name '{}' does not exist", name).into())
+    }
+
+    /// Calls the script or native function `name` with `args`. A script
+    /// function's body is evaluated in a child of the environment the
+    /// function was defined in, with its parameters bound to `args`.
+    pub fn call_func(&self, name: &str, args: Vec<Val>) -> Result<Val, EvalError> {
+        self.call_func_at(name, None, args)
+    }
+
+    /// Like [`Env::call_func`], but finds the function where the resolver
+    /// found it, or by name if `loc` is `None`.
+    pub(crate) fn call_func_at(&self, name: &str, loc: Option<NameLoc>, args: Vec<Val>) -> Result<Val, EvalError> {
+        let found = match loc {
+            None => self.find(name),
+            Some(NameLoc::Named { depth }) => self.ancestor(depth).and_then(|env| env.find(name)),
+            Some(NameLoc::Local { .. }) => None,
+        };
+
+        match found {
+            Some((defined_in, NamedInfo::Func { params, signature, body })) => {
+                check_arity(Some(name), params.len(), args.len())?;
+                if self.call_depth >= MAX_CALL_DEPTH {
+                    return Err(format!("maximum call depth of {} exceeded", MAX_CALL_DEPTH).into());
+                }
+                if let Some(signature) = signature {
+                    signature.check_args(name, params, &args)?;
+                }
+
+                let mut env = defined_in.create_child();
+                env.call_depth = self.call_depth + 1;
+                env.params = params;
+                for (slot, arg) in args.into_iter().enumerate() {
+                    env.store_local(slot, arg)?;
+                }
+                let result = match body.eval(&mut env) {
+                    Err(EvalError::EarlyReturn(val)) => val,
+                    result => result?,
+                };
+                if let Some(signature) = signature {
+                    signature.check_result(name, &result)?;
+                }
+                Ok(result)
+            }
+            Some((_, NamedInfo::NativeFunc(func))) => self.call_native(name, func, &args),
+            _ => Err(format!("function with name '{}' does not exist", name).into()),
+        }
+    }
+
+    /// Calls the native function `func` that is known to scripts as `name`,
+    /// charging its result to the budget.
+    pub(crate) fn call_native(&self, name: &str, func: &NativeFunc, args: &[Val]) -> Result<Val, EvalError> {
+        if let Some(arity) = func.arity() {
+            check_arity(Some(name), arity, args.len())?;
+        }
+
+        let result = func.call(args).map_err(|err| match err.into_eval_error() {
+            EvalError::Runtime(msg) => EvalError::Runtime(format!("{}: {}", name, msg)),
+            err => err,
+        })?;
+        self.charge(&result)?;
+        Ok(result)
+    }
+
+    /// Looks `name` up along the parent chain, along with the environment
+    /// that defines it.
+    pub(crate) fn find(&self, name: &str) -> Option<(&Self, &NamedInfo)> {
+        match self.named.get(name) {
+            Some(info) => Some((self, info)),
+            None => self.parent.and_then(|parent| parent.find(name)),
+        }
+    }
+
+    /// Bindings and functions defined directly in this environment, in no
+    /// particular order. Those of parent environments are not included.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &NamedInfo)> {
+        self.named.iter().map(|(name, info)| (name.as_str(), info))
+    }
+
+    /// Names of every binding and function visible from this environment.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.iter().map(|(name, _)| name).collect();
+        if let Some(parent) = self.parent {
+            names.extend(parent.names());
+        }
+        names
+    }
+
+    /// Takes the bindings and functions defined directly in this
+    /// environment, leaving it empty.
+    pub(crate) fn take_named(&mut self) -> HashMap<String, NamedInfo> {
+        std::mem::take(&mut self.named)
+    }
+
+    pub(crate) fn extend(&mut self, named: HashMap<String, NamedInfo>) {
+        self.named.extend(named);
     }
 
     pub fn get_named_info(&self, name: &str) -> Option<NamedInfo> {
@@ -67,9 +325,24 @@ impl<'parent> Env<'parent> {
     }
 }
 
+/// Fails unless a function taking `arity` arguments was given `given`. The
+/// error names the function if it is called by a name.
+pub(crate) fn check_arity(name: Option<&str>, arity: usize, given: usize) -> Result<(), EvalError> {
+    if arity == given {
+        return Ok(());
+    }
+
+    let function = match name {
+        Some(name) => format!("function '{}'", name),
+        None => "function".to_string(),
+    };
+    Err(format!("{} takes {} argument(s) but {} were given", function, arity, given).into())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::expr::binding_usage::BindingUsage;
+    use crate::expr::{Expr, FuncCall, Number};
 
     use super::*;
 
@@ -80,22 +353,130 @@ mod tests {
 
         assert_eq!(
             BindingUsage {
-                name: "foo".to_string()
+                name: "foo".to_string(),
+                loc: None,
             }
                 .eval(&env),
             Ok(Val::Number(110))
         )
     }
 
+    #[test]
+    fn list_names_including_parent_env() {
+        let mut parent = Env::default();
+        parent.store_binding("foo".to_string(), Val::Number(1));
+
+        let mut child = parent.create_child();
+        child.store_func(
+            "bar".to_string(),
+            vec![],
+            Stmt::Expr(Expr::Number(Number(1))),
+        );
+
+        let mut names = child.names();
+        names.sort();
+        assert_eq!(names, vec!["bar", "foo"]);
+    }
+
+    #[test]
+    fn call_func_with_args() {
+        let mut env = Env::default();
+        env.store_func(
+            "id".to_string(),
+            vec!["x".to_string()],
+            Stmt::Expr(Expr::BindingUsage(BindingUsage {
+                name: "x".to_string(),
+                loc: None,
+            })),
+        );
+
+        assert_eq!(env.call_func("id", vec![Val::Number(5)]), Ok(Val::Number(5)));
+        assert_eq!(env.get_named_info("x"), None);
+    }
+
+    #[test]
+    fn call_func_with_wrong_number_of_args() {
+        let mut env = Env::default();
+        env.store_func("f".to_string(), vec![], Stmt::Expr(Expr::Number(Number(1))));
+
+        assert_eq!(
+            env.call_func("f", vec![Val::Number(5)]),
+            Err("function 'f' takes 0 argument(s) but 1 were given".into())
+        );
+    }
+
+    #[test]
+    fn call_func_in_env_it_was_defined_in() {
+        let mut env = Env::default();
+        env.store_binding("x".to_string(), Val::Number(1));
+        env.store_func(
+            "get_x".to_string(),
+            vec![],
+            Stmt::Expr(Expr::BindingUsage(BindingUsage {
+                name: "x".to_string(),
+                loc: None,
+            })),
+        );
+
+        let mut child = env.create_child();
+        child.store_binding("x".to_string(), Val::Number(2));
+        assert_eq!(child.call_func("get_x", vec![]), Ok(Val::Number(1)));
+    }
+
+    #[test]
+    fn call_native_func() {
+        let mut env = Env::default();
+        env.store_native_func(
+            "fail".to_string(),
+            NativeFunc::new(|_| Err(crate::Error::Eval("no luck".into()))),
+        );
+
+        assert_eq!(env.call_func("fail", vec![]), Err("fail: no luck".into()));
+    }
+
+    #[test]
+    fn stop_unbounded_recursion() {
+        let mut env = Env::default();
+        env.store_func(
+            "f".to_string(),
+            vec![],
+            Stmt::Expr(Expr::FuncCall(FuncCall {
+                callee: "f".to_string(),
+                args: vec![],
+                loc: None,
+            })),
+        );
+
+        assert_eq!(
+            env.call_func("f", vec![]),
+            Err(format!("maximum call depth of {} exceeded", MAX_CALL_DEPTH).into())
+        );
+    }
+
+    #[test]
+    fn iterate_over_own_named_info() {
+        let mut parent = Env::default();
+        parent.store_binding("foo".to_string(), Val::Number(1));
+
+        let mut child = parent.create_child();
+        child.store_binding("bar".to_string(), Val::Number(2));
+
+        assert_eq!(
+            child.iter().collect::<Vec<_>>(),
+            vec![("bar", &NamedInfo::Binding(Val::Number(2)))]
+        );
+    }
+
     #[test]
     fn eval_with_non_existing_binding_usage() {
         let env = Env::default();
         assert_eq!(
             BindingUsage {
-                name: "foo".to_string()
+                name: "foo".to_string(),
+                loc: None,
             }
                 .eval(&env),
-            Err("binding with name 'foo' does not exist".to_string())
+            Err("binding with name 'foo' does not exist".into())
         )
     }
 }
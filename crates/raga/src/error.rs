@@ -0,0 +1,120 @@
+use crate::{ParseError, Val};
+use std::{fmt, io};
+
+/// Why evaluating a script stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    /// The script itself failed, e.g. by using a binding that does not exist.
+    Runtime(String),
+    /// The script used up the fuel it was given.
+    OutOfFuel,
+    /// The script was still running at its deadline.
+    Timeout,
+    /// The values held by the script grew past the memory limit.
+    MemoryLimit,
+    /// A `?` found a `None` or `Err`, which the function it is in returns.
+    /// Calls and [`Parse::eval`](crate::Parse::eval) turn this back into
+    /// their result, so it only ever escapes from evaluating a single
+    /// statement or expression. A `?` outside of any function ends the
+    /// whole program with the value as its result, and globals defined
+    /// before it are kept as after any other successful evaluation.
+    EarlyReturn(Val),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Runtime(msg) => write!(f, "{}", msg),
+            Self::OutOfFuel => write!(f, "out of fuel"),
+            Self::Timeout => write!(f, "deadline exceeded"),
+            Self::MemoryLimit => write!(f, "memory limit exceeded"),
+            Self::EarlyReturn(val) => write!(f, "returned early with {}", val),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl From<String> for EvalError {
+    fn from(msg: String) -> Self {
+        Self::Runtime(msg)
+    }
+}
+
+impl From<&str> for EvalError {
+    fn from(msg: &str) -> Self {
+        Self::Runtime(msg.to_string())
+    }
+}
+
+/// Everything that can go wrong when running raga code through an
+/// [`Engine`](crate::Engine).
+#[derive(Debug)]
+pub enum Error {
+    Parse(Vec<ParseError>),
+    /// Names that are undefined or used as something they are not, found
+    /// before evaluation.
+    Resolve(Vec<ParseError>),
+    /// Values used in ways their types do not allow, found before evaluation
+    /// when type checking is turned on.
+    Type(Vec<ParseError>),
+    Eval(EvalError),
+    Io(io::Error),
+    SourceTooLong { len: usize, max: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(errors) => {
+                let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "parse error: {}", messages.join(", "))
+            }
+            Self::Resolve(errors) => {
+                let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "resolve error: {}", messages.join(", "))
+            }
+            Self::Type(errors) => {
+                let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "type error: {}", messages.join(", "))
+            }
+            Self::Eval(msg) => write!(f, "evaluation error: {}", msg),
+            Self::Io(err) => write!(f, "{}", err),
+            Self::SourceTooLong { len, max } => {
+                write!(f, "source is {} bytes long, the limit is {}", len, max)
+            }
+        }
+    }
+}
+
+impl Error {
+    /// Turns an error raised by host code while a script is being evaluated
+    /// into an evaluation error of the script.
+    pub(crate) fn into_eval_error(self) -> EvalError {
+        match self {
+            Self::Eval(err) => err,
+            err => EvalError::Runtime(err.to_string()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<EvalError> for Error {
+    fn from(err: EvalError) -> Self {
+        Self::Eval(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
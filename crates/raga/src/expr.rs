@@ -1,19 +1,109 @@
 pub mod binding_usage;
 pub mod blocks;
+pub mod func_call;
+pub mod match_expr;
+pub mod member;
+pub mod struct_lit;
+pub mod try_expr;
+pub mod tuple;
+pub mod variant_lit;
 
 use crate::env::Env;
-use crate::{utils, val::Val};
-
-pub(crate) use binding_usage::BindingUsage;
+use crate::error::EvalError;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{NodeKind, SyntaxNode, SyntaxToken};
+use crate::val::Val;
+use std::fmt;
+
+pub(crate) use binding_usage::{BindingUsage, NameLoc};
 pub(crate) use blocks::Block;
+pub(crate) use func_call::FuncCall;
+pub(crate) use match_expr::Match;
+pub(crate) use member::{FieldAccess, MethodCall};
+pub(crate) use struct_lit::StructLit;
+pub(crate) use try_expr::Try;
+pub(crate) use tuple::Tuple;
+pub(crate) use variant_lit::VariantLit;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Number(pub i32);
 
 impl Number {
     pub fn new(s: &str) -> Result<(&str, Self), String> {
-        let (s, number) = utils::extract_digits(s)?;
-        Ok((s, Self(number.parse().unwrap())))
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    /// Parses digits, with a `-` in front for a negative number, as literal
+    /// patterns do.
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::Literal);
+        let minus = p.at(TokenKind::Minus).then(|| p.bump());
+        let token = p.expect(TokenKind::Number, "digits")?;
+        p.finish_node();
+
+        let text = format!("{}{}", minus.map_or("", |minus| minus.text), token.text);
+        match text.parse::<i32>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(ParseError {
+                message: format!("number literal '{}' is too large", text),
+                span: token.span,
+            }),
+        }
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let text: String = node
+            .child_tokens()
+            .filter(|token| matches!(token.kind(), TokenKind::Minus | TokenKind::Number))
+            .map(|token| token.text().to_string())
+            .collect();
+        text.parse().ok().map(Self)
+    }
+}
+
+/// Prints a negative number in parentheses, so that on a line of its own it
+/// does not continue the expression before it as a subtraction.
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 0 {
+            write!(f, "({})", self.0)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bool(pub bool);
+
+impl Bool {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::Literal);
+        if !p.at(TokenKind::TrueKw) && !p.at(TokenKind::FalseKw) {
+            return Err(p.error("expected true or false".to_string()));
+        }
+        p.bump();
+        p.finish_node();
+        Ok(())
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        node.child_tokens().find_map(|token| match token.kind() {
+            TokenKind::TrueKw => Some(Self(true)),
+            TokenKind::FalseKw => Some(Self(false)),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for Bool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
@@ -23,21 +113,117 @@ pub enum Op {
     Sub,
     Mul,
     Div,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
 }
 
 impl Op {
     pub fn new(s: &str) -> Result<(&str, Self), String> {
-        utils::tag("+", s)
-            .map(|s| (s, Self::Add))
-            .or_else(|_| utils::tag("-", s).map(|s| (s, Self::Sub)))
-            .or_else(|_| utils::tag("*", s).map(|s| (s, Self::Mul)))
-            .or_else(|_| utils::tag("/", s).map(|s| (s, Self::Div)))
+        parser::parse_root_with(s, Self::parse, |root| {
+            root.child_tokens().find_map(|token| Self::lower(&token))
+        })
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        if p.peek().and_then(Self::from_token_kind).is_none() {
+            return Err(p.error("expected operator".to_string()));
+        }
+        p.bump();
+        Ok(())
+    }
+
+    pub(crate) fn lower(token: &SyntaxToken) -> Option<Self> {
+        Self::from_token_kind(token.kind())
+    }
+
+    fn from_token_kind(kind: TokenKind) -> Option<Self> {
+        match kind {
+            TokenKind::Plus => Some(Self::Add),
+            TokenKind::Minus => Some(Self::Sub),
+            TokenKind::Star => Some(Self::Mul),
+            TokenKind::Slash => Some(Self::Div),
+            TokenKind::EqEq => Some(Self::Eq),
+            TokenKind::NotEq => Some(Self::Ne),
+            TokenKind::Less => Some(Self::Lt),
+            TokenKind::LessEq => Some(Self::Le),
+            TokenKind::Greater => Some(Self::Gt),
+            TokenKind::GreaterEq => Some(Self::Ge),
+            _ => None,
+        }
+    }
+
+    fn binding_power(&self) -> (u8, u8) {
+        match self {
+            Self::Eq | Self::Ne | Self::Lt | Self::Le | Self::Gt | Self::Ge => (1, 2),
+            Self::Add | Self::Sub => (3, 4),
+            Self::Mul | Self::Div => (5, 6),
+        }
+    }
+
+    /// Whether the operator compares its operands, giving a Bool, rather
+    /// than doing arithmetic on them.
+    pub(crate) fn is_comparison(&self) -> bool {
+        self.binding_power().0 == 1
+    }
+
+    /// Applies the operator to already evaluated operands. Any two values
+    /// can be checked for equality, but only numbers can be ordered.
+    pub(crate) fn apply(&self, lhs: Val, rhs: Val) -> Result<Val, EvalError> {
+        let (lhs, rhs) = match (lhs, rhs) {
+            (Val::Number(lhs), Val::Number(rhs)) => (lhs, rhs),
+            (lhs, rhs) if *self == Self::Eq => return Ok(Val::Bool(lhs == rhs)),
+            (lhs, rhs) if *self == Self::Ne => return Ok(Val::Bool(lhs != rhs)),
+            _ => return Err("cannot evaluate operation whose left-hand side and right-hand side are not both numbers".into())
+        };
+
+        if *self == Self::Div && rhs == 0 {
+            return Err("division by zero".into());
+        }
+
+        let result = match self {
+            Self::Add => lhs.checked_add(rhs).map(Val::Number),
+            Self::Sub => lhs.checked_sub(rhs).map(Val::Number),
+            Self::Mul => lhs.checked_mul(rhs).map(Val::Number),
+            Self::Div => lhs.checked_div(rhs).map(Val::Number),
+            Self::Eq => Some(Val::Bool(lhs == rhs)),
+            Self::Ne => Some(Val::Bool(lhs != rhs)),
+            Self::Lt => Some(Val::Bool(lhs < rhs)),
+            Self::Le => Some(Val::Bool(lhs <= rhs)),
+            Self::Gt => Some(Val::Bool(lhs > rhs)),
+            Self::Ge => Some(Val::Bool(lhs >= rhs)),
+        };
+
+        result
+            .ok_or_else(|| format!("integer overflow in {} {} {}", lhs, self, rhs).into())
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        };
+        write!(f, "{}", op)
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Number(Number),
+    Bool(Bool),
     Operation {
         lhs: Box<Self>,
         rhs: Box<Self>,
@@ -45,66 +231,190 @@ pub enum Expr {
     },
     BindingUsage(BindingUsage),
     Block(Block),
+    FuncCall(FuncCall),
+    FieldAccess(FieldAccess),
+    MethodCall(MethodCall),
+    StructLit(StructLit),
+    VariantLit(VariantLit),
+    Tuple(Tuple),
+    Match(Match),
+    Try(Try),
 }
 
 impl Expr {
     pub fn new(s: &str) -> Result<(&str, Self), String> {
-        Self::new_operation(s)
-            .or_else(|_| Self::new_non_operation(s))
+        parser::parse_with(s, Self::parse, Self::lower)
     }
 
-    pub fn new_number(s: &str) -> Result<(&str, Self), String> {
-        Number::new(s).map(|(s, number)| (s, Self::Number(number)))
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        Self::parse_with_binding_power(p, 0)
     }
 
-    pub fn new_non_operation(s: &str) -> Result<(&str, Self), String> {
-        Self::new_number(s)
-            .or_else(|_| {
-                BindingUsage::new(s)
-                    .map(|(s, binding_usage)| (s, Self::BindingUsage(binding_usage)))
-            })
-            .or_else(|_| Block::new(s).map(|(s, block)| (s, Self::Block(block))))
+    fn parse_with_binding_power(p: &mut Parser, min_binding_power: u8) -> Result<(), ParseError> {
+        let checkpoint = p.checkpoint();
+        Self::parse_lhs(p)?;
+        member::parse_postfix(p, checkpoint)?;
+
+        while let Some(op) = p.peek().and_then(Op::from_token_kind) {
+            let (left_binding_power, right_binding_power) = op.binding_power();
+            if left_binding_power < min_binding_power {
+                break;
+            }
+
+            p.start_node_at(checkpoint, NodeKind::BinaryExpr);
+            p.bump();
+            Self::parse_with_binding_power(p, right_binding_power)?;
+            p.finish_node();
+        }
+
+        Ok(())
     }
 
-    pub fn new_operation(s: &str) -> Result<(&str, Self), String> {
-        let (s, lhs) = Self::new_non_operation(s)?;
-        let (s, _) = utils::skip_whitespace(s);
+    fn parse_lhs(p: &mut Parser) -> Result<(), ParseError> {
+        match p.peek() {
+            Some(TokenKind::Number | TokenKind::Minus) => Number::parse(p),
+            Some(TokenKind::TrueKw | TokenKind::FalseKw) => Bool::parse(p),
+            Some(TokenKind::Ident) if StructLit::at_start(p) => StructLit::parse(p),
+            Some(TokenKind::Ident) if VariantLit::at_start(p) => VariantLit::parse(p),
+            Some(TokenKind::Ident) => {
+                let checkpoint = p.checkpoint();
+                BindingUsage::parse(p)?;
+                if p.at_adjacent(TokenKind::LParen) {
+                    FuncCall::parse_args(p, checkpoint)?;
+                }
+                Ok(())
+            }
+            Some(TokenKind::LBrace) => Block::parse(p),
+            Some(TokenKind::LParen) => Self::parse_paren_or_tuple(p),
+            Some(TokenKind::MatchKw) => Match::parse(p),
+            Some(TokenKind::Error) => {
+                let token = p.bump();
+                Err(ParseError {
+                    message: format!("unexpected character '{}'", token.text),
+                    span: token.span,
+                })
+            }
+            _ => Err(p.error("expected expression".to_string())),
+        }
+    }
 
-        let (s, op) = Op::new(s)?;
-        let (s, _) = utils::skip_whitespace(s);
+    /// Parses `(x)`, or a tuple once a comma follows the first value.
+    pub(crate) fn parse_paren_or_tuple(p: &mut Parser) -> Result<(), ParseError> {
+        let checkpoint = p.checkpoint();
+        p.expect(TokenKind::LParen, "(")?;
+        Self::parse(p)?;
+
+        if !p.at(TokenKind::Comma) {
+            p.start_node_at(checkpoint, NodeKind::ParenExpr);
+            p.expect(TokenKind::RParen, ")")?;
+            p.finish_node();
+            return Ok(());
+        }
 
-        let (s, rhs) = Self::new_non_operation(s)?;
+        p.start_node_at(checkpoint, NodeKind::TupleExpr);
+        while p.at(TokenKind::Comma) {
+            p.bump();
+            if p.at(TokenKind::RParen) {
+                break;
+            }
+            Self::parse(p)?;
+        }
+        p.expect(TokenKind::RParen, ", or )")?;
+        p.finish_node();
+        Ok(())
+    }
 
-        Ok((s, Self::Operation {
-            lhs: Box::new(lhs),
-            rhs: Box::new(rhs),
-            op
-        }))
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        match node.kind() {
+            NodeKind::Literal => Number::lower(node)
+                .map(Self::Number)
+                .or_else(|| Bool::lower(node).map(Self::Bool)),
+            NodeKind::NameRef => BindingUsage::lower(node).map(Self::BindingUsage),
+            NodeKind::Block => Block::lower(node).map(Self::Block),
+            NodeKind::CallExpr => FuncCall::lower(node).map(Self::FuncCall),
+            NodeKind::FieldExpr => FieldAccess::lower(node).map(Self::FieldAccess),
+            NodeKind::MethodCallExpr => MethodCall::lower(node).map(Self::MethodCall),
+            NodeKind::StructLit => StructLit::lower(node).map(Self::StructLit),
+            NodeKind::VariantLit => VariantLit::lower(node).map(Self::VariantLit),
+            NodeKind::TupleExpr => Tuple::lower(node).map(Self::Tuple),
+            NodeKind::MatchExpr => Match::lower(node).map(Self::Match),
+            NodeKind::TryExpr => Try::lower(node).map(Self::Try),
+            NodeKind::ParenExpr => node.children().next().and_then(|inner| Self::lower(&inner)),
+            NodeKind::BinaryExpr => {
+                let mut operands = node.children();
+                let lhs = Self::lower(&operands.next()?)?;
+                let rhs = Self::lower(&operands.next()?)?;
+                let op = node.child_tokens().find_map(|token| Op::lower(&token))?;
+
+                Some(Self::Operation {
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                    op,
+                })
+            }
+            _ => None,
+        }
     }
 
-    pub fn eval(&self, env: &Env) -> Result<Val, String> {
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        env.tick()?;
+
         match self {
             Self::Operation { lhs, rhs, op } => {
                 let lhs = lhs.eval(env)?;
                 let rhs = rhs.eval(env)?;
-
-                let (lhs, rhs) = match (lhs, rhs) {
-                    (Val::Number(lhs), Val::Number(rhs)) => (lhs, rhs),
-                    _ => return Err("cannot evaluate operation whose left-hand side and right-hand side are not both numbers".to_string())
-                };
-
-                let result = match op {
-                    Op::Add => lhs + rhs,
-                    Op::Sub => lhs - rhs,
-                    Op::Mul => lhs * rhs,
-                    Op::Div => lhs / rhs,
-                };
-
-                Ok(Val::Number(result))
+                op.apply(lhs, rhs)
             }
             Self::Number(Number(n)) => Ok(Val::Number(*n)),
+            Self::Bool(Bool(b)) => Ok(Val::Bool(*b)),
             Self::BindingUsage(binding_usage) => binding_usage.eval(env),
             Self::Block(block) => block.eval(env),
+            Self::FuncCall(func_call) => func_call.eval(env),
+            Self::FieldAccess(field_access) => field_access.eval(env),
+            Self::MethodCall(method_call) => method_call.eval(env),
+            Self::StructLit(struct_lit) => struct_lit.eval(env),
+            Self::VariantLit(variant_lit) => variant_lit.eval(env),
+            Self::Tuple(tuple) => tuple.eval(env),
+            Self::Match(match_expr) => match_expr.eval(env),
+            Self::Try(try_expr) => try_expr.eval(env),
+        }
+    }
+}
+
+/// Prints the expression as raga source, adding only the parentheses needed
+/// for it to parse back into the same tree.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(number) => write!(f, "{}", number),
+            Self::Bool(b) => write!(f, "{}", b),
+            Self::Operation { lhs, rhs, op } => {
+                let (left_binding_power, right_binding_power) = op.binding_power();
+                lhs.fmt_operand(f, left_binding_power)?;
+                write!(f, " {} ", op)?;
+                rhs.fmt_operand(f, right_binding_power)
+            }
+            Self::BindingUsage(binding_usage) => write!(f, "{}", binding_usage),
+            Self::Block(block) => write!(f, "{}", block),
+            Self::FuncCall(func_call) => write!(f, "{}", func_call),
+            Self::FieldAccess(field_access) => write!(f, "{}", field_access),
+            Self::MethodCall(method_call) => write!(f, "{}", method_call),
+            Self::StructLit(struct_lit) => write!(f, "{}", struct_lit),
+            Self::VariantLit(variant_lit) => write!(f, "{}", variant_lit),
+            Self::Tuple(tuple) => write!(f, "{}", tuple),
+            Self::Match(match_expr) => write!(f, "{}", match_expr),
+            Self::Try(try_expr) => write!(f, "{}", try_expr),
+        }
+    }
+}
+
+impl Expr {
+    /// An operand is parsed with a minimum binding power, so an operation
+    /// binding any looser than that has to be put in parentheses.
+    pub(crate) fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, min_binding_power: u8) -> fmt::Result {
+        match self {
+            Self::Operation { op, .. } if op.binding_power().0 < min_binding_power => write!(f, "({})", self),
+            _ => write!(f, "{}", self),
         }
     }
 }
@@ -122,6 +432,30 @@ mod tests {
         assert_eq!(Number::new("123"), Ok(("", Number(123))));
     }
 
+    #[test]
+    fn parse_negative_number() {
+        assert_eq!(Number::new("-123"), Ok(("", Number(-123))));
+        assert_eq!(Number::new("-2147483648"), Ok(("", Number(i32::MIN))));
+        assert_eq!(
+            Expr::new("1 - -2"),
+            Ok((
+                "",
+                Expr::Operation {
+                    lhs: Box::new(Expr::Number(Number(1))),
+                    rhs: Box::new(Expr::Number(Number(-2))),
+                    op: Op::Sub,
+                },
+            )),
+        );
+    }
+
+    #[test]
+    fn display_negative_number_in_parens() {
+        assert_eq!(Number(-5).to_string(), "(-5)");
+        assert_eq!(Expr::new(&Number(-5).to_string()), Ok(("", Expr::Number(Number(-5)))));
+        assert_eq!(Number(i32::MIN).to_string(), "(-2147483648)");
+    }
+
     #[test]
     fn parse_number_as_expr() {
         assert_eq!(Expr::new("123"), Ok(("", Expr::Number(Number(123)))));
@@ -147,6 +481,13 @@ mod tests {
         assert_eq!(Op::new("/"), Ok(("", Op::Div)));
     }
 
+    #[test]
+    fn parse_comparison_ops() {
+        for (src, op) in [("==", Op::Eq), ("!=", Op::Ne), ("<", Op::Lt), ("<=", Op::Le), (">", Op::Gt), (">=", Op::Ge)] {
+            assert_eq!(Op::new(src), Ok(("", op)));
+        }
+    }
+
     #[test]
     fn parse_one_plus_two() {
         assert_eq!(
@@ -177,6 +518,105 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_mul_before_add() {
+        assert_eq!(
+            Expr::new("1 + 2 * 3"),
+            Ok((
+                "",
+                Expr::Operation {
+                    lhs: Box::new(Expr::Number(Number(1))),
+                    rhs: Box::new(Expr::Operation {
+                        lhs: Box::new(Expr::Number(Number(2))),
+                        rhs: Box::new(Expr::Number(Number(3))),
+                        op: Op::Mul,
+                    }),
+                    op: Op::Add,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_sub_as_left_associative() {
+        assert_eq!(
+            Expr::new("5 - 2 - 1"),
+            Ok((
+                "",
+                Expr::Operation {
+                    lhs: Box::new(Expr::Operation {
+                        lhs: Box::new(Expr::Number(Number(5))),
+                        rhs: Box::new(Expr::Number(Number(2))),
+                        op: Op::Sub,
+                    }),
+                    rhs: Box::new(Expr::Number(Number(1))),
+                    op: Op::Sub,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_parenthesized_expr() {
+        assert_eq!(
+            Expr::new("(1 + 2) * 3"),
+            Ok((
+                "",
+                Expr::Operation {
+                    lhs: Box::new(Expr::Operation {
+                        lhs: Box::new(Expr::Number(Number(1))),
+                        rhs: Box::new(Expr::Number(Number(2))),
+                        op: Op::Add,
+                    }),
+                    rhs: Box::new(Expr::Number(Number(3))),
+                    op: Op::Mul,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_binding_usage_starting_with_keyword() {
+        assert_eq!(
+            Expr::new("letter"),
+            Ok((
+                "",
+                Expr::BindingUsage(BindingUsage {
+                    name: "letter".to_string(),
+                    loc: None,
+                })
+            ))
+        )
+    }
+
+    #[test]
+    fn parse_number_too_large() {
+        assert_eq!(
+            Expr::new("99999999999"),
+            Err("number literal '99999999999' is too large".to_string())
+        )
+    }
+
+    #[test]
+    fn keep_text_after_expr() {
+        assert_eq!(
+            Expr::new("1 }"),
+            Ok((" }", Expr::Number(Number(1))))
+        )
+    }
+
+    #[test]
+    fn display_operations_with_minimal_parens() {
+        let (_, expr) = Expr::new("((1 + 2) * (3 - (4 - 5))) - (6 / 7 * 8)").unwrap();
+        assert_eq!(expr.to_string(), "(1 + 2) * (3 - (4 - 5)) - 6 / 7 * 8");
+    }
+
+    #[test]
+    fn display_left_associative_chain_without_parens() {
+        let (_, expr) = Expr::new("((1 - 2) - 3) / 4 / 5").unwrap();
+        assert_eq!(expr.to_string(), "(1 - 2 - 3) / 4 / 5");
+    }
+
     #[test]
     fn eval_non_number_operation() {
         assert_eq!(
@@ -185,7 +625,7 @@ mod tests {
                 rhs: Box::new(Expr::Block(Block{ stmts: vec![] })),
                 op: Op::Add
             }.eval(&Env::default()),
-            Err("cannot evaluate operation whose left-hand side and right-hand side are not both numbers".to_string())
+            Err("cannot evaluate operation whose left-hand side and right-hand side are not both numbers".into())
         )
     }
 
@@ -244,6 +684,101 @@ mod tests {
         )
     }
 
+    #[test]
+    fn eval_division_by_zero() {
+        let env = Env::default();
+        assert_eq!(
+            Expr::Operation {
+                lhs:  Box::new(Expr::Number(Number(1))),
+                rhs:  Box::new(Expr::Number(Number(0))),
+                op: Op::Div,
+            }
+            .eval(&env),
+            Err("division by zero".into())
+        )
+    }
+
+    #[test]
+    fn eval_overflowing_operation() {
+        let env = Env::default();
+        assert_eq!(
+            Expr::Operation {
+                lhs:  Box::new(Expr::Number(Number(65536))),
+                rhs:  Box::new(Expr::Number(Number(65536))),
+                op: Op::Mul,
+            }
+            .eval(&env),
+            Err("integer overflow in 65536 * 65536".into())
+        );
+        assert_eq!(
+            Expr::Operation {
+                lhs:  Box::new(Expr::Number(Number(i32::MIN))),
+                rhs:  Box::new(Expr::Number(Number(-1))),
+                op: Op::Div,
+            }
+            .eval(&env),
+            Err("integer overflow in -2147483648 / -1".into())
+        );
+    }
+
+    #[test]
+    fn parse_bool() {
+        assert_eq!(Bool::new("true"), Ok(("", Bool(true))));
+        assert_eq!(Expr::new("false"), Ok(("", Expr::Bool(Bool(false)))));
+        assert_eq!(Expr::new("false").unwrap().1.to_string(), "false");
+    }
+
+    #[test]
+    fn parse_comparisons_below_arithmetic() {
+        let (_, expr) = Expr::new("1 + 2 < 3 * 4 == true").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Operation {
+                lhs: Box::new(Expr::Operation {
+                    lhs: Box::new(Expr::Operation {
+                        lhs: Box::new(Expr::Number(Number(1))),
+                        rhs: Box::new(Expr::Number(Number(2))),
+                        op: Op::Add,
+                    }),
+                    rhs: Box::new(Expr::Operation {
+                        lhs: Box::new(Expr::Number(Number(3))),
+                        rhs: Box::new(Expr::Number(Number(4))),
+                        op: Op::Mul,
+                    }),
+                    op: Op::Lt,
+                }),
+                rhs: Box::new(Expr::Bool(Bool(true))),
+                op: Op::Eq,
+            }
+        );
+        assert_eq!(expr.to_string(), "1 + 2 < 3 * 4 == true");
+        let (_, expr) = Expr::new("a == (b != c)").unwrap();
+        assert_eq!(expr.to_string(), "a == (b != c)");
+    }
+
+    #[test]
+    fn eval_comparisons() {
+        let env = Env::default();
+        for (src, expected) in [
+            ("1 < 2", true),
+            ("2 < 2", false),
+            ("2 <= 2", true),
+            ("-1 > 0", false),
+            ("0 >= -1", true),
+            ("1 == 1", true),
+            ("1 != 1", false),
+            ("{} == {}", true),
+            ("1 == {}", false),
+            ("true != false", true),
+        ] {
+            assert_eq!(Expr::new(src).unwrap().1.eval(&env), Ok(Val::Bool(expected)), "{}", src);
+        }
+        assert_eq!(
+            Expr::new("true < false").unwrap().1.eval(&env),
+            Err("cannot evaluate operation whose left-hand side and right-hand side are not both numbers".into())
+        );
+    }
+
     #[test]
     fn parse_binding_usage() {
         assert_eq!(
@@ -251,7 +786,8 @@ mod tests {
             Ok((
                 "",
                 Expr::BindingUsage(BindingUsage {
-                    name: "bar".to_string()
+                    name: "bar".to_string(),
+                    loc: None,
                 })
             ))
         )
@@ -286,16 +822,22 @@ mod tests {
                     stmts: vec![
                         Stmt::BindingDef(BindingDef {
                             name: "a".to_string(),
+                            ty: None,
                             val: Expr::Number(Number(10)),
+                            slot: None,
                         }),
                         Stmt::BindingDef(BindingDef {
                             name: "b".to_string(),
+                            ty: None,
                             val: Expr::BindingUsage(BindingUsage {
                                 name: "a".to_string(),
+                                loc: None,
                             }),
+                            slot: None,
                         }),
                         Stmt::Expr(Expr::BindingUsage(BindingUsage {
                             name: "b".to_string(),
+                            loc: None,
                         })),
                     ]
                 })
@@ -303,6 +845,37 @@ mod tests {
         )
     }
 
+    #[test]
+    fn parse_func_call_only_without_space_before_paren() {
+        assert_eq!(
+            Expr::new("f(1)"),
+            Ok((
+                "",
+                Expr::FuncCall(FuncCall {
+                    callee: "f".to_string(),
+                    args: vec![Expr::Number(Number(1))],
+                    loc: None,
+                })
+            ))
+        );
+        assert_eq!(
+            Expr::new("f (1)"),
+            Ok((
+                " (1)",
+                Expr::BindingUsage(BindingUsage {
+                    name: "f".to_string(),
+                    loc: None,
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_func_call_as_operand() {
+        let (_, expr) = Expr::new("2 * f(1) + 3").unwrap();
+        assert_eq!(expr.to_string(), "2 * f(1) + 3");
+    }
+
     #[test]
     fn eval_binding_usage() {
         let mut env = Env::default();
@@ -310,7 +883,8 @@ mod tests {
 
         assert_eq!(
             Expr::BindingUsage(BindingUsage {
-                name: "ten".to_string()
+                name: "ten".to_string(),
+                loc: None,
             })
             .eval(&env),
             Ok(Val::Number(10))
@@ -1,23 +1,56 @@
-use crate::{env::Env, utils, val::Val};
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::{env::Env, error::EvalError, val::Val};
+use std::fmt;
+
+/// Where the resolver found the definition a name refers to, counted in
+/// scopes out from the one the name is used in. Blocks and function bodies
+/// are scopes, the top level of a program is not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameLoc {
+    /// A binding of an enclosing scope, kept in one of its slots.
+    Local { depth: usize, slot: usize },
+    /// A function of an enclosing scope, or a global if `depth` reaches past
+    /// every scope, which is still looked up by name from there.
+    Named { depth: usize },
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct BindingUsage {
     pub name: String,
+    /// Set by the resolver. Names it has not seen are looked up by name.
+    pub loc: Option<NameLoc>,
 }
 
 impl BindingUsage {
     pub fn new(s: &str) -> Result<(&str, Self), String> {
-        let (s, name) = utils::extract_ident(s)?;
-        Ok((
-            s,
-            Self {
-                name: name.to_string(),
-            },
-        ))
+        parser::parse_with(s, Self::parse, Self::lower)
     }
 
-    pub fn eval(&self, env: &Env) -> Result<Val, String> {
-        env.get_binding(&self.name)
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::NameRef);
+        p.expect(TokenKind::Ident, "identifier")?;
+        p.finish_node();
+        Ok(())
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let name = node.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+        Some(Self {
+            name: name.text().to_string(),
+            loc: None,
+        })
+    }
+
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        env.get_binding_at(&self.name, self.loc)
+    }
+}
+
+impl fmt::Display for BindingUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
     }
 }
 
@@ -32,7 +65,8 @@ mod tests {
             Ok((
                 "",
                 BindingUsage {
-                    name: "abs".to_string()
+                    name: "abs".to_string(),
+                    loc: None,
                 }
             ))
         )
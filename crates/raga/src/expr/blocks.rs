@@ -1,6 +1,11 @@
 use crate::env::Env;
+use crate::error::EvalError;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::stmt::Stmt;
+use crate::syntax::{NodeKind, SyntaxNode};
 use crate::val::Val;
-use crate::{stmt::Stmt, utils};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Block {
@@ -9,17 +14,25 @@ pub struct Block {
 
 impl Block {
     pub fn new(s: &str) -> Result<(&str, Self), String> {
-        let s = utils::tag("{", s)?;
-        let (s, _) = utils::skip_whitespace(s);
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
 
-        let (s, stmts) = utils::sequence(Stmt::new, s)?;
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::Block);
+        p.expect(TokenKind::LBrace, "{")?;
+        Stmt::parse_sequence(p, true);
+        p.expect(TokenKind::RBrace, "}")?;
+        p.finish_node();
+        Ok(())
+    }
 
-        let (s, _) = utils::skip_whitespace(s);
-        let s = utils::tag("}", s)?;
-        Ok((s, Self { stmts }))
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        Some(Self {
+            stmts: Stmt::lower_sequence(node),
+        })
     }
 
-    pub fn eval(&self, env: &Env) -> Result<Val, String> {
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
         if self.stmts.is_empty() {
             return Ok(Val::Unit);
         }
@@ -34,6 +47,23 @@ impl Block {
     }
 }
 
+/// Prints each statement on its own line, indented by four spaces.
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.stmts.is_empty() {
+            return write!(f, "{{}}");
+        }
+
+        writeln!(f, "{{")?;
+        for stmt in &self.stmts {
+            for line in stmt.to_string().lines() {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,16 +111,22 @@ mod tests {
                     stmts: vec![
                         Stmt::BindingDef(BindingDef {
                             name: "a".to_string(),
-                            val: Expr::Number(Number(10))
+                            ty: None,
+                            val: Expr::Number(Number(10)),
+                            slot: None,
                         }),
                         Stmt::BindingDef(BindingDef {
                             name: "b".to_string(),
+                            ty: None,
                             val: Expr::BindingUsage(BindingUsage {
                                 name: "a".to_string(),
+                                loc: None,
                             }),
+                            slot: None,
                         }),
                         Stmt::Expr(Expr::BindingUsage(BindingUsage {
                             name: "b".to_string(),
+                            loc: None,
                         })),
                     ]
                 }
@@ -124,10 +160,13 @@ mod tests {
                 stmts: vec![
                     Stmt::BindingDef(BindingDef {
                         name: "two".to_string(),
-                        val: Expr::Number(Number(2))
+                        ty: None,
+                        val: Expr::Number(Number(2)),
+                        slot: None,
                     }),
                     Stmt::Expr(Expr::BindingUsage(BindingUsage {
-                        name: "two".to_string()
+                        name: "two".to_string(),
+                        loc: None,
                     }))
                 ]
             }
@@ -137,21 +176,28 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_mut_passed)]
     fn eval_block_with_multiple_binding_defs() {
         assert_eq!(
             Block {
                 stmts: vec![
                     Stmt::BindingDef(BindingDef {
                         name: "jump".to_string(),
-                        val: Expr::Number(Number(22))
+                        ty: None,
+                        val: Expr::Number(Number(22)),
+                        slot: None,
                     }),
                     Stmt::BindingDef(BindingDef {
                         name: "cut".to_string(),
-                        val: Expr::Number(Number(200))
+                        ty: None,
+                        val: Expr::Number(Number(200)),
+                        slot: None,
                     }),
                     Stmt::BindingDef(BindingDef {
                         name: "pre".to_string(),
-                        val: Expr::Number(Number(32))
+                        ty: None,
+                        val: Expr::Number(Number(32)),
+                        slot: None,
                     })
                 ]
             }
@@ -161,6 +207,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_mut_passed)]
     fn eval_block_with_multiple_exprs() {
         assert_eq!(
             Block {
@@ -188,12 +235,16 @@ mod tests {
                 stmts: vec![
                     Stmt::BindingDef(BindingDef{
                         name: "baz".to_string(),
+                        ty: None,
                         val: Expr::BindingUsage(BindingUsage {
                             name: "foo".to_string(),
-                        })
+                            loc: None,
+                        }),
+                        slot: None,
                     }),
                     Stmt::Expr(Expr::BindingUsage(BindingUsage {
-                        name: "baz".to_string()
+                        name: "baz".to_string(),
+                        loc: None,
                     }))
                 ]
             }.eval(&env),
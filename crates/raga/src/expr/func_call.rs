@@ -0,0 +1,183 @@
+use crate::env::Env;
+use crate::error::EvalError;
+use crate::expr::{Expr, NameLoc};
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{Checkpoint, NodeKind, SyntaxNode};
+use crate::val::Val;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuncCall {
+    pub callee: String,
+    pub args: Vec<Expr>,
+    /// Set by the resolver. Callees it has not seen are looked up by name.
+    pub loc: Option<NameLoc>,
+}
+
+impl FuncCall {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        let checkpoint = p.checkpoint();
+        p.start_node(NodeKind::NameRef);
+        p.expect(TokenKind::Ident, "identifier")?;
+        p.finish_node();
+
+        if !p.at_adjacent(TokenKind::LParen) {
+            return Err(p.error("expected (".to_string()));
+        }
+        Self::parse_args(p, checkpoint)
+    }
+
+    /// Parses the argument list after a callee that has already been added
+    /// at `checkpoint`, and wraps both in a call node. The `(` has to follow
+    /// the callee directly, as `f (x)` is the two expressions `f` and `(x)`.
+    pub(crate) fn parse_args(p: &mut Parser, checkpoint: Checkpoint) -> Result<(), ParseError> {
+        p.start_node_at(checkpoint, NodeKind::CallExpr);
+        Self::parse_arg_list(p)?;
+        p.finish_node();
+        Ok(())
+    }
+
+    /// Parses a parenthesized, comma separated list of arguments.
+    pub(crate) fn parse_arg_list(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::ArgList);
+        p.expect(TokenKind::LParen, "(")?;
+
+        while !p.at(TokenKind::RParen) {
+            Expr::parse(p)?;
+            if !p.at(TokenKind::Comma) {
+                break;
+            }
+            p.bump();
+        }
+
+        p.expect(TokenKind::RParen, ", or )")?;
+        p.finish_node();
+        Ok(())
+    }
+
+    pub(crate) fn lower_arg_list(node: &SyntaxNode) -> Option<Vec<Expr>> {
+        node.children()
+            .find(|arg_list| arg_list.kind() == NodeKind::ArgList)?
+            .children()
+            .map(|arg| Expr::lower(&arg))
+            .collect()
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let callee = node
+            .children()
+            .next()
+            .filter(|name_ref| name_ref.kind() == NodeKind::NameRef)?
+            .child_tokens()
+            .find(|token| token.kind() == TokenKind::Ident)?;
+
+        Some(Self {
+            callee: callee.text().to_string(),
+            args: Self::lower_arg_list(node)?,
+            loc: None,
+        })
+    }
+
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        env.call_func_at(&self.callee, self.loc, eval_args(&self.args, env)?)
+    }
+}
+
+pub(crate) fn eval_args(args: &[Expr], env: &Env) -> Result<Vec<Val>, EvalError> {
+    args.iter().map(|arg| arg.eval(env)).collect()
+}
+
+/// Prints `(arg, arg)`.
+pub(crate) fn fmt_args(f: &mut fmt::Formatter<'_>, args: &[Expr]) -> fmt::Result {
+    write!(f, "(")?;
+    for (idx, arg) in args.iter().enumerate() {
+        if idx > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", arg)?;
+    }
+    write!(f, ")")
+}
+
+impl fmt::Display for FuncCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.callee)?;
+        fmt_args(f, &self.args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BindingUsage, Number, Op};
+    use crate::stmt::Stmt;
+
+    #[test]
+    fn parse_call_without_args() {
+        assert_eq!(
+            FuncCall::new("now()"),
+            Ok((
+                "",
+                FuncCall {
+                    callee: "now".to_string(),
+                    args: vec![],
+                    loc: None,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_call_with_args() {
+        assert_eq!(
+            FuncCall::new("add(1, a * 2,)"),
+            Ok((
+                "",
+                FuncCall {
+                    callee: "add".to_string(),
+                    args: vec![
+                        Expr::Number(Number(1)),
+                        Expr::Operation {
+                            lhs: Box::new(Expr::BindingUsage(BindingUsage {
+                                name: "a".to_string(),
+                                loc: None,
+                            })),
+                            rhs: Box::new(Expr::Number(Number(2))),
+                            op: Op::Mul,
+                        },
+                    ],
+                    loc: None,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_call_with_missing_comma() {
+        assert_eq!(FuncCall::new("add(1 2)"), Err("expected , or )".to_string()));
+    }
+
+    #[test]
+    fn display_call() {
+        let (_, call) = FuncCall::new("f( (1+2) *3,x )").unwrap();
+        assert_eq!(call.to_string(), "f((1 + 2) * 3, x)");
+    }
+
+    #[test]
+    fn eval_call_of_script_func() {
+        let mut env = Env::default();
+        env.store_func(
+            "double".to_string(),
+            vec!["x".to_string()],
+            Stmt::new("x * 2").unwrap().1,
+        );
+
+        let (_, call) = FuncCall::new("double(21)").unwrap();
+        assert_eq!(call.eval(&env), Ok(Val::Number(42)));
+    }
+}
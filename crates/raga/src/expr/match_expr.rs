@@ -0,0 +1,284 @@
+use crate::env::Env;
+use crate::error::EvalError;
+use crate::expr::Expr;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::pattern::Pattern;
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::val::Val;
+use std::fmt;
+
+/// Evaluates the body of the first arm whose pattern matches the value of
+/// the scrutinee and whose guard, if it has one, holds:
+///
+/// ```text
+/// match shape {
+///     Shape::Circle(r) => 3 * r * r,
+///     Shape::Rect(w, h) if w > 0 => w * h,
+///     _ => 0,
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub scrutinee: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+}
+
+/// An arm of a `match`. The names its pattern binds are in scope in the
+/// guard and the body, and nowhere else.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+impl Match {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::MatchExpr);
+        p.expect(TokenKind::MatchKw, "match")?;
+        Expr::parse(p)?;
+
+        p.start_node(NodeKind::MatchArmList);
+        p.expect(TokenKind::LBrace, "{")?;
+        while !p.at(TokenKind::RBrace) && p.peek().is_some() {
+            p.start_node(NodeKind::MatchArm);
+            Pattern::parse(p)?;
+            if p.at(TokenKind::IfKw) {
+                p.start_node(NodeKind::MatchGuard);
+                p.bump();
+                Expr::parse(p)?;
+                p.finish_node();
+            }
+            p.expect(TokenKind::FatArrow, "=>")?;
+            let body_is_block = p.at(TokenKind::LBrace);
+            Expr::parse(p)?;
+            p.finish_node();
+
+            // Arms are separated by commas, which may be left out after a
+            // block.
+            if p.at(TokenKind::Comma) {
+                p.bump();
+            } else if !body_is_block {
+                break;
+            }
+        }
+        p.expect(TokenKind::RBrace, ", or }")?;
+        p.finish_node();
+
+        p.finish_node();
+        Ok(())
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let mut children = node.children();
+        let scrutinee = Expr::lower(&children.next()?)?;
+        let arms = children
+            .next()?
+            .children()
+            .map(|arm| MatchArm::lower(&arm))
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            scrutinee: Box::new(scrutinee),
+            arms,
+        })
+    }
+
+    /// Evaluates the scrutinee once, then tries the arms in order, each in
+    /// a scope of its own.
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        let val = self.scrutinee.eval(env)?;
+
+        for arm in &self.arms {
+            let mut bound = Vec::new();
+            if !arm.pattern.matches(&val, &mut bound) {
+                continue;
+            }
+
+            let mut arm_env = env.create_child();
+            for (binding, val) in bound {
+                match binding.slot {
+                    Some(slot) => arm_env.store_local(slot, val)?,
+                    None => arm_env.bind(binding.name.clone(), val)?,
+                }
+            }
+            if let Some(guard) = &arm.guard {
+                if !guard_holds(guard.eval(&arm_env)?)? {
+                    continue;
+                }
+            }
+            return arm.body.eval(&arm_env);
+        }
+
+        Err(no_match(&val))
+    }
+}
+
+impl MatchArm {
+    fn lower(node: &SyntaxNode) -> Option<Self> {
+        let mut pattern = None;
+        let mut guard = None;
+        let mut body = None;
+        for child in node.children() {
+            match child.kind() {
+                NodeKind::MatchGuard => guard = Some(Expr::lower(&child.children().next()?)?),
+                _ if pattern.is_none() => pattern = Some(Pattern::lower(&child)?),
+                _ => body = Some(Expr::lower(&child)?),
+            }
+        }
+
+        Some(Self {
+            pattern: pattern?,
+            guard,
+            body: body?,
+        })
+    }
+}
+
+/// Whether the value of a guard lets its arm be taken.
+pub(crate) fn guard_holds(val: Val) -> Result<bool, EvalError> {
+    match val {
+        Val::Bool(holds) => Ok(holds),
+        val => Err(format!("expected Bool for match guard, found {}", val.type_name()).into()),
+    }
+}
+
+/// The error of a `match` that has no arm for `val`.
+pub(crate) fn no_match(val: &Val) -> EvalError {
+    format!("no match arm matches {}", val).into()
+}
+
+/// Prints each arm on its own line, indented by four spaces.
+impl fmt::Display for Match {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.arms.is_empty() {
+            return write!(f, "match {} {{}}", self.scrutinee);
+        }
+
+        writeln!(f, "match {} {{", self.scrutinee)?;
+        for arm in &self.arms {
+            let arm = format!("{},", arm);
+            for line in arm.lines() {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+impl fmt::Display for MatchArm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)?;
+        if let Some(guard) = &self.guard {
+            write!(f, " if {}", guard)?;
+        }
+        write!(f, " => {}", self.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BindingUsage, Number};
+    use crate::pattern::PatBinding;
+
+    fn eval(src: &str, env: &Env) -> Result<Val, EvalError> {
+        Match::new(src).unwrap().1.eval(env)
+    }
+
+    #[test]
+    fn parse_match() {
+        assert_eq!(
+            Match::new("match x { 0 => 1, n if t => n }"),
+            Ok((
+                "",
+                Match {
+                    scrutinee: Box::new(Expr::BindingUsage(BindingUsage {
+                        name: "x".to_string(),
+                        loc: None,
+                    })),
+                    arms: vec![
+                        MatchArm {
+                            pattern: Pattern::Number(0),
+                            guard: None,
+                            body: Expr::Number(Number(1)),
+                        },
+                        MatchArm {
+                            pattern: Pattern::Binding(PatBinding {
+                                name: "n".to_string(),
+                                slot: None,
+                            }),
+                            guard: Some(Expr::BindingUsage(BindingUsage {
+                                name: "t".to_string(),
+                                loc: None,
+                            })),
+                            body: Expr::BindingUsage(BindingUsage {
+                                name: "n".to_string(),
+                                loc: None,
+                            }),
+                        },
+                    ],
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn separate_arms_with_commas_unless_body_is_block() {
+        assert!(Match::new("match x {\n    0 => { 1 }\n    _ => 2\n}").is_ok());
+        assert_eq!(
+            Match::new("match x {\n    0 => 1\n    _ => 2\n}"),
+            Err("expected , or }".to_string())
+        );
+        assert_eq!(Match::new("match x { 0 1 }"), Err("expected =>".to_string()));
+    }
+
+    #[test]
+    fn display_match() {
+        for src in [
+            "match x {}",
+            "match (x, y) {\n    (0, _) => 1,\n    [a, ..] if t => {\n        a\n    },\n}",
+        ] {
+            assert_eq!(Match::new(src).unwrap().1.to_string(), src);
+        }
+    }
+
+    #[test]
+    fn eval_first_matching_arm() {
+        let mut env = Env::default();
+        env.store_binding("x".to_string(), Val::Number(2));
+        assert_eq!(eval("match x { 1 => 10, n => n * 100, _ => 0 }", &env), Ok(Val::Number(200)));
+        assert_eq!(
+            eval("match (x, 3) { (a, 4) => a, (a, b) => a + b }", &env),
+            Ok(Val::Number(5))
+        );
+        assert_eq!(eval("match x { n => n }", &env), Ok(Val::Number(2)));
+        assert_eq!(env.get_named_info("n"), None);
+    }
+
+    #[test]
+    fn eval_guards() {
+        let mut env = Env::default();
+        env.store_binding("yes".to_string(), Val::Bool(true));
+        env.store_binding("no".to_string(), Val::Bool(false));
+        assert_eq!(eval("match 1 { n if no => n, _ if yes => 2 }", &env), Ok(Val::Number(2)));
+        assert_eq!(eval("match 5 { n if n < 3 => n, n if n == 5 => 0, _ => 1 }", &env), Ok(Val::Number(0)));
+        assert_eq!(
+            eval("match 1 { n if n => n }", &env),
+            Err("expected Bool for match guard, found Number".into())
+        );
+    }
+
+    #[test]
+    fn fail_when_no_arm_matches() {
+        assert_eq!(
+            eval("match (1, 2) { (a,) => a }", &Env::default()),
+            Err("no match arm matches (1, 2)".into())
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use crate::env::Env;
+use crate::error::EvalError;
+use crate::expr::func_call::{self, FuncCall};
+use crate::expr::Expr;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{Checkpoint, NodeKind, SyntaxNode};
+use crate::val::Val;
+use std::fmt;
+
+/// Postfix operators bind tighter than any infix operator, so an operation
+/// has to be put in parentheses to have a field accessed on its value.
+pub(crate) const POSTFIX_BINDING_POWER: u8 = 7;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldAccess {
+    pub target: Box<Expr>,
+    pub field: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodCall {
+    pub target: Box<Expr>,
+    pub method: String,
+    pub args: Vec<Expr>,
+}
+
+/// Parses any number of `.field`, `.method(args)` and `?` suffixes after
+/// the expression that was started at `checkpoint`, each one wrapping what
+/// came before it.
+pub(crate) fn parse_postfix(p: &mut Parser, checkpoint: Checkpoint) -> Result<(), ParseError> {
+    while p.at(TokenKind::Dot) || p.at(TokenKind::Question) {
+        if p.at(TokenKind::Question) {
+            p.start_node_at(checkpoint, NodeKind::TryExpr);
+            p.bump();
+            p.finish_node();
+            continue;
+        }
+
+        p.bump();
+        p.expect(TokenKind::Ident, "field or method name")?;
+
+        if p.at_adjacent(TokenKind::LParen) {
+            p.start_node_at(checkpoint, NodeKind::MethodCallExpr);
+            FuncCall::parse_arg_list(p)?;
+        } else {
+            p.start_node_at(checkpoint, NodeKind::FieldExpr);
+        }
+        p.finish_node();
+    }
+
+    Ok(())
+}
+
+fn lower_target_and_name(node: &SyntaxNode) -> Option<(Box<Expr>, String)> {
+    let target = Expr::lower(&node.children().next()?)?;
+    let name = node.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+    Some((Box::new(target), name.text().to_string()))
+}
+
+impl FieldAccess {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Expr::parse, Self::lower)
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        if node.kind() != NodeKind::FieldExpr {
+            return None;
+        }
+
+        let (target, field) = lower_target_and_name(node)?;
+        Some(Self { target, field })
+    }
+
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        get_field(self.target.eval(env)?, &self.field, env)
+    }
+}
+
+impl fmt::Display for FieldAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.target.fmt_operand(f, POSTFIX_BINDING_POWER)?;
+        write!(f, ".{}", self.field)
+    }
+}
+
+impl MethodCall {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Expr::parse, Self::lower)
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        if node.kind() != NodeKind::MethodCallExpr {
+            return None;
+        }
+
+        let (target, method) = lower_target_and_name(node)?;
+        Some(Self {
+            target,
+            method,
+            args: FuncCall::lower_arg_list(node)?,
+        })
+    }
+
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        let target = self.target.eval(env)?;
+        let args = func_call::eval_args(&self.args, env)?;
+        call_method(target, &self.method, &args, env)
+    }
+}
+
+/// Reads `field` of an already evaluated `target`: a field of a struct or
+/// host object, or an entry of a map.
+pub(crate) fn get_field(target: Val, field: &str, env: &Env) -> Result<Val, EvalError> {
+    match target {
+        Val::Object(object) => {
+            let val = object.get_field(field).map_err(crate::Error::into_eval_error)?;
+            env.charge(&val)?;
+            Ok(val)
+        }
+        Val::Map(entries) => entries
+            .get(field)
+            .cloned()
+            .ok_or_else(|| format!("Map has no field '{}'", field).into()),
+        Val::Struct(val) => val
+            .get(field)
+            .cloned()
+            .ok_or_else(|| format!("{} has no field '{}'", val.name, field).into()),
+        val => Err(format!("cannot access field '{}' of {}", field, val.type_name()).into()),
+    }
+}
+
+/// Calls `method` on an already evaluated `target`, which has to be a host
+/// object.
+pub(crate) fn call_method(target: Val, method: &str, args: &[Val], env: &Env) -> Result<Val, EvalError> {
+    match target {
+        Val::Object(object) => {
+            let val = object
+                .call_method(method, args)
+                .map_err(crate::Error::into_eval_error)?;
+            env.charge(&val)?;
+            Ok(val)
+        }
+        val => Err(format!("cannot call method '{}' on {}", method, val.type_name()).into()),
+    }
+}
+
+impl fmt::Display for MethodCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.target.fmt_operand(f, POSTFIX_BINDING_POWER)?;
+        write!(f, ".{}", self.method)?;
+        func_call::fmt_args(f, &self.args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BindingUsage, Number, Op};
+    use crate::object::HostObject;
+    use crate::Error;
+    use std::collections::BTreeMap;
+    use std::rc::Rc;
+
+    struct Request {
+        path: String,
+    }
+
+    impl HostObject for Request {
+        fn type_name(&self) -> &'static str {
+            "Request"
+        }
+
+        fn get_field(&self, name: &str) -> Result<Val, Error> {
+            match name {
+                "path" => Ok(Val::from(self.path.as_str())),
+                _ => Err(Error::Eval(format!("Request has no field '{}'", name).into())),
+            }
+        }
+
+        fn call_method(&self, name: &str, args: &[Val]) -> Result<Val, Error> {
+            match (name, args) {
+                ("depth", []) => Ok(Val::Number(self.path.matches('/').count() as i32)),
+                ("startsWith", [Val::Str(prefix)]) => Ok(Val::Bool(self.path.starts_with(&**prefix))),
+                _ => Err(Error::Eval(format!("Request has no method '{}'", name).into())),
+            }
+        }
+    }
+
+    fn env_with_request() -> Env<'static> {
+        let mut env = Env::default();
+        env.store_binding(
+            "req".to_string(),
+            Val::object(Request {
+                path: "/api/users".to_string(),
+            }),
+        );
+        env
+    }
+
+    #[test]
+    fn parse_field_access() {
+        assert_eq!(
+            FieldAccess::new("req.path"),
+            Ok((
+                "",
+                FieldAccess {
+                    target: Box::new(Expr::BindingUsage(BindingUsage {
+                        name: "req".to_string(),
+                        loc: None,
+                    })),
+                    field: "path".to_string(),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_chained_method_calls() {
+        let (_, call) = MethodCall::new("a.b(1)\n  .c(2)").unwrap();
+        assert_eq!(call.method, "c");
+        assert_eq!(call.args, vec![Expr::Number(Number(2))]);
+        assert_eq!(call.to_string(), "a.b(1).c(2)");
+    }
+
+    #[test]
+    fn display_target_operation_in_parens() {
+        let access = FieldAccess {
+            target: Box::new(Expr::Operation {
+                lhs: Box::new(Expr::Number(Number(1))),
+                rhs: Box::new(Expr::Number(Number(2))),
+                op: Op::Add,
+            }),
+            field: "x".to_string(),
+        };
+        assert_eq!(access.to_string(), "(1 + 2).x");
+    }
+
+    #[test]
+    fn eval_field_and_method_of_object() {
+        let env = env_with_request();
+
+        assert_eq!(
+            FieldAccess::new("req.path").unwrap().1.eval(&env),
+            Ok(Val::Str("/api/users".into()))
+        );
+        assert_eq!(
+            MethodCall::new("req.depth()").unwrap().1.eval(&env),
+            Ok(Val::Number(2))
+        );
+        assert_eq!(
+            FieldAccess::new("req.body").unwrap().1.eval(&env),
+            Err("Request has no field 'body'".into())
+        );
+    }
+
+    #[test]
+    fn eval_field_of_map() {
+        let mut env = Env::default();
+        env.store_binding(
+            "config".to_string(),
+            Val::Map(Rc::new(BTreeMap::from([("limit".to_string(), Val::Number(10))]))),
+        );
+
+        assert_eq!(
+            FieldAccess::new("config.limit").unwrap().1.eval(&env),
+            Ok(Val::Number(10))
+        );
+    }
+
+    #[test]
+    fn eval_field_of_struct() {
+        let mut env = Env::default();
+        env.store_binding(
+            "p".to_string(),
+            Val::Struct(Rc::new(crate::StructVal {
+                name: "Point".to_string(),
+                fields: vec![("x".to_string(), Val::Number(1))],
+            })),
+        );
+
+        assert_eq!(FieldAccess::new("p.x").unwrap().1.eval(&env), Ok(Val::Number(1)));
+        assert_eq!(
+            FieldAccess::new("p.y").unwrap().1.eval(&env),
+            Err("Point has no field 'y'".into())
+        );
+        assert_eq!(
+            MethodCall::new("p.len()").unwrap().1.eval(&env),
+            Err("cannot call method 'len' on Point".into())
+        );
+    }
+
+    #[test]
+    fn eval_field_of_number() {
+        assert_eq!(
+            FieldAccess::new("1.x").unwrap().1.eval(&Env::default()),
+            Err("cannot access field 'x' of Number".into())
+        );
+    }
+}
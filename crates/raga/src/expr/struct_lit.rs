@@ -0,0 +1,240 @@
+use crate::env::Env;
+use crate::error::EvalError;
+use crate::expr::Expr;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::val::Val;
+use std::fmt;
+
+/// Builds a value of a struct, like `Point { x: 1, y: 2 }`. Fields that are
+/// left out are taken from the base after `..`, as in `Point { x: 5, ..p }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLit {
+    pub name: String,
+    pub fields: Vec<(String, Expr)>,
+    pub base: Option<Box<Expr>>,
+}
+
+impl StructLit {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    /// Whether the parser is at a struct literal rather than a name. Struct
+    /// names are capitalized, and the `{` has to be on the same line and
+    /// start the fields, a base or an empty literal, so that a name followed
+    /// by a block still parses as both.
+    pub(crate) fn at_start(p: &Parser) -> bool {
+        let is_capitalized = p
+            .peek_text()
+            .is_some_and(|name| name.starts_with(|c: char| c.is_ascii_uppercase()));
+
+        is_capitalized
+            && p.nth(1) == Some(TokenKind::LBrace)
+            && p.nth_on_same_line(1)
+            && matches!(
+                (p.nth(2), p.nth(3)),
+                (Some(TokenKind::Ident), Some(TokenKind::Colon))
+                    | (Some(TokenKind::DotDot), _)
+                    | (Some(TokenKind::RBrace), _)
+            )
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::StructLit);
+        p.expect(TokenKind::Ident, "struct name")?;
+        p.expect(TokenKind::LBrace, "{")?;
+
+        let mut names = Vec::new();
+        let mut after_comma = true;
+        while p.at(TokenKind::Ident) {
+            p.start_node(NodeKind::FieldInit);
+            let name = p.bump();
+            p.expect(TokenKind::Colon, ":")?;
+            Expr::parse(p)?;
+            p.finish_node();
+
+            if names.contains(&name.text) {
+                p.report(ParseError {
+                    message: format!("field '{}' is given twice", name.text),
+                    span: name.span,
+                });
+            }
+            names.push(name.text);
+
+            after_comma = p.at(TokenKind::Comma);
+            if !after_comma {
+                break;
+            }
+            p.bump();
+        }
+
+        if after_comma && p.at(TokenKind::DotDot) {
+            p.start_node(NodeKind::StructBase);
+            p.bump();
+            Expr::parse(p)?;
+            p.finish_node();
+            p.expect(TokenKind::RBrace, "}")?;
+        } else {
+            p.expect(TokenKind::RBrace, ", or }")?;
+        }
+        p.finish_node();
+        Ok(())
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let name = node.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+
+        let mut fields = Vec::new();
+        let mut base = None;
+        for child in node.children() {
+            match child.kind() {
+                NodeKind::FieldInit => {
+                    let name = child.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+                    let val = Expr::lower(&child.children().next()?)?;
+                    fields.push((name.text().to_string(), val));
+                }
+                NodeKind::StructBase => base = Some(Box::new(Expr::lower(&child.children().next()?)?)),
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            name: name.text().to_string(),
+            fields,
+            base,
+        })
+    }
+
+    /// Evaluates the fields in source order and then the base, before the
+    /// struct is looked up.
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, val)| Ok((name.clone(), val.eval(env)?)))
+            .collect::<Result<_, EvalError>>()?;
+        let base = self.base.as_ref().map(|base| base.eval(env)).transpose()?;
+
+        env.get_struct(&self.name)?.construct(fields, base)
+    }
+}
+
+impl fmt::Display for StructLit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.fields.is_empty() && self.base.is_none() {
+            return write!(f, "{} {{}}", self.name);
+        }
+
+        write!(f, "{} {{ ", self.name)?;
+        for (idx, (name, val)) in self.fields.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", name, val)?;
+        }
+        if let Some(base) = &self.base {
+            if !self.fields.is_empty() {
+                write!(f, ", ")?;
+            }
+            write!(f, "..{}", base)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BindingUsage, Number};
+
+    #[test]
+    fn parse_struct_lit() {
+        assert_eq!(
+            StructLit::new("Point { x: 1, y: 2 }"),
+            Ok((
+                "",
+                StructLit {
+                    name: "Point".to_string(),
+                    fields: vec![
+                        ("x".to_string(), Expr::Number(Number(1))),
+                        ("y".to_string(), Expr::Number(Number(2))),
+                    ],
+                    base: None,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_struct_lit_with_base() {
+        assert_eq!(
+            StructLit::new("Point { x: 5, ..p }"),
+            Ok((
+                "",
+                StructLit {
+                    name: "Point".to_string(),
+                    fields: vec![("x".to_string(), Expr::Number(Number(5)))],
+                    base: Some(Box::new(Expr::BindingUsage(BindingUsage {
+                        name: "p".to_string(),
+                        loc: None,
+                    }))),
+                }
+            ))
+        );
+        assert_eq!(
+            StructLit::new("Point { x: 5 ..p }"),
+            Err("expected , or }".to_string())
+        );
+    }
+
+    #[test]
+    fn display_struct_lits() {
+        for src in ["Point {}", "Point { ..p }", "Point { x: 1 + 2, ..p }", "Line { from: Point { x: 1 }, to: p }"] {
+            assert_eq!(StructLit::new(src).unwrap().1.to_string(), src);
+        }
+    }
+
+    #[test]
+    fn refuse_field_given_twice() {
+        assert_eq!(
+            StructLit::new("Point { x: 1, x: 2 }"),
+            Err("field 'x' is given twice".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_name_before_block_as_name() {
+        assert_eq!(
+            Expr::new("point {}"),
+            Ok((
+                " {}",
+                Expr::BindingUsage(BindingUsage {
+                    name: "point".to_string(),
+                    loc: None,
+                })
+            ))
+        );
+        assert_eq!(
+            Expr::new("Point { 1 }"),
+            Ok((
+                " { 1 }",
+                Expr::BindingUsage(BindingUsage {
+                    name: "Point".to_string(),
+                    loc: None,
+                })
+            ))
+        );
+        assert_eq!(
+            Expr::new("Point\n{}"),
+            Ok((
+                "\n{}",
+                Expr::BindingUsage(BindingUsage {
+                    name: "Point".to_string(),
+                    loc: None,
+                })
+            ))
+        );
+    }
+}
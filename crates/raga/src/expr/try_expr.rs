@@ -0,0 +1,126 @@
+use crate::env::Env;
+use crate::error::EvalError;
+use crate::expr::member::POSTFIX_BINDING_POWER;
+use crate::expr::Expr;
+use crate::parser;
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::val::Val;
+use std::fmt;
+use std::ops::ControlFlow;
+
+/// `value?`, which evaluates to what a `Some` or `Ok` holds, and returns a
+/// `None` or `Err` from the function it is in right away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Try {
+    pub inner: Box<Expr>,
+}
+
+impl Try {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Expr::parse, Self::lower)
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        if node.kind() != NodeKind::TryExpr {
+            return None;
+        }
+
+        let inner = Expr::lower(&node.children().next()?)?;
+        Some(Self { inner: Box::new(inner) })
+    }
+
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        match unwrap(self.inner.eval(env)?)? {
+            ControlFlow::Continue(val) => Ok(val),
+            ControlFlow::Break(val) => Err(EvalError::EarlyReturn(val)),
+        }
+    }
+}
+
+/// Continues with the value a `Some` or `Ok` holds, or breaks with a `None`
+/// or `Err` as it is.
+pub(crate) fn unwrap(val: Val) -> Result<ControlFlow<Val, Val>, EvalError> {
+    if let Val::Enum(variant) = &val {
+        match (variant.name.as_str(), variant.variant.as_str(), variant.fields.as_slice()) {
+            ("Option", "Some", [inner]) | ("Result", "Ok", [inner]) => {
+                return Ok(ControlFlow::Continue(inner.clone()));
+            }
+            ("Option", "None", []) | ("Result", "Err", [_]) => return Ok(ControlFlow::Break(val)),
+            _ => {}
+        }
+    }
+    Err(format!("expected Option or Result for ?, found {}", val.type_name()).into())
+}
+
+impl fmt::Display for Try {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt_operand(f, POSTFIX_BINDING_POWER)?;
+        write!(f, "?")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::{BindingUsage, FieldAccess};
+
+    fn env() -> Env<'static> {
+        let mut env = Env::default();
+        env.store_binding("found".to_string(), Val::some(1));
+        env.store_binding("missing".to_string(), Val::none());
+        env.store_binding("failed".to_string(), Val::err("bad"));
+        env
+    }
+
+    #[test]
+    fn parse_try_after_other_suffixes() {
+        assert_eq!(
+            Try::new("a.b?"),
+            Ok((
+                "",
+                Try {
+                    inner: Box::new(Expr::FieldAccess(FieldAccess {
+                        target: Box::new(Expr::BindingUsage(BindingUsage {
+                            name: "a".to_string(),
+                            loc: None,
+                        })),
+                        field: "b".to_string(),
+                    })),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn display_tries() {
+        for src in ["a??", "a?.b(1)?", "(a + b)?", "Some(1)?"] {
+            assert_eq!(Expr::new(src).unwrap().1.to_string(), src);
+        }
+    }
+
+    #[test]
+    fn unwrap_some_and_ok() {
+        assert_eq!(Try::new("found?").unwrap().1.eval(&env()), Ok(Val::Number(1)));
+        assert_eq!(Try::new("Ok(2)?").unwrap().1.eval(&env()), Ok(Val::Number(2)));
+    }
+
+    #[test]
+    fn return_none_and_err_early() {
+        assert_eq!(
+            Try::new("missing?").unwrap().1.eval(&env()),
+            Err(EvalError::EarlyReturn(Val::none()))
+        );
+        assert_eq!(
+            Try::new("failed?").unwrap().1.eval(&env()),
+            Err(EvalError::EarlyReturn(Val::err("bad")))
+        );
+    }
+
+    #[test]
+    fn refuse_other_values() {
+        assert_eq!(
+            Try::new("1?").unwrap().1.eval(&env()),
+            Err("expected Option or Result for ?, found Number".into())
+        );
+    }
+}
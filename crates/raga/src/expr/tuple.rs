@@ -0,0 +1,93 @@
+use crate::env::Env;
+use crate::error::EvalError;
+use crate::expr::func_call::eval_args;
+use crate::expr::Expr;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::SyntaxNode;
+use crate::val::Val;
+use std::fmt;
+
+/// A fixed number of values in parentheses, like `(1, x)`. One value needs
+/// a trailing comma, as in `(1,)`, to tell it apart from a parenthesized
+/// expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuple {
+    pub items: Vec<Expr>,
+}
+
+impl Tuple {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    /// Tuples are parsed along with parenthesized expressions, which they
+    /// start out like.
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        Expr::parse_paren_or_tuple(p)
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let items = node.children().map(|item| Expr::lower(&item)).collect::<Option<_>>()?;
+        Some(Self { items })
+    }
+
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        let items = eval_args(&self.items, env)?;
+        let val = Val::Tuple(items.into());
+        env.charge(&val)?;
+        Ok(val)
+    }
+}
+
+impl fmt::Display for Tuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        for (idx, item) in self.items.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        if self.items.len() == 1 {
+            write!(f, ",")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Number;
+
+    #[test]
+    fn parse_tuples() {
+        assert_eq!(
+            Tuple::new("(1, 2,)"),
+            Ok((
+                "",
+                Tuple {
+                    items: vec![Expr::Number(Number(1)), Expr::Number(Number(2))],
+                }
+            ))
+        );
+        assert_eq!(Expr::new("(1)"), Ok(("", Expr::Number(Number(1)))));
+        assert_eq!(Tuple::new("(1, 2 3)"), Err("expected , or )".to_string()));
+    }
+
+    #[test]
+    fn display_tuples() {
+        for src in ["(1,)", "(1, (2, 3))"] {
+            assert_eq!(Tuple::new(src).unwrap().1.to_string(), src);
+        }
+    }
+
+    #[test]
+    fn eval_tuple() {
+        let val = Tuple::new("(1, 2)").unwrap().1.eval(&Env::default()).unwrap();
+        assert_eq!(val, Val::Tuple(vec![Val::Number(1), Val::Number(2)].into()));
+        assert_ne!(val, Val::List(vec![Val::Number(1), Val::Number(2)].into()));
+        assert_eq!(val.to_string(), "(1, 2)");
+        assert_eq!(Tuple::new("(1,)").unwrap().1.eval(&Env::default()).unwrap().to_string(), "(1,)");
+    }
+}
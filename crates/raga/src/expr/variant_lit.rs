@@ -0,0 +1,145 @@
+use crate::enum_def;
+use crate::env::Env;
+use crate::error::EvalError;
+use crate::expr::func_call::{eval_args, fmt_args, FuncCall};
+use crate::expr::Expr;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::val::Val;
+use std::fmt;
+
+/// Builds a variant of an enum, like `Shape::Rect(1, 2)`, or `Shape::Empty`
+/// for a variant without values. Variants of the built-in `Option` and
+/// `Result` enums are written without the enum name, as in `Some(1)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantLit {
+    pub enum_name: String,
+    pub variant: String,
+    pub args: Vec<Expr>,
+}
+
+impl VariantLit {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    /// Whether the parser is at a variant rather than a name.
+    pub(crate) fn at_start(p: &Parser) -> bool {
+        p.at(TokenKind::Ident)
+            && (p.nth(1) == Some(TokenKind::ColonColon) || p.peek_text().and_then(enum_def::prelude_enum).is_some())
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::VariantLit);
+        if p.nth(1) == Some(TokenKind::ColonColon) {
+            p.expect(TokenKind::Ident, "enum name")?;
+            p.bump();
+        }
+        p.expect(TokenKind::Ident, "variant name")?;
+        if p.at_adjacent(TokenKind::LParen) {
+            FuncCall::parse_arg_list(p)?;
+        }
+        p.finish_node();
+        Ok(())
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let (enum_name, variant) = enum_def::lower_path(node)?;
+        let has_args = node.children().any(|child| child.kind() == NodeKind::ArgList);
+
+        Some(Self {
+            enum_name,
+            variant,
+            args: if has_args { FuncCall::lower_arg_list(node)? } else { Vec::new() },
+        })
+    }
+
+    /// Evaluates the values in source order before the enum is looked up.
+    pub fn eval(&self, env: &Env) -> Result<Val, EvalError> {
+        let args = eval_args(&self.args, env)?;
+        env.get_enum(&self.enum_name)?.construct(&self.variant, args)
+    }
+}
+
+impl fmt::Display for VariantLit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if enum_def::prelude_enum(&self.variant) != Some(self.enum_name.as_str()) {
+            write!(f, "{}::", self.enum_name)?;
+        }
+        write!(f, "{}", self.variant)?;
+        if self.args.is_empty() {
+            return Ok(());
+        }
+        fmt_args(f, &self.args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::Number;
+
+    #[test]
+    fn parse_variant_lits() {
+        assert_eq!(
+            VariantLit::new("Shape::Rect(1, 2)"),
+            Ok((
+                "",
+                VariantLit {
+                    enum_name: "Shape".to_string(),
+                    variant: "Rect".to_string(),
+                    args: vec![Expr::Number(Number(1)), Expr::Number(Number(2))],
+                }
+            ))
+        );
+        assert_eq!(
+            VariantLit::new("Shape::Empty (1)"),
+            Ok((
+                " (1)",
+                VariantLit {
+                    enum_name: "Shape".to_string(),
+                    variant: "Empty".to_string(),
+                    args: vec![],
+                }
+            ))
+        );
+        assert_eq!(VariantLit::new("Shape::"), Err("expected variant name".to_string()));
+    }
+
+    #[test]
+    fn parse_prelude_variants_without_enum_name() {
+        assert_eq!(
+            VariantLit::new("Some(1)"),
+            Ok((
+                "",
+                VariantLit {
+                    enum_name: "Option".to_string(),
+                    variant: "Some".to_string(),
+                    args: vec![Expr::Number(Number(1))],
+                }
+            ))
+        );
+        assert_eq!(VariantLit::new("None").unwrap().1.enum_name, "Option");
+        assert_eq!(VariantLit::new("Result::Err(1)").unwrap().1, VariantLit::new("Err(1)").unwrap().1);
+    }
+
+    #[test]
+    fn display_variant_lits() {
+        for src in ["Shape::Empty", "Shape::Rect(1 + 2, Shape::Empty)", "Ok(None)", "Shape::Some"] {
+            assert_eq!(VariantLit::new(src).unwrap().1.to_string(), src);
+        }
+    }
+
+    #[test]
+    fn eval_variant_of_undefined_enum() {
+        assert_eq!(
+            VariantLit::new("Shape::Empty").unwrap().1.eval(&Env::default()),
+            Err("enum with name 'Shape' does not exist".into())
+        );
+        assert_eq!(
+            VariantLit::new("Err(1)").unwrap().1.eval(&Env::default()),
+            Ok(Val::err(1))
+        );
+    }
+}
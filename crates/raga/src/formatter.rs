@@ -0,0 +1,344 @@
+//! Canonical source formatter.
+//!
+//! Formatting works on the lossless syntax tree so comments survive. The
+//! canonical style puts one statement per line and one `match` arm per line,
+//! each followed by a comma, indents block and `match` contents by four
+//! spaces, separates tokens by a single space (except just inside
+//! parentheses, brackets and empty braces, before a comma or `?`, around the
+//! dot of a field access and the `::` of a variant, after the `..` of a
+//! struct base or list pattern and between a callee or variant and its
+//! arguments) and keeps at most one blank line between statements.
+
+use crate::lexer::TokenKind;
+use crate::syntax::{NodeKind, SyntaxElement, SyntaxNode};
+use crate::ParseError;
+
+const INDENT: &str = "    ";
+
+/// Formats `input` in the canonical style. Input with syntax errors is left
+/// alone and its errors are returned instead.
+pub fn format(input: &str) -> Result<String, Vec<ParseError>> {
+    let parse = crate::parse(input);
+    if !parse.errors().is_empty() {
+        return Err(parse.errors().to_vec());
+    }
+
+    let mut formatter = Formatter::default();
+    formatter.fmt_sequence(parse.syntax().children_with_tokens(), "");
+    if !formatter.out.is_empty() {
+        formatter.out.push('\n');
+    }
+
+    Ok(formatter.out)
+}
+
+#[derive(Default)]
+struct Formatter {
+    out: String,
+    indent: usize,
+    /// Set after a comment inside a statement, so the rest of the statement
+    /// has to continue on the next line.
+    pending_break: bool,
+}
+
+impl Formatter {
+    fn fmt_node(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            NodeKind::Block => self.fmt_block(node, ""),
+            NodeKind::MatchArmList => self.fmt_block(node, ","),
+            _ => self.fmt_inline(node),
+        }
+    }
+
+    /// Prints statements and comments one per line, keeping comments that
+    /// followed a statement on the same line there. Each statement is
+    /// followed by `separator`.
+    fn fmt_sequence(&mut self, elements: impl Iterator<Item = SyntaxElement>, separator: &str) {
+        let mut is_first = true;
+        let mut newlines = 0;
+
+        for element in elements {
+            match element {
+                SyntaxElement::Token(token) if token.kind() == TokenKind::Whitespace => {
+                    newlines += token.text().matches('\n').count();
+                }
+                SyntaxElement::Token(token) if token.kind() == TokenKind::Comment && !is_first && newlines == 0 => {
+                    self.out.push(' ');
+                    self.out.push_str(token.text());
+                }
+                SyntaxElement::Token(token) => {
+                    self.start_line(is_first, newlines);
+                    self.out.push_str(token.text());
+                    is_first = false;
+                    newlines = 0;
+                }
+                SyntaxElement::Node(node) => {
+                    self.start_line(is_first, newlines);
+                    self.fmt_node(&node);
+                    self.out.push_str(separator);
+                    is_first = false;
+                    newlines = 0;
+                }
+            }
+        }
+    }
+
+    /// Prints the contents of a block, or the arms of a `match` with
+    /// `separator` being the comma after each of them.
+    fn fmt_block(&mut self, node: &SyntaxNode, separator: &str) {
+        let contents: Vec<_> = node
+            .children_with_tokens()
+            .filter(|element| match element {
+                SyntaxElement::Token(token) => {
+                    !matches!(token.kind(), TokenKind::LBrace | TokenKind::RBrace | TokenKind::Comma)
+                }
+                SyntaxElement::Node(_) => true,
+            })
+            .collect();
+
+        self.emit("{");
+
+        let is_empty = contents.iter().all(|element| {
+            matches!(element, SyntaxElement::Token(token) if token.kind() == TokenKind::Whitespace)
+        });
+        if !is_empty {
+            self.indent += 1;
+            self.newline();
+            self.fmt_sequence(contents.into_iter(), separator);
+            self.indent -= 1;
+            self.newline();
+        }
+
+        self.out.push('}');
+    }
+
+    fn fmt_inline(&mut self, node: &SyntaxNode) {
+        for element in node.children_with_tokens() {
+            match element {
+                SyntaxElement::Token(token) => match token.kind() {
+                    TokenKind::Whitespace => {}
+                    TokenKind::Comment => {
+                        self.emit(token.text());
+                        self.pending_break = true;
+                    }
+                    TokenKind::Comma
+                    | TokenKind::Dot
+                    | TokenKind::Colon
+                    | TokenKind::ColonColon
+                    | TokenKind::Question => self.glue(token.text()),
+                    TokenKind::Ident if matches!(node.kind(), NodeKind::FieldExpr | NodeKind::MethodCallExpr) => {
+                        self.glue(token.text())
+                    }
+                    TokenKind::Ident if self.out.ends_with("::") => self.glue(token.text()),
+                    TokenKind::Number
+                        if matches!(node.kind(), NodeKind::Literal | NodeKind::LiteralPat)
+                            && node.child_tokens().any(|token| token.kind() == TokenKind::Minus) =>
+                    {
+                        self.glue(token.text())
+                    }
+                    TokenKind::LParen
+                        if matches!(node.kind(), NodeKind::ArgList | NodeKind::VariantPat | NodeKind::VariantDef) =>
+                    {
+                        self.glue(token.text())
+                    }
+                    TokenKind::RBrace if self.out.ends_with('{') => self.glue(token.text()),
+                    _ => self.emit(token.text()),
+                },
+                SyntaxElement::Node(node) => self.fmt_node(&node),
+            }
+        }
+    }
+
+    /// Appends a token, separated from the previous one by a single space
+    /// unless it sits just inside parentheses or brackets, follows `..` or
+    /// starts a line.
+    fn emit(&mut self, text: &str) {
+        if self.pending_break {
+            self.pending_break = false;
+            self.indent += 1;
+            self.newline();
+            self.indent -= 1;
+        } else if !text.starts_with([')', ']'])
+            && !self.out.ends_with("..")
+            && self.out.ends_with(|c: char| !c.is_whitespace() && c != '(' && c != '[')
+        {
+            self.out.push(' ');
+        }
+
+        self.out.push_str(text);
+    }
+
+    /// Appends a token directly after the previous one.
+    fn glue(&mut self, text: &str) {
+        if self.pending_break {
+            self.pending_break = false;
+            self.indent += 1;
+            self.newline();
+            self.indent -= 1;
+        }
+
+        self.out.push_str(text);
+    }
+
+    fn start_line(&mut self, is_first: bool, newlines: usize) {
+        self.pending_break = false;
+        if is_first {
+            return;
+        }
+
+        if newlines > 1 {
+            self.out.push('\n');
+        }
+        self.newline();
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(input: &str, expected: &str) {
+        let formatted = format(input).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted, "formatting is not idempotent");
+    }
+
+    #[test]
+    fn format_empty_input() {
+        check("", "");
+        check(" \n\n ", "");
+    }
+
+    #[test]
+    fn format_spaces_around_ops() {
+        check("1+2*  3", "1 + 2 * 3\n");
+        check("1- -2*( - 3)", "1 - -2 * (-3)\n");
+        check("a<=b+1==true!=(c>-1)", "a <= b + 1 == true != (c > -1)\n");
+    }
+
+    #[test]
+    fn format_parens_without_inner_spaces() {
+        check("( 1+2 )*3", "(1 + 2) * 3\n");
+    }
+
+    #[test]
+    fn format_func_calls() {
+        check("f( 1 ,g( ),(2) )", "f(1, g(), (2))\n");
+    }
+
+    #[test]
+    fn format_field_access_and_method_calls() {
+        check("req . path\nreq\n  .header( 1 ).len()", "req.path\nreq.header(1).len()\n");
+    }
+
+    #[test]
+    fn format_binding_def() {
+        check("let   a=10", "let a = 10\n");
+    }
+
+    #[test]
+    fn format_one_stmt_per_line() {
+        check("let a = 1 let b = 2   a", "let a = 1\nlet b = 2\na\n");
+    }
+
+    #[test]
+    fn format_params_on_one_line() {
+        check("fn add  x\n   y=>x+y", "fn add x y => x + y\n");
+    }
+
+    #[test]
+    fn format_type_annotations() {
+        check("let x :Int=5", "let x: Int = 5\n");
+        check("fn add a:Int b :Int->Int=>a+b", "fn add a: Int b: Int -> Int => a + b\n");
+    }
+
+    #[test]
+    fn format_structs() {
+        check("struct Point{x:Int,y :Int}", "struct Point { x: Int, y: Int }
+");
+        check("struct Empty {  }
+Empty {  }", "struct Empty {}
+Empty {}
+");
+        check("let q = Point{x:1, .. p}", "let q = Point { x: 1, ..p }
+");
+    }
+
+    #[test]
+    fn format_enums_and_matches() {
+        check("enum Shape{Circle(r:Int),Empty }", "enum Shape { Circle(r: Int), Empty }\n");
+        check(
+            "match s{Shape :: Circle( r )if r=>r , Shape::Empty=>{0} (a,[ - 1,.. rest ])=>a, _=>( 1 , )}",
+            "match s {\n    Shape::Circle(r) if r => r,\n    Shape::Empty => {\n        0\n    },\n    (a, [-1, ..rest]) => a,\n    _ => (1,),\n}\n",
+        );
+        check("match x {  }\nShape::Rect( 1,2 )", "match x {}\nShape::Rect(1, 2)\n");
+        check("Some( f(x) ? .y ?)", "Some(f(x)?.y?)\n");
+        check(
+            "match x { # arms\n  0 => 1, # zero\n\n  _ => 2 }",
+            "match x {\n    # arms\n    0 => 1, # zero\n\n    _ => 2,\n}\n",
+        );
+    }
+
+    #[test]
+    fn format_empty_block() {
+        check("fn f => {   \n }", "fn f => {}\n");
+    }
+
+    #[test]
+    fn format_indented_block_contents() {
+        check(
+            "let a = { let b = 1 {b\n} }",
+            "let a = {\n    let b = 1\n    {\n        b\n    }\n}\n",
+        );
+    }
+
+    #[test]
+    fn format_keeps_single_blank_line() {
+        check("let a = 1\n\n\n\nlet b = 2\n\n", "let a = 1\n\nlet b = 2\n");
+    }
+
+    #[test]
+    fn format_drops_blank_lines_at_start_of_block() {
+        check("{\n\n  1\n\n  2\n\n}", "{\n    1\n\n    2\n}\n");
+    }
+
+    #[test]
+    fn format_keeps_comments() {
+        check(
+            "# header\nlet a = 1   # trailing\n\n{ # opening\n  a # in block\n  # before brace\n}",
+            "# header\nlet a = 1 # trailing\n\n{\n    # opening\n    a # in block\n    # before brace\n}\n",
+        );
+    }
+
+    #[test]
+    fn format_breaks_line_after_comment_inside_stmt() {
+        check("let a = # why\n  1 +\n 2", "let a = # why\n    1 + 2\n");
+    }
+
+    #[test]
+    fn format_every_valid_prefix_idempotently_and_keep_meaning() {
+        let program = "fn f x # the x\n y => { let z = ( x # c\n+ 1) * y\n\n\n z }\nlet a = {\n f(1 # one\n, 2) # call\n} # end\n(a)";
+        for end in 0..=program.len() {
+            let input = &program[..end];
+            if let Ok(formatted) = format(input) {
+                assert_eq!(format(&formatted).unwrap(), formatted, "input: {:?}", input);
+                assert_eq!(crate::parse(&formatted).stmts(), crate::parse(input).stmts());
+            }
+        }
+    }
+
+    #[test]
+    fn refuse_to_format_input_with_errors() {
+        assert_eq!(
+            format("let = 1").map_err(|errors| errors.len()),
+            Err(1)
+        );
+    }
+}
@@ -1,47 +1,150 @@
+use crate::enum_def;
+use crate::error::EvalError;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
 use crate::stmt::Stmt;
-use crate::{Env, utils};
+use crate::syntax::{NodeKind, SyntaxElement, SyntaxNode};
+use crate::type_ann::{Subject, TypeAnn};
+use crate::{Env, Val};
+use std::fmt;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FuncDef {
     pub name: String,
     pub params: Vec<String>,
-    pub body: Box<Stmt>,
+    /// The annotated types, if any parameter or the result has one.
+    pub signature: Option<Rc<Signature>>,
+    pub body: Rc<Stmt>,
+}
+
+/// The types a function's parameters and result are annotated with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Signature {
+    /// One entry for each parameter, `None` where it has no annotation.
+    pub params: Vec<Option<TypeAnn>>,
+    pub ret: Option<TypeAnn>,
+}
+
+impl Signature {
+    /// Fails unless the arguments of a call to the function `name` have
+    /// the types its parameters are annotated with.
+    pub(crate) fn check_args(&self, name: &str, params: &[String], args: &[Val]) -> Result<(), EvalError> {
+        for ((ty, param), arg) in self.params.iter().zip(params).zip(args) {
+            if let Some(ty) = ty {
+                ty.check(arg, || Subject::Param {
+                    func: name.to_string(),
+                    param: param.clone(),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_result(&self, name: &str, result: &Val) -> Result<(), EvalError> {
+        match &self.ret {
+            Some(ty) => ty.check(result, || Subject::Result(name.to_string())),
+            None => Ok(()),
+        }
+    }
 }
 
 impl FuncDef {
-    pub(crate) fn new(s: &str) -> Result<(&str, Self), String> {
-        let s = utils::tag("fn", s)?;
-        let (s, _) = utils::extract_whitespace(s)?;
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
 
-        let (s, name) = utils::extract_ident(s)?;
-        let (s, _) = utils::skip_whitespace(s);
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::FuncDef);
+        p.expect(TokenKind::FnKw, "fn")?;
+        let name = p.expect(TokenKind::Ident, "identifier")?;
+        enum_def::check_def_name(p, name);
 
-        let (s, params) = utils::sequence(
-            |s| utils::extract_ident(s).map(|(s, ident)| (s, ident.to_string())),
-            s
-        )?;
+        p.start_node(NodeKind::ParamList);
+        while p.at(TokenKind::Ident) {
+            let param = p.bump();
+            enum_def::check_def_name(p, param);
+            if p.at(TokenKind::Colon) {
+                TypeAnn::parse(p, TokenKind::Colon, ":")?;
+            }
+        }
+        p.finish_node();
 
-        let s = utils::tag("=>", s)?;
-        let (s, _) = utils::skip_whitespace(s);
+        if p.at(TokenKind::Arrow) {
+            TypeAnn::parse(p, TokenKind::Arrow, "->")?;
+        }
+        p.expect(TokenKind::FatArrow, "=>")?;
+        Stmt::parse(p)?;
+        p.finish_node();
+        Ok(())
+    }
 
-        let (s, body) = Stmt::new(s)?;
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let name = node.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
 
-        Ok((
-            s,
-            Self {
-                name: name.to_string(),
-                params,
-                body: Box::new(body),
+        let mut children = node.children();
+        let param_list = children
+            .next()
+            .filter(|param_list| param_list.kind() == NodeKind::ParamList)?;
+
+        // Each annotation follows the parameter it belongs to.
+        let mut params = Vec::new();
+        let mut signature = Signature::default();
+        for element in param_list.children_with_tokens() {
+            match element {
+                SyntaxElement::Token(token) if token.kind() == TokenKind::Ident => {
+                    params.push(token.text().to_string());
+                    signature.params.push(None);
+                }
+                SyntaxElement::Node(ty) if ty.kind() == NodeKind::TypeAnn => {
+                    *signature.params.last_mut()? = TypeAnn::lower(&ty);
+                }
+                _ => {}
             }
-        ))
+        }
+
+        let mut rest = children.peekable();
+        if let Some(ret) = rest.next_if(|child| child.kind() == NodeKind::TypeAnn) {
+            signature.ret = TypeAnn::lower(&ret);
+        }
+        let body = Stmt::lower(&rest.next()?)?;
+
+        let is_annotated = signature.ret.is_some() || signature.params.iter().any(Option::is_some);
+        Some(Self {
+            name: name.text().to_string(),
+            params,
+            signature: is_annotated.then(|| Rc::new(signature)),
+            body: Rc::new(body),
+        })
+    }
+
+    /// The type the parameter at `idx` is annotated with.
+    pub fn param_type(&self, idx: usize) -> Option<&TypeAnn> {
+        self.signature.as_ref()?.params.get(idx)?.as_ref()
     }
 
-    pub(crate) fn eval(&self, env: &mut Env) -> Result<(), String> {
-        env.store_func(self.name.clone(), self.params.clone(), *self.body.clone());
+    pub(crate) fn eval(&self, env: &mut Env) -> Result<(), EvalError> {
+        env.store_func_def(self);
         Ok(())
     }
 }
 
+impl fmt::Display for FuncDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fn {}", self.name)?;
+        for (idx, param) in self.params.iter().enumerate() {
+            write!(f, " {}", param)?;
+            if let Some(ty) = self.param_type(idx) {
+                write!(f, ": {}", ty)?;
+            }
+        }
+        if let Some(ty) = self.signature.as_ref().and_then(|signature| signature.ret.as_ref()) {
+            write!(f, " -> {}", ty)?;
+        }
+        write!(f, " => {}", self.body)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{Env, Val};
@@ -57,7 +160,8 @@ mod tests {
                 FuncDef {
                     name: "newEmpty".to_string(),
                     params: vec![],
-                    body: Box::new(Stmt::Expr(Expr::Block(Block { stmts: vec![] }))),
+                    signature: None,
+                    body: Rc::new(Stmt::Expr(Expr::Block(Block { stmts: vec![] }))),
                 }
             ))
         )
@@ -72,7 +176,8 @@ mod tests {
                 FuncDef {
                     name: "newEmpty".to_string(),
                     params: vec!["x".to_string()],
-                    body: Box::new(Stmt::Expr(Expr::Block(Block { stmts: vec![] }))),
+                    signature: None,
+                    body: Rc::new(Stmt::Expr(Expr::Block(Block { stmts: vec![] }))),
                 }
             ))
         )
@@ -87,12 +192,15 @@ mod tests {
                 FuncDef {
                     name: "newEmpty".to_string(),
                     params: vec!["x".to_string(), "y".to_string()],
-                    body: Box::new(Stmt::Expr(Expr::Operation {
+                    signature: None,
+                    body: Rc::new(Stmt::Expr(Expr::Operation {
                         lhs: Box::new(Expr::BindingUsage(BindingUsage {
-                            name: "x".to_string()
+                            name: "x".to_string(),
+                            loc: None,
                         })),
                         rhs: Box::new(Expr::BindingUsage(BindingUsage {
-                            name: "y".to_string()
+                            name: "y".to_string(),
+                            loc: None,
                         })),
                         op: Op::Add
                     }))
@@ -107,9 +215,57 @@ mod tests {
             Stmt::FuncDef(FuncDef {
                 name: "return_one".to_string(),
                 params: vec![],
-                body: Box::new(Stmt::Expr(Expr::Number(Number(1))))
+                signature: None,
+                body: Rc::new(Stmt::Expr(Expr::Number(Number(1))))
             }).eval(&mut Env::default()),
             Ok(Val::Unit)
         )
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn share_body_with_defined_function() {
+        let (_, func_def) = FuncDef::new("fn double x => x * 2").unwrap();
+        let mut env = Env::default();
+        func_def.eval(&mut env).unwrap();
+
+        let (_, body) = env.get_func("double").unwrap();
+        assert!(Rc::ptr_eq(&body, &func_def.body));
+    }
+
+    #[test]
+    fn parse_annotated_func_def() {
+        let (_, func_def) = FuncDef::new("fn add a: Int b -> Int => a + b").unwrap();
+        assert_eq!(func_def.params, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(
+            func_def.signature.as_deref(),
+            Some(&Signature {
+                params: vec![
+                    Some(TypeAnn {
+                        name: "Int".to_string(),
+                    }),
+                    None,
+                ],
+                ret: Some(TypeAnn {
+                    name: "Int".to_string(),
+                }),
+            })
+        );
+        assert_eq!(func_def.to_string(), "fn add a: Int b -> Int => a + b");
+    }
+
+    #[test]
+    fn check_annotated_types_at_call() {
+        let (_, func_def) = FuncDef::new("fn wrap x: Int -> Unit => x").unwrap();
+        let mut env = Env::default();
+        func_def.eval(&mut env).unwrap();
+
+        assert_eq!(
+            env.call_func("wrap", vec![Val::Unit]),
+            Err("expected Int for parameter 'x' of 'wrap', found Unit".into())
+        );
+        assert_eq!(
+            env.call_func("wrap", vec![Val::Number(1)]),
+            Err("expected Unit for result of 'wrap', found Number".into())
+        );
+    }
+}
@@ -0,0 +1,385 @@
+use crate::utils;
+
+pub const KEYWORDS: &[&str] = &["let", "fn", "struct", "enum", "match", "if", "true", "false"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    LetKw,
+    FnKw,
+    StructKw,
+    EnumKw,
+    MatchKw,
+    IfKw,
+    TrueKw,
+    FalseKw,
+    Ident,
+    Number,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Equals,
+    EqEq,
+    NotEq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    FatArrow,
+    Arrow,
+    Colon,
+    ColonColon,
+    Comma,
+    Dot,
+    DotDot,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Underscore,
+    Question,
+    Whitespace,
+    Comment,
+    Error,
+}
+
+impl TokenKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Self::Whitespace | Self::Comment)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// One-based line and column at which the span starts within `input`.
+    pub fn line_col(&self, input: &str) -> (usize, usize) {
+        let before = &input[..self.start];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+
+        let line = before.matches('\n').count() + 1;
+        let col = before[line_start..].chars().count() + 1;
+        (line, col)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+pub struct Lexer<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { input, offset: 0 }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.input[self.offset..];
+        let (kind, len) = next_token(rest)?;
+
+        let start = self.offset;
+        self.offset += len;
+
+        Some(Token {
+            kind,
+            text: &self.input[start..self.offset],
+            span: Span {
+                start,
+                end: self.offset,
+            },
+        })
+    }
+}
+
+pub fn lex(input: &str) -> Vec<Token<'_>> {
+    Lexer::new(input).collect()
+}
+
+fn next_token(s: &str) -> Option<(TokenKind, usize)> {
+    let c = s.chars().next()?;
+
+    if c.is_whitespace() {
+        let (_, whitespace) = utils::take_while(|c| c.is_whitespace(), s);
+        return Some((TokenKind::Whitespace, whitespace.len()));
+    }
+
+    if c == '#' {
+        let (_, comment) = utils::take_while(|c| c != '\n', s);
+        return Some((TokenKind::Comment, comment.len()));
+    }
+
+    if let Ok((_, digits)) = utils::extract_digits(s) {
+        return Some((TokenKind::Number, digits.len()));
+    }
+
+    if let Ok((_, ident)) = utils::extract_ident(s) {
+        let kind = match ident {
+            "let" => TokenKind::LetKw,
+            "fn" => TokenKind::FnKw,
+            "struct" => TokenKind::StructKw,
+            "enum" => TokenKind::EnumKw,
+            "match" => TokenKind::MatchKw,
+            "if" => TokenKind::IfKw,
+            "true" => TokenKind::TrueKw,
+            "false" => TokenKind::FalseKw,
+            _ => TokenKind::Ident,
+        };
+        return Some((kind, ident.len()));
+    }
+
+    if s.starts_with("=>") {
+        return Some((TokenKind::FatArrow, 2));
+    }
+
+    if s.starts_with("==") {
+        return Some((TokenKind::EqEq, 2));
+    }
+
+    if s.starts_with("!=") {
+        return Some((TokenKind::NotEq, 2));
+    }
+
+    if s.starts_with("<=") {
+        return Some((TokenKind::LessEq, 2));
+    }
+
+    if s.starts_with(">=") {
+        return Some((TokenKind::GreaterEq, 2));
+    }
+
+    if s.starts_with("->") {
+        return Some((TokenKind::Arrow, 2));
+    }
+
+    if s.starts_with("..") {
+        return Some((TokenKind::DotDot, 2));
+    }
+
+    if s.starts_with("::") {
+        return Some((TokenKind::ColonColon, 2));
+    }
+
+    let kind = match c {
+        '+' => TokenKind::Plus,
+        '-' => TokenKind::Minus,
+        '*' => TokenKind::Star,
+        '/' => TokenKind::Slash,
+        '=' => TokenKind::Equals,
+        '<' => TokenKind::Less,
+        '>' => TokenKind::Greater,
+        ':' => TokenKind::Colon,
+        ',' => TokenKind::Comma,
+        '.' => TokenKind::Dot,
+        '(' => TokenKind::LParen,
+        ')' => TokenKind::RParen,
+        '{' => TokenKind::LBrace,
+        '}' => TokenKind::RBrace,
+        '[' => TokenKind::LBracket,
+        ']' => TokenKind::RBracket,
+        '_' => TokenKind::Underscore,
+        '?' => TokenKind::Question,
+        _ => TokenKind::Error,
+    };
+
+    Some((kind, c.len_utf8()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<(TokenKind, &str)> {
+        lex(input).into_iter().map(|t| (t.kind, t.text)).collect()
+    }
+
+    #[test]
+    fn lex_nothing() {
+        assert_eq!(kinds(""), vec![]);
+    }
+
+    #[test]
+    fn lex_binding_def() {
+        assert_eq!(
+            kinds("let a = 10"),
+            vec![
+                (TokenKind::LetKw, "let"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Ident, "a"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Equals, "="),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Number, "10"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_keyword_prefix_as_ident() {
+        assert_eq!(
+            kinds("letter fnord structure enumerate matches iffy trueish falsely"),
+            vec![
+                (TokenKind::Ident, "letter"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Ident, "fnord"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Ident, "structure"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Ident, "enumerate"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Ident, "matches"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Ident, "iffy"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Ident, "trueish"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Ident, "falsely"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_fat_arrow_before_equals() {
+        assert_eq!(
+            kinds("=>="),
+            vec![(TokenKind::FatArrow, "=>"), (TokenKind::Equals, "=")]
+        );
+    }
+
+    #[test]
+    fn lex_double_equals_before_fat_arrow() {
+        assert_eq!(
+            kinds("==>"),
+            vec![(TokenKind::EqEq, "=="), (TokenKind::Greater, ">")]
+        );
+    }
+
+    #[test]
+    fn lex_comparisons() {
+        assert_eq!(
+            kinds("<<=>>=!="),
+            vec![
+                (TokenKind::Less, "<"),
+                (TokenKind::LessEq, "<="),
+                (TokenKind::Greater, ">"),
+                (TokenKind::GreaterEq, ">="),
+                (TokenKind::NotEq, "!="),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_arrow_before_minus() {
+        assert_eq!(
+            kinds("-->:"),
+            vec![(TokenKind::Minus, "-"), (TokenKind::Arrow, "->"), (TokenKind::Colon, ":")]
+        );
+    }
+
+    #[test]
+    fn lex_dot_dot_before_dot() {
+        assert_eq!(
+            kinds("...p"),
+            vec![(TokenKind::DotDot, ".."), (TokenKind::Dot, "."), (TokenKind::Ident, "p")]
+        );
+    }
+
+    #[test]
+    fn lex_colon_colon_before_colon() {
+        assert_eq!(
+            kinds(":::x"),
+            vec![(TokenKind::ColonColon, "::"), (TokenKind::Colon, ":"), (TokenKind::Ident, "x")]
+        );
+    }
+
+    #[test]
+    fn lex_underscore_apart_from_ident() {
+        assert_eq!(
+            kinds("_ _x"),
+            vec![
+                (TokenKind::Underscore, "_"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Underscore, "_"),
+                (TokenKind::Ident, "x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_operators_and_delimiters() {
+        assert_eq!(
+            kinds("+-*/,.(){}[]?"),
+            vec![
+                (TokenKind::Plus, "+"),
+                (TokenKind::Minus, "-"),
+                (TokenKind::Star, "*"),
+                (TokenKind::Slash, "/"),
+                (TokenKind::Comma, ","),
+                (TokenKind::Dot, "."),
+                (TokenKind::LParen, "("),
+                (TokenKind::RParen, ")"),
+                (TokenKind::LBrace, "{"),
+                (TokenKind::RBrace, "}"),
+                (TokenKind::LBracket, "["),
+                (TokenKind::RBracket, "]"),
+                (TokenKind::Question, "?"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_comment_up_to_newline() {
+        assert_eq!(
+            kinds("1 # one\n2"),
+            vec![
+                (TokenKind::Number, "1"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Comment, "# one"),
+                (TokenKind::Whitespace, "\n"),
+                (TokenKind::Number, "2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_unknown_char_as_error() {
+        assert_eq!(
+            kinds("1 ~"),
+            vec![
+                (TokenKind::Number, "1"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Error, "~"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_carry_spans() {
+        let tokens = lex("ab 12");
+        assert_eq!(tokens[0].span, Span { start: 0, end: 2 });
+        assert_eq!(tokens[2].span, Span { start: 3, end: 5 });
+    }
+
+    #[test]
+    fn span_line_col() {
+        let input = "a\nbc d";
+        assert_eq!(Span { start: 0, end: 1 }.line_col(input), (1, 1));
+        assert_eq!(Span { start: 5, end: 6 }.line_col(input), (2, 4));
+    }
+}
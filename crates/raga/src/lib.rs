@@ -1,32 +1,357 @@
 extern crate core;
 
 pub mod binding_def;
+mod budget;
+mod bytecode;
+mod compiler;
+mod engine;
+pub mod enum_def;
 mod env;
+mod error;
 pub mod expr;
+mod formatter;
+pub mod lexer;
+mod native;
+mod object;
+mod optimizer;
+mod parser;
+pub mod pattern;
+mod resolver;
+#[cfg(feature = "serde")]
+mod serde_bridge;
 pub mod stmt;
+pub mod struct_def;
+pub mod syntax;
+#[cfg(test)]
+mod test_gen;
+pub mod type_ann;
+mod typeck;
 mod utils;
 pub mod val;
-mod func_def;
+pub mod func_def;
+mod vm;
 
-pub use val::Val;
-pub use env::Env;
+pub use val::{EnumVal, StructVal, Val};
+pub use engine::{Backend, Engine, FuncArgs, Limits};
+pub use env::{Env, NamedInfo};
+pub use error::{Error, EvalError};
+pub use formatter::format;
+pub use native::{IntoNativeFunc, IntoNativeResult, NativeFunc};
+pub use object::HostObject;
+pub use parser::ParseError;
+#[cfg(feature = "serde")]
+pub use serde_bridge::{from_val, to_val};
 
+/// The syntax tree of a program, the statements lowered from it and every
+/// syntax error found along the way. Statements that failed to parse are
+/// left out, so `stmts` is a partial AST whenever `errors` is non-empty.
 #[derive(Debug)]
-pub struct Parse(stmt::Stmt);
+pub struct Parse {
+    syntax: syntax::SyntaxNode,
+    stmts: Vec<stmt::Stmt>,
+    errors: Vec<ParseError>,
+    resolution: resolver::Resolution,
+}
 
 impl Parse {
-    pub fn eval(&self, env: &mut Env) -> Result<Val, String> {
-        self.0.eval(env)
+    /// The lossless syntax tree, which prints back to the exact source text.
+    pub fn syntax(&self) -> &syntax::SyntaxNode {
+        &self.syntax
+    }
+
+    pub fn stmts(&self) -> &[stmt::Stmt] {
+        &self.stmts
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Whether the input stopped in the middle of a statement, for example
+    /// inside a block that was never closed, so that more input could still
+    /// complete it. This is the case when every error is at the end of input.
+    pub fn is_incomplete(&self) -> bool {
+        let end = self.syntax.span().end;
+        !self.errors.is_empty() && self.errors.iter().all(|error| error.span.start == end)
+    }
+
+    /// Names that are used as something they are not, or that neither the
+    /// program nor `env` defines, so that using them would fail. Names are
+    /// only checked once the program has no syntax errors.
+    pub fn name_errors(&self, env: &Env) -> Vec<ParseError> {
+        if !self.errors.is_empty() {
+            return Vec::new();
+        }
+
+        let mut spans = Vec::new();
+        name_ref_spans(&self.syntax, &mut spans);
+
+        let undefined = self
+            .resolution
+            .globals
+            .iter()
+            .filter(|global| env.find(&global.name).is_none())
+            .map(|global| {
+                let kind = if global.is_call { "function" } else { "binding" };
+                (global.idx, format!("undefined {} '{}'", kind, global.name))
+            });
+        let mut errors: Vec<_> = self.resolution.errors.iter().cloned().chain(undefined).collect();
+        errors.sort_by_key(|(idx, _)| *idx);
+
+        errors
+            .into_iter()
+            .map(|(idx, message)| ParseError {
+                message,
+                span: spans[idx],
+            })
+            .collect()
+    }
+
+    /// Mismatches between the types of values and how they are used, like
+    /// adding a number to a block whose value is `Unit`, found by inferring
+    /// types for the program running in `env`. Types are only checked once
+    /// the program has no syntax errors.
+    pub fn type_errors(&self, env: &Env) -> Vec<ParseError> {
+        if !self.errors.is_empty() {
+            return Vec::new();
+        }
+        typeck::check(&self.syntax, env)
+    }
+
+    /// Folds constant arithmetic, removes unused bindings and inlines
+    /// trivial functions. The program evaluates to the same value or error
+    /// as before, but may use less fuel and memory doing so.
+    pub fn optimize(&mut self) {
+        optimizer::optimize(&mut self.stmts);
+        resolver::resolve(&mut self.stmts);
+    }
+
+    /// Evaluates the program, returning the value of its last statement.
+    /// A `?` outside of any function ends the program early with the `None`
+    /// or `Err` it found.
+    pub fn eval(&self, env: &mut Env) -> Result<Val, EvalError> {
+        let mut result = Val::Unit;
+        for stmt in &self.stmts {
+            result = match stmt.eval(env) {
+                Err(EvalError::EarlyReturn(val)) => return Ok(val),
+                result => result?,
+            };
+        }
+        Ok(result)
+    }
+}
+
+pub fn parse(s: &str) -> Parse {
+    let mut p = parser::Parser::new(s);
+    stmt::Stmt::parse_sequence(&mut p, false);
+
+    let (syntax, errors) = p.finish();
+    let mut stmts = stmt::Stmt::lower_sequence(&syntax);
+    let resolution = resolver::resolve(&mut stmts);
+    Parse {
+        stmts,
+        syntax,
+        errors,
+        resolution,
+    }
+}
+
+/// Collects the spans of the names in `node`, in the order the resolver
+/// numbers them.
+fn name_ref_spans(node: &syntax::SyntaxNode, spans: &mut Vec<lexer::Span>) {
+    for child in node.children() {
+        match child.kind() {
+            syntax::NodeKind::NameRef => spans.push(child.span()),
+            syntax::NodeKind::Error => {}
+            _ => name_ref_spans(&child, spans),
+        }
     }
 }
 
-pub fn parse(s: &str) -> Result<Parse, String> {
-    let (s, stmt) = stmt::Stmt::new(s)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Span;
+
+    fn messages(parse: &Parse) -> Vec<&str> {
+        parse.errors().iter().map(|error| error.message.as_str()).collect()
+    }
+
+    #[test]
+    fn parse_program_with_multiple_stmts() {
+        let parse = parse("let a = 1\nlet b = 2 a + b");
+        assert!(parse.errors().is_empty());
+        assert_eq!(parse.stmts().len(), 3);
+        assert_eq!(parse.eval(&mut Env::default()), Ok(Val::Number(3)));
+    }
+
+    #[test]
+    fn report_every_error_in_program() {
+        let parse = parse("let = 1\nlet a = 2\nfn => 3\n)\na");
+        assert_eq!(
+            messages(&parse),
+            vec![
+                "expected identifier",
+                "expected identifier",
+                "expected expression"
+            ]
+        );
+        assert_eq!(parse.stmts().len(), 2);
+    }
+
+    #[test]
+    fn report_definitions_named_after_prelude_variants() {
+        let parse = parse("fn Some x => x + 1\nlet None = 2\nfn f Ok => Ok\nSome(2)");
+        assert_eq!(
+            messages(&parse),
+            vec![
+                "'Some' is a variant of Option and cannot be defined",
+                "'None' is a variant of Option and cannot be defined",
+                "'Ok' is a variant of Result and cannot be defined",
+            ]
+        );
+        assert_eq!(parse.stmts().len(), 4);
+    }
+
+    #[test]
+    fn report_malformed_let_as_binding_def_error() {
+        let parse = parse("let 5 = 5");
+        assert_eq!(
+            parse.errors(),
+            &[ParseError {
+                message: "expected identifier".to_string(),
+                span: Span { start: 4, end: 5 },
+            }]
+        );
+    }
+
+    #[test]
+    fn recover_inside_block() {
+        let parse = parse("let a = {\n  let = 1\n  2\n}\na");
+        assert_eq!(messages(&parse), vec!["expected identifier"]);
+        assert_eq!(parse.stmts().len(), 2);
+        assert_eq!(parse.eval(&mut Env::default()), Ok(Val::Number(2)));
+    }
+
+    #[test]
+    fn report_unclosed_block_at_end_of_input() {
+        let parse = parse("{ 1");
+        assert_eq!(
+            parse.errors(),
+            &[ParseError {
+                message: "expected }".to_string(),
+                span: Span { start: 3, end: 3 },
+            }]
+        );
+    }
+
+    #[test]
+    fn print_syntax_tree_back_to_source() {
+        let inputs = [
+            "",
+            "   ",
+            "# just a comment",
+            "let a = 10 / 2",
+            "  let   a=1 # one\n\n fn  add x y =>{ x+y }\n(a)  * 2  ",
+            "{\n  let b = {  }\n  b\n}\n",
+            "let = 1\nlet a = 2\nfn => 3\n)\na",
+            "let a = {\n  let = 1\n  2\n}\na",
+            "{ 1",
+            "1 ~ 2 ~~ }}} ((",
+            "let a = (1 + \n fn",
+            "99999999999 + x",
+        ];
+
+        for input in inputs {
+            assert_eq!(parse(input).syntax().to_string(), input);
+        }
+    }
 
-    if s.is_empty() {
-        Ok(Parse(stmt))
-    } else {
-        dbg!(s);
-        Err("input was not consumed fully by parser".to_string())
+    #[test]
+    fn print_every_prefix_of_program_back_to_source() {
+        let program = "fn f x => { let y = (x + 1) * 2 # double\n y }\nlet a = {\n f\n}\n";
+        for end in 0..=program.len() {
+            let input = &program[..end];
+            assert_eq!(parse(input).syntax().to_string(), input);
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn keep_trivia_and_errors_in_syntax_tree() {
+        assert_eq!(
+            format!("{:?}", parse("let a = 1 # one\n)").syntax()),
+            r##"Root@0..17
+  BindingDef@0..9
+    LetKw@0..3 "let"
+    Whitespace@3..4 " "
+    Ident@4..5 "a"
+    Whitespace@5..6 " "
+    Equals@6..7 "="
+    Whitespace@7..8 " "
+    Literal@8..9
+      Number@8..9 "1"
+  Whitespace@9..10 " "
+  Comment@10..15 "# one"
+  Whitespace@15..16 "\n"
+  Error@16..17
+    RParen@16..17 ")"
+"##
+        );
+    }
+
+    #[test]
+    fn detect_incomplete_input() {
+        assert!(parse("{\n  let a = 1\n").is_incomplete());
+        assert!(parse("fn add x y =>").is_incomplete());
+        assert!(parse("let a = (1 +  # more to come\n").is_incomplete());
+    }
+
+    #[test]
+    fn do_not_treat_complete_or_broken_input_as_incomplete() {
+        assert!(!parse("{ 1 }").is_incomplete());
+        assert!(!parse("").is_incomplete());
+        assert!(!parse("let = 1\n{").is_incomplete());
+        assert!(!parse("1 )").is_incomplete());
+    }
+
+    #[test]
+    fn report_name_errors_with_spans() {
+        let mut env = Env::default();
+        env.store_binding("known".to_string(), Val::Number(1));
+
+        let parse = parse("let a = known\n{\n  let b = 1\n  b() + f + missing(a)\n}\nfn f => c\nlet c = 2");
+        assert!(parse.errors().is_empty());
+        assert_eq!(
+            parse.name_errors(&env),
+            vec![
+                ParseError {
+                    message: "'b' is a binding, not a function".to_string(),
+                    span: Span { start: 30, end: 31 },
+                },
+                ParseError {
+                    message: "undefined binding 'f'".to_string(),
+                    span: Span { start: 36, end: 37 },
+                },
+                ParseError {
+                    message: "undefined function 'missing'".to_string(),
+                    span: Span { start: 40, end: 47 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn stop_unbounded_recursion_of_resolved_function() {
+        let parse = parse("fn f => { f() }\nf()");
+        assert_eq!(
+            parse.eval(&mut Env::default()),
+            Err(format!("maximum call depth of {} exceeded", env::MAX_CALL_DEPTH).into())
+        );
+    }
+
+    #[test]
+    fn report_unexpected_character() {
+        let parse = parse("1 ~ 2");
+        assert_eq!(messages(&parse), vec!["unexpected character '~'"]);
+    }
+}
@@ -0,0 +1,186 @@
+//! Functions implemented in Rust that scripts can call like their own.
+
+use crate::env;
+use crate::{Error, Val};
+use std::fmt;
+use std::rc::Rc;
+
+type Callback = dyn Fn(&[Val]) -> Result<Val, Error>;
+
+#[derive(Clone)]
+pub struct NativeFunc {
+    arity: Option<usize>,
+    callback: Rc<Callback>,
+}
+
+impl NativeFunc {
+    /// Wraps a function taking any number of arguments, which is left to
+    /// check them itself.
+    pub fn new(callback: impl Fn(&[Val]) -> Result<Val, Error> + 'static) -> Self {
+        Self {
+            arity: None,
+            callback: Rc::new(callback),
+        }
+    }
+
+    /// Number of arguments the function takes, if it is fixed.
+    pub fn arity(&self) -> Option<usize> {
+        self.arity
+    }
+
+    pub fn call(&self, args: &[Val]) -> Result<Val, Error> {
+        (self.callback)(args)
+    }
+}
+
+impl fmt::Debug for NativeFunc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunc").field("arity", &self.arity).finish_non_exhaustive()
+    }
+}
+
+/// Two native functions are equal only if they are the same function.
+impl PartialEq for NativeFunc {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.callback, &other.callback)
+    }
+}
+
+/// Return values of typed native functions: anything convertible into a
+/// [`Val`], or a `Result` of it with an [`Error`] to fail. Failures a script
+/// should be able to handle are returned as values instead, such as an
+/// `Option` or a `Result` with any other error type, which become the
+/// built-in `Option` and `Result` enums.
+pub trait IntoNativeResult {
+    fn into_native_result(self) -> Result<Val, Error>;
+}
+
+impl<T: Into<Val>> IntoNativeResult for T {
+    fn into_native_result(self) -> Result<Val, Error> {
+        Ok(self.into())
+    }
+}
+
+impl<T: Into<Val>> IntoNativeResult for Result<T, Error> {
+    fn into_native_result(self) -> Result<Val, Error> {
+        self.map(Into::into)
+    }
+}
+
+/// Rust closures whose arguments convert from [`Val`] with `TryFrom` and
+/// whose result converts back with [`IntoNativeResult`]. `Args` is the tuple
+/// of argument types, which only serves to tell the impls apart.
+pub trait IntoNativeFunc<Args> {
+    fn into_native_func(self) -> NativeFunc;
+}
+
+macro_rules! impl_into_native_func {
+    ($len:expr; $($arg:ident),*) => {
+        impl<F, R, $($arg),*> IntoNativeFunc<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: IntoNativeResult,
+            $($arg: TryFrom<Val>, <$arg as TryFrom<Val>>::Error: fmt::Display,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_native_func(self) -> NativeFunc {
+                let callback = move |args: &[Val]| {
+                    env::check_arity(None, $len, args.len()).map_err(Error::Eval)?;
+                    let mut args = args.iter().cloned().enumerate();
+                    $(
+                        let (idx, arg) = args.next().unwrap();
+                        let $arg = $arg::try_from(arg)
+                            .map_err(|err| Error::Eval(format!("argument {}: {}", idx + 1, err).into()))?;
+                    )*
+                    self($($arg),*).into_native_result()
+                };
+
+                NativeFunc {
+                    arity: Some($len),
+                    callback: Rc::new(callback),
+                }
+            }
+        }
+    };
+}
+
+impl_into_native_func!(0;);
+impl_into_native_func!(1; A);
+impl_into_native_func!(2; A, B);
+impl_into_native_func!(3; A, B, C);
+impl_into_native_func!(4; A, B, C, D);
+impl_into_native_func!(5; A, B, C, D, E);
+impl_into_native_func!(6; A, B, C, D, E, G);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_untyped_func() {
+        let count = NativeFunc::new(|args| Ok(Val::Number(args.len() as i32)));
+        assert_eq!(count.arity(), None);
+        assert_eq!(count.call(&[Val::Unit, Val::Unit]).unwrap(), Val::Number(2));
+    }
+
+    #[test]
+    fn convert_args_and_result_of_typed_func() {
+        let add = (|a: i32, b: i32| a + b).into_native_func();
+        assert_eq!(add.arity(), Some(2));
+        assert_eq!(add.call(&[Val::Number(1), Val::Number(2)]).unwrap(), Val::Number(3));
+    }
+
+    #[test]
+    fn report_wrong_arg_type() {
+        let add = (|a: i32, b: i32| a + b).into_native_func();
+        assert_eq!(
+            add.call(&[Val::Number(1), Val::Unit]).unwrap_err().to_string(),
+            "evaluation error: argument 2: expected Number but found Unit"
+        );
+    }
+
+    #[test]
+    fn report_wrong_number_of_args() {
+        let answer = (|| 42).into_native_func();
+        assert_eq!(
+            answer.call(&[Val::Unit]).unwrap_err().to_string(),
+            "evaluation error: function takes 0 argument(s) but 1 were given"
+        );
+    }
+
+    #[test]
+    fn pass_on_errors_of_fallible_func() {
+        let div = (|a: i32, b: i32| {
+            if b == 0 {
+                Err(Error::Eval("division by zero".into()))
+            } else {
+                Ok(a / b)
+            }
+        })
+        .into_native_func();
+
+        assert_eq!(div.call(&[Val::Number(6), Val::Number(3)]).unwrap(), Val::Number(2));
+        assert!(div.call(&[Val::Number(6), Val::Number(0)]).is_err());
+    }
+
+    #[test]
+    fn return_recoverable_failures_as_values() {
+        let parse = (|s: String| s.parse::<i32>().map_err(|err| err.to_string())).into_native_func();
+        assert_eq!(parse.call(&[Val::from("12")]).unwrap(), Val::ok(12));
+        assert_eq!(
+            parse.call(&[Val::from("x")]).unwrap(),
+            Val::err("invalid digit found in string")
+        );
+
+        let first = (|list: Vec<i32>| list.first().copied()).into_native_func();
+        assert_eq!(first.call(&[Val::from(Vec::<i32>::new())]).unwrap(), Val::none());
+    }
+
+    #[test]
+    fn compare_by_identity() {
+        let f = NativeFunc::new(|_| Ok(Val::Unit));
+        let g = NativeFunc::new(|_| Ok(Val::Unit));
+        assert_eq!(f, f.clone());
+        assert_ne!(f, g);
+    }
+}
@@ -0,0 +1,128 @@
+//! Opaque host values that scripts can read fields of and call methods on.
+
+use crate::{Error, Val};
+use std::any::Any;
+use std::fmt;
+
+/// A Rust value handed to scripts as a [`Val::Object`], such as a database
+/// row or a request context. Scripts access it with `obj.field` and
+/// `obj.method(args)`; everything else about it stays opaque to them.
+pub trait HostObject: Any {
+    /// Name of the type as shown to scripts, e.g. in error messages.
+    fn type_name(&self) -> &'static str;
+
+    fn get_field(&self, name: &str) -> Result<Val, Error> {
+        Err(Error::Eval(
+            format!("{} has no field '{}'", self.type_name(), name).into(),
+        ))
+    }
+
+    fn call_method(&self, name: &str, args: &[Val]) -> Result<Val, Error> {
+        let _ = args;
+        Err(Error::Eval(
+            format!("{} has no method '{}'", self.type_name(), name).into(),
+        ))
+    }
+
+    /// Objects are only equal to themselves unless this is overridden,
+    /// typically by downcasting `other` to `Self` and comparing.
+    fn equals(&self, other: &dyn HostObject) -> bool {
+        std::ptr::addr_eq(self as *const Self, other as *const dyn HostObject)
+    }
+
+    fn display(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.type_name())
+    }
+}
+
+impl dyn HostObject {
+    pub fn downcast_ref<T: HostObject>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref()
+    }
+}
+
+impl PartialEq for dyn HostObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other)
+    }
+}
+
+impl fmt::Debug for dyn HostObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(self.type_name()).finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for dyn HostObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(i32);
+
+    impl HostObject for Counter {
+        fn type_name(&self) -> &'static str {
+            "Counter"
+        }
+    }
+
+    struct Point(i32, i32);
+
+    impl HostObject for Point {
+        fn type_name(&self) -> &'static str {
+            "Point"
+        }
+
+        fn equals(&self, other: &dyn HostObject) -> bool {
+            other
+                .downcast_ref::<Self>()
+                .is_some_and(|other| (self.0, self.1) == (other.0, other.1))
+        }
+
+        fn display(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Point({}, {})", self.0, self.1)
+        }
+    }
+
+    #[test]
+    fn compare_by_identity_by_default() {
+        let counter = Val::object(Counter(1));
+        assert_eq!(counter, counter.clone());
+        assert_ne!(counter, Val::object(Counter(1)));
+    }
+
+    #[test]
+    fn dispatch_equality_and_display_to_object() {
+        let point = Val::object(Point(1, 2));
+        assert_eq!(point, Val::object(Point(1, 2)));
+        assert_ne!(point, Val::object(Point(2, 1)));
+        assert_ne!(point, Val::object(Counter(1)));
+        assert_eq!(point.to_string(), "Point(1, 2)");
+    }
+
+    #[test]
+    fn report_missing_field_and_method() {
+        let counter = Counter(1);
+        assert_eq!(
+            counter.get_field("count").unwrap_err().to_string(),
+            "evaluation error: Counter has no field 'count'"
+        );
+        assert_eq!(
+            counter.call_method("inc", &[]).unwrap_err().to_string(),
+            "evaluation error: Counter has no method 'inc'"
+        );
+    }
+
+    #[test]
+    fn downcast_to_concrete_type() {
+        let counter = Val::object(Counter(7));
+        assert_eq!(counter.downcast_ref::<Counter>().map(|c| c.0), Some(7));
+        assert!(counter.downcast_ref::<Point>().is_none());
+        assert!(Val::Unit.downcast_ref::<Counter>().is_none());
+    }
+}
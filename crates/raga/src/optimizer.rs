@@ -0,0 +1,575 @@
+//! Optional optimizations of the syntax tree, run before evaluation.
+//!
+//! - Arithmetic on constants is folded, unless it would fail, so that the
+//!   error is still raised when the program runs.
+//! - A `let` in a block is removed if its value is a constant or a local
+//!   binding, the block defines the name only once, no function in the
+//!   block mentions it and nothing after it in the block does.
+//! - A call of a trivial function, whose body is arithmetic on constants and
+//!   its parameters, is replaced by the body if every argument is a constant
+//!   or a local binding.
+//! - A `match` on a constant is replaced by the body of the arm it takes,
+//!   if the patterns and guards of the arms up to that one can be decided
+//!   without running anything. An arm that binds names is only taken this
+//!   way if its body is trivial, so that the names can be replaced with
+//!   the values they are bound to.
+//!
+//! None of these change what a program evaluates to or the errors it fails
+//! with, but an optimized program may use less fuel and memory. The
+//! optimizer works on names, so the statements have to be resolved again
+//! afterwards.
+
+use crate::expr::{Block, Bool, Expr, Match, Number};
+use crate::stmt::Stmt;
+use crate::val::Val;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub(crate) fn optimize(stmts: &mut [Stmt]) {
+    let mut optimizer = Optimizer {
+        scopes: vec![Scope::new(stmts)],
+        in_global_func: false,
+    };
+    for stmt in stmts {
+        optimizer.stmt(stmt);
+    }
+}
+
+/// A function that can be inlined.
+#[derive(Debug)]
+struct Inline {
+    params: Vec<String>,
+    body: Expr,
+}
+
+#[derive(Debug, Clone)]
+enum Symbol {
+    Binding,
+    Func(Option<Rc<Inline>>),
+    Struct,
+    Enum,
+}
+
+#[derive(Debug)]
+struct Scope {
+    /// The names defined so far.
+    names: HashMap<String, Symbol>,
+    /// How often each name is defined in the whole scope. Only functions
+    /// that are defined once are known to be the one a call refers to.
+    definitions: HashMap<String, usize>,
+}
+
+impl Scope {
+    fn new(stmts: &[Stmt]) -> Self {
+        let mut definitions = HashMap::new();
+        for stmt in stmts {
+            let name = match stmt {
+                Stmt::BindingDef(binding_def) => &binding_def.name,
+                Stmt::FuncDef(func_def) => &func_def.name,
+                Stmt::StructDef(struct_def) => &struct_def.name,
+                Stmt::EnumDef(enum_def) => &enum_def.name,
+                Stmt::Expr(_) => continue,
+            };
+            *definitions.entry(name.clone()).or_insert(0) += 1;
+        }
+
+        Self {
+            names: HashMap::new(),
+            definitions,
+        }
+    }
+}
+
+struct Optimizer {
+    scopes: Vec<Scope>,
+    /// Whether the optimizer is in the body of a global function, which may
+    /// run after the program has ended and its globals have been replaced.
+    in_global_func: bool,
+}
+
+impl Optimizer {
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::BindingDef(binding_def) => {
+                self.expr(&mut binding_def.val);
+                self.declare(&binding_def.name, Symbol::Binding);
+            }
+            Stmt::FuncDef(func_def) => {
+                // The function is in scope inside its own body.
+                self.declare(&func_def.name, Symbol::Func(None));
+
+                let in_global_func = self.in_global_func;
+                self.in_global_func |= self.scopes.len() == 1;
+                let mut scope = Scope::new(&[]);
+                for param in &func_def.params {
+                    scope.names.insert(param.clone(), Symbol::Binding);
+                }
+                self.scopes.push(scope);
+                self.stmt(Rc::make_mut(&mut func_def.body));
+                self.scopes.pop();
+                self.in_global_func = in_global_func;
+
+                // Inlining would skip checking annotated types.
+                let defined_once = self.scopes.last().unwrap().definitions.get(&func_def.name) == Some(&1);
+                let inlinable = defined_once && func_def.signature.is_none();
+                let inline = match &*func_def.body {
+                    Stmt::Expr(body) if inlinable && is_trivial(body, &func_def.params) => Some(Rc::new(Inline {
+                        params: func_def.params.clone(),
+                        body: body.clone(),
+                    })),
+                    _ => None,
+                };
+                self.declare(&func_def.name, Symbol::Func(inline));
+            }
+            Stmt::StructDef(struct_def) => self.declare(&struct_def.name, Symbol::Struct),
+            Stmt::EnumDef(enum_def) => self.declare(&enum_def.name, Symbol::Enum),
+            Stmt::Expr(expr) => self.expr(expr),
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Number(_) | Expr::Bool(_) | Expr::BindingUsage(_) => {}
+            Expr::Operation { lhs, rhs, op } => {
+                self.expr(lhs);
+                self.expr(rhs);
+                if let (Some(lhs), Some(rhs)) = (literal_val(lhs), literal_val(rhs)) {
+                    if let Some(folded) = op.apply(lhs, rhs).ok().and_then(literal) {
+                        *expr = folded;
+                    }
+                }
+            }
+            Expr::Block(block) => self.block(block),
+            Expr::FuncCall(func_call) => {
+                for arg in &mut func_call.args {
+                    self.expr(arg);
+                }
+
+                let inline = match self.lookup(&func_call.callee) {
+                    Some((_, Symbol::Func(Some(inline)))) => Rc::clone(inline),
+                    _ => return,
+                };
+                let args_are_simple = func_call.args.iter().all(|arg| self.is_simple(arg));
+                if inline.params.len() != func_call.args.len() || !args_are_simple {
+                    return;
+                }
+
+                let args: HashMap<_, _> = inline.params.iter().zip(&func_call.args).collect();
+                let mut body = inline.body.clone();
+                substitute(&mut body, &args);
+                *expr = body;
+                self.expr(expr);
+            }
+            Expr::FieldAccess(field_access) => self.expr(&mut field_access.target),
+            Expr::Try(try_expr) => self.expr(&mut try_expr.inner),
+            Expr::MethodCall(method_call) => {
+                self.expr(&mut method_call.target);
+                for arg in &mut method_call.args {
+                    self.expr(arg);
+                }
+            }
+            Expr::StructLit(struct_lit) => {
+                for (_, val) in &mut struct_lit.fields {
+                    self.expr(val);
+                }
+                if let Some(base) = &mut struct_lit.base {
+                    self.expr(base);
+                }
+            }
+            Expr::VariantLit(variant_lit) => {
+                for arg in &mut variant_lit.args {
+                    self.expr(arg);
+                }
+            }
+            Expr::Tuple(tuple) => {
+                for item in &mut tuple.items {
+                    self.expr(item);
+                }
+            }
+            Expr::Match(match_expr) => {
+                self.expr(&mut match_expr.scrutinee);
+                for arm in &mut match_expr.arms {
+                    let mut scope = Scope::new(&[]);
+                    for binding in arm.pattern.bindings() {
+                        scope.names.insert(binding.name.clone(), Symbol::Binding);
+                    }
+                    self.scopes.push(scope);
+                    if let Some(guard) = &mut arm.guard {
+                        self.expr(guard);
+                    }
+                    self.expr(&mut arm.body);
+                    self.scopes.pop();
+                }
+                if let Some(body) = self.taken_arm(match_expr) {
+                    *expr = body;
+                }
+            }
+        }
+    }
+
+    /// The body of the arm a `match` on a constant takes, with the names
+    /// its pattern binds replaced. There is none if that cannot be known
+    /// before running the `match`, or if no arm is taken and it fails.
+    fn taken_arm(&mut self, match_expr: &Match) -> Option<Expr> {
+        let val = constant_val(&match_expr.scrutinee)?;
+        for arm in &match_expr.arms {
+            let mut bound = Vec::new();
+            if !arm.pattern.matches(&val, &mut bound) {
+                continue;
+            }
+            let names: Vec<_> = bound.iter().map(|(binding, _)| binding.name.clone()).collect();
+            let vals = bound
+                .into_iter()
+                .map(|(_, val)| literal(val))
+                .collect::<Option<Vec<_>>>()?;
+            let args: HashMap<_, _> = names.iter().zip(&vals).collect();
+
+            if let Some(guard) = &arm.guard {
+                if !is_trivial(guard, &names) {
+                    return None;
+                }
+                let mut guard = guard.clone();
+                substitute(&mut guard, &args);
+                self.expr(&mut guard);
+                match guard {
+                    Expr::Bool(Bool(true)) => {}
+                    Expr::Bool(Bool(false)) => continue,
+                    _ => return None,
+                }
+            }
+
+            if names.is_empty() {
+                return Some(arm.body.clone());
+            }
+            if !is_trivial(&arm.body, &names) {
+                return None;
+            }
+            let mut body = arm.body.clone();
+            substitute(&mut body, &args);
+            self.expr(&mut body);
+            return Some(body);
+        }
+        None
+    }
+
+    fn block(&mut self, block: &mut Block) {
+        self.scopes.push(Scope::new(&block.stmts));
+        let mut unused = Vec::new();
+        for (idx, stmt) in block.stmts.iter_mut().enumerate() {
+            match stmt {
+                // Whether the value is simple has to be known before the
+                // binding is, as the value may refer to an outer one of the
+                // same name. An annotated binding stays, as checking its
+                // type may fail.
+                Stmt::BindingDef(binding_def) => {
+                    self.expr(&mut binding_def.val);
+                    if binding_def.ty.is_none() && self.is_simple(&binding_def.val) {
+                        unused.push(idx);
+                    }
+                    self.declare(&binding_def.name, Symbol::Binding);
+                }
+                stmt => self.stmt(stmt),
+            }
+        }
+        let scope = self.scopes.pop().expect("the block has a scope");
+
+        // The last statement is the value of the block, so it has to stay
+        // even if it is a `let`. A function reads the binding when it is
+        // called, which may be after the name was defined again, so a name
+        // a function mentions or that is defined twice stays too.
+        unused.retain(|&idx| match &block.stmts[idx] {
+            Stmt::BindingDef(binding_def) => {
+                let name = &binding_def.name;
+                idx + 1 < block.stmts.len()
+                    && scope.definitions.get(name) == Some(&1)
+                    && !block
+                        .stmts
+                        .iter()
+                        .any(|stmt| matches!(stmt, Stmt::FuncDef(_)) && mentions_stmt(stmt, name))
+                    && !block.stmts[idx + 1..].iter().any(|stmt| mentions_stmt(stmt, name))
+            }
+            _ => false,
+        });
+        for idx in unused.into_iter().rev() {
+            block.stmts.remove(idx);
+        }
+    }
+
+    fn declare(&mut self, name: &str, symbol: Symbol) {
+        let scope = self.scopes.last_mut().expect("there is a global scope");
+        scope.names.insert(name.to_string(), symbol);
+    }
+
+    /// What `name` refers to, along with the index of the scope defining
+    /// it, if it is known for sure.
+    fn lookup(&self, name: &str) -> Option<(usize, &Symbol)> {
+        let (idx, symbol) = self
+            .scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(idx, scope)| scope.names.get(name).map(|symbol| (idx, symbol)))?;
+
+        if idx == 0 && self.in_global_func {
+            return None;
+        }
+        Some((idx, symbol))
+    }
+
+    /// Whether `expr` is a constant or a local binding, which can be
+    /// evaluated any number of times, or not at all, without failing.
+    fn is_simple(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Number(_) | Expr::Bool(_) => true,
+            Expr::BindingUsage(binding_usage) => {
+                matches!(self.lookup(&binding_usage.name), Some((idx, Symbol::Binding)) if idx > 0)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Whether `expr` only does arithmetic or comparisons on constants and
+/// `params`.
+fn is_trivial(expr: &Expr, params: &[String]) -> bool {
+    match expr {
+        Expr::Number(_) | Expr::Bool(_) => true,
+        Expr::BindingUsage(binding_usage) => params.contains(&binding_usage.name),
+        Expr::Operation { lhs, rhs, .. } => is_trivial(lhs, params) && is_trivial(rhs, params),
+        _ => false,
+    }
+}
+
+/// The value of `expr` if it is a literal.
+fn literal_val(expr: &Expr) -> Option<Val> {
+    match expr {
+        Expr::Number(Number(n)) => Some(Val::Number(*n)),
+        Expr::Bool(Bool(b)) => Some(Val::Bool(*b)),
+        _ => None,
+    }
+}
+
+/// The value of `expr` if it is a literal or a tuple of constants.
+fn constant_val(expr: &Expr) -> Option<Val> {
+    match expr {
+        Expr::Tuple(tuple) => Some(Val::Tuple(
+            tuple.items.iter().map(constant_val).collect::<Option<_>>()?,
+        )),
+        expr => literal_val(expr),
+    }
+}
+
+/// The literal that evaluates to `val`, if there is one.
+fn literal(val: Val) -> Option<Expr> {
+    match val {
+        Val::Number(n) => Some(Expr::Number(Number(n))),
+        Val::Bool(b) => Some(Expr::Bool(Bool(b))),
+        _ => None,
+    }
+}
+
+/// Replaces the parameters in the body of a trivial function with the
+/// arguments of a call.
+fn substitute(expr: &mut Expr, args: &HashMap<&String, &Expr>) {
+    match expr {
+        Expr::BindingUsage(binding_usage) => {
+            if let Some(arg) = args.get(&binding_usage.name) {
+                *expr = (*arg).clone();
+            }
+        }
+        Expr::Operation { lhs, rhs, .. } => {
+            substitute(lhs, args);
+            substitute(rhs, args);
+        }
+        _ => {}
+    }
+}
+
+/// Whether `name` is used or defined anywhere in `stmt`.
+fn mentions_stmt(stmt: &Stmt, name: &str) -> bool {
+    match stmt {
+        Stmt::BindingDef(binding_def) => binding_def.name == name || mentions(&binding_def.val, name),
+        Stmt::FuncDef(func_def) => {
+            func_def.name == name
+                || func_def.params.iter().any(|param| param == name)
+                || mentions_stmt(&func_def.body, name)
+        }
+        Stmt::StructDef(struct_def) => struct_def.name == name,
+        Stmt::EnumDef(enum_def) => enum_def.name == name,
+        Stmt::Expr(expr) => mentions(expr, name),
+    }
+}
+
+fn mentions(expr: &Expr, name: &str) -> bool {
+    match expr {
+        Expr::Number(_) | Expr::Bool(_) => false,
+        Expr::Operation { lhs, rhs, .. } => mentions(lhs, name) || mentions(rhs, name),
+        Expr::BindingUsage(binding_usage) => binding_usage.name == name,
+        Expr::Block(block) => block.stmts.iter().any(|stmt| mentions_stmt(stmt, name)),
+        Expr::FuncCall(func_call) => {
+            func_call.callee == name || func_call.args.iter().any(|arg| mentions(arg, name))
+        }
+        Expr::FieldAccess(field_access) => mentions(&field_access.target, name),
+        Expr::Try(try_expr) => mentions(&try_expr.inner, name),
+        Expr::MethodCall(method_call) => {
+            mentions(&method_call.target, name) || method_call.args.iter().any(|arg| mentions(arg, name))
+        }
+        Expr::StructLit(struct_lit) => {
+            struct_lit.name == name
+                || struct_lit.fields.iter().any(|(_, val)| mentions(val, name))
+                || struct_lit.base.as_deref().is_some_and(|base| mentions(base, name))
+        }
+        Expr::VariantLit(variant_lit) => {
+            variant_lit.enum_name == name || variant_lit.args.iter().any(|arg| mentions(arg, name))
+        }
+        Expr::Tuple(tuple) => tuple.items.iter().any(|item| mentions(item, name)),
+        Expr::Match(match_expr) => {
+            mentions(&match_expr.scrutinee, name)
+                || match_expr.arms.iter().any(|arm| {
+                    arm.pattern.bindings().iter().any(|binding| binding.name == name)
+                        || arm.guard.as_ref().is_some_and(|guard| mentions(guard, name))
+                        || mentions(&arm.body, name)
+                })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_gen::{self, Profile};
+    use crate::{Backend, Engine, Val};
+
+    fn optimized(src: &str) -> String {
+        let mut parse = crate::parse(src);
+        assert!(parse.errors().is_empty(), "{:?}", parse.errors());
+        parse.optimize();
+        parse.stmts().iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn fold_constant_arithmetic() {
+        assert_eq!(optimized("60 * 60 * 24"), "86400");
+        assert_eq!(optimized("let a = (1 + 2) * x"), "let a = 3 * x");
+    }
+
+    #[test]
+    fn keep_arithmetic_that_fails() {
+        assert_eq!(optimized("1 / 0"), "1 / 0");
+        assert_eq!(optimized("2147483647 + 1 - 5"), "2147483647 + 1 - 5");
+    }
+
+    #[test]
+    fn remove_unused_lets() {
+        assert_eq!(
+            optimized("{\n    let a = 1\n    let b = 2\n    let c = a\n    b\n}"),
+            "{\n    let a = 1\n    let b = 2\n    b\n}"
+        );
+    }
+
+    #[test]
+    fn keep_lets_functions_may_read() {
+        let src = "{\n    let a = 1\n    fn g => a\n    let a = 2\n    g()\n}";
+        assert_eq!(optimized(src), src);
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            assert_eq!(engine_with(backend, true).eval(src).unwrap(), Val::Number(2));
+        }
+    }
+
+    #[test]
+    fn keep_lets_that_may_fail_or_are_last() {
+        let src = "{\n    let a = missing\n    let b = 1 / 0\n    let c: Unit = 1\n    let d = 1\n}";
+        assert_eq!(optimized(src), src);
+    }
+
+    #[test]
+    fn inline_trivial_functions() {
+        assert_eq!(
+            optimized("fn square x => x * x\nsquare(3)\n{\n    let a = 4\n    square(a)\n}"),
+            "fn square x => x * x\n9\n{\n    let a = 4\n    a * a\n}"
+        );
+    }
+
+    #[test]
+    fn optimize_inside_match_arms() {
+        assert_eq!(
+            optimized("fn square x => x * x\nmatch (2 + 3, 1) {\n    (n, _) if t => square(n),\n    _ => 0,\n}"),
+            "fn square x => x * x\nmatch (5, 1) {\n    (n, _) if t => n * n,\n    _ => 0,\n}"
+        );
+    }
+
+    #[test]
+    fn fold_matches_on_constants() {
+        assert_eq!(
+            optimized("match 2 + 1 {\n    0 => a,\n    n if n < 2 => b,\n    n => n * 10,\n}"),
+            "30"
+        );
+        assert_eq!(optimized("match (1, true) {\n    (0, _) => a,\n    (1, _) => f(x),\n}"), "f(x)");
+        assert_eq!(optimized("match 1 {\n    _ if false => a,\n    _ if true => b,\n}"), "b");
+    }
+
+    #[test]
+    fn keep_matches_that_may_fail_or_need_running() {
+        // No arm is taken.
+        let src = "match 3 {\n    0 => 1,\n}";
+        assert_eq!(optimized(src), src);
+
+        // The guard fails.
+        let src = "match 3 {\n    n if n / 0 == 1 => 1,\n    _ => 2,\n}";
+        assert_eq!(optimized(src), src);
+
+        // The guard depends on a binding.
+        let src = "match 3 {\n    n if n < x => 1,\n    _ => 2,\n}";
+        assert_eq!(optimized(src), src);
+
+        // The body would have to define `n`.
+        let src = "match 3 {\n    n => f(n),\n}";
+        assert_eq!(optimized(src), src);
+
+        // The scrutinee is not a constant.
+        let src = "match x {\n    _ => 1,\n}";
+        assert_eq!(optimized(src), src);
+    }
+
+    #[test]
+    fn only_inline_functions_known_at_the_call() {
+        // Defined twice, so either could be called.
+        let src = "fn one => 1\nlet a = one()\nfn one => 2\none()";
+        assert_eq!(optimized(src), src);
+
+        // Global functions may be called after `id` has been replaced.
+        let src = "fn id x => x\nfn f => id(1)";
+        assert_eq!(optimized(src), src);
+
+        // The argument could fail.
+        let src = "fn id x => x\nid(missing)";
+        assert_eq!(optimized(src), src);
+
+        // The argument has to be checked.
+        let src = "fn id x: Int => x\nid(1)";
+        assert_eq!(optimized(src), src);
+    }
+
+    fn engine_with(backend: Backend, optimize: bool) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_backend(backend);
+        engine.set_optimize(optimize);
+        engine
+    }
+
+    #[test]
+    fn agree_with_unoptimized_evaluator() {
+        // Heavy on constants, some of which overflow, which is what the
+        // optimizer looks for.
+        let profile = Profile {
+            numbers: &["0", "1", "3", "46341", "2147483647"],
+            redefine: true,
+        };
+        test_gen::check_agreement(0x9e37_79b9_7f4a_7c15, profile, 100, || {
+            vec![
+                engine_with(Backend::TreeWalker, false),
+                engine_with(Backend::TreeWalker, true),
+                engine_with(Backend::Vm, true),
+            ]
+        });
+    }
+}
@@ -0,0 +1,382 @@
+use crate::lexer::{self, Span, Token, TokenKind};
+use crate::syntax::{Checkpoint, GreenNodeBuilder, NodeKind, SyntaxNode};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+pub(crate) struct StmtStart {
+    checkpoint: Checkpoint,
+    depth: usize,
+    position: usize,
+}
+
+pub(crate) struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token<'a>>,
+    cursor: usize,
+    consumed: usize,
+    builder: GreenNodeBuilder,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    /// Creates a parser whose tree starts out with an open root node.
+    pub(crate) fn new(s: &'a str) -> Self {
+        let mut builder = GreenNodeBuilder::default();
+        builder.start_node(NodeKind::Root);
+
+        Self {
+            input: s,
+            tokens: lexer::lex(s),
+            cursor: 0,
+            consumed: 0,
+            builder,
+            errors: Vec::new(),
+        }
+    }
+
+    pub(crate) fn peek(&self) -> Option<TokenKind> {
+        self.tokens.get(self.position()).map(|token| token.kind)
+    }
+
+    /// The text of the next token, skipping trivia.
+    pub(crate) fn peek_text(&self) -> Option<&'a str> {
+        self.tokens.get(self.position()).map(|token| token.text)
+    }
+
+    /// The kind of the `n`th token from the next one on, skipping trivia.
+    pub(crate) fn nth(&self, n: usize) -> Option<TokenKind> {
+        self.tokens[self.cursor..]
+            .iter()
+            .filter(|token| !token.kind.is_trivia())
+            .nth(n)
+            .map(|token| token.kind)
+    }
+
+    /// Whether the `n`th token from the next one on, skipping trivia, is on
+    /// the same line as the token before it.
+    pub(crate) fn nth_on_same_line(&self, n: usize) -> bool {
+        let tokens = &self.tokens[self.cursor..];
+        let mut non_trivia = (0..tokens.len()).filter(|&idx| !tokens[idx].kind.is_trivia());
+        let Some(idx) = non_trivia.nth(n) else {
+            return false;
+        };
+        !tokens[..idx]
+            .iter()
+            .rev()
+            .take_while(|token| token.kind.is_trivia())
+            .any(|token| token.text.contains('\n'))
+    }
+
+    pub(crate) fn at(&self, kind: TokenKind) -> bool {
+        self.peek() == Some(kind)
+    }
+
+    /// Whether the next token is `kind` and directly follows the last one,
+    /// with no trivia in between.
+    pub(crate) fn at_adjacent(&self, kind: TokenKind) -> bool {
+        self.tokens.get(self.cursor).map(|token| token.kind) == Some(kind)
+    }
+
+    /// Adds the next token to the tree, along with any trivia in front of it.
+    pub(crate) fn bump(&mut self) -> Token<'a> {
+        self.eat_trivia();
+        let token = self.tokens[self.cursor];
+        self.builder.token(token.kind, token.text);
+        self.cursor += 1;
+        self.consumed = token.span.end;
+        token
+    }
+
+    pub(crate) fn expect(&mut self, kind: TokenKind, what: &str) -> Result<Token<'a>, ParseError> {
+        if self.at(kind) {
+            Ok(self.bump())
+        } else {
+            Err(self.error(format!("expected {}", what)))
+        }
+    }
+
+    /// Trivia in front of a node stays with the enclosing node, so it is
+    /// added before the new node is started.
+    pub(crate) fn start_node(&mut self, kind: NodeKind) {
+        self.eat_trivia();
+        self.builder.start_node(kind);
+    }
+
+    pub(crate) fn start_node_at(&mut self, checkpoint: Checkpoint, kind: NodeKind) {
+        self.builder.start_node_at(checkpoint, kind);
+    }
+
+    pub(crate) fn finish_node(&mut self) {
+        self.builder.finish_node();
+    }
+
+    pub(crate) fn checkpoint(&mut self) -> Checkpoint {
+        self.eat_trivia();
+        self.builder.checkpoint()
+    }
+
+    /// Builds an error pointing at the next token, or at the end of input.
+    pub(crate) fn error(&self, message: String) -> ParseError {
+        let span = self.tokens.get(self.position()).map_or(
+            Span {
+                start: self.input.len(),
+                end: self.input.len(),
+            },
+            |token| token.span,
+        );
+
+        ParseError { message, span }
+    }
+
+    pub(crate) fn report(&mut self, error: ParseError) {
+        self.errors.push(error);
+    }
+
+    pub(crate) fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Adds the remaining trivia to the tree, closes the root node and hands
+    /// back the tree along with every error reported while building it.
+    pub(crate) fn finish(mut self) -> (SyntaxNode, Vec<ParseError>) {
+        self.eat_trivia();
+        self.builder.finish_node();
+        (SyntaxNode::new_root(self.builder.finish()), self.errors)
+    }
+
+    /// Index of the next token, used to check whether recovery made progress.
+    pub(crate) fn position(&self) -> usize {
+        self.tokens[self.cursor..]
+            .iter()
+            .position(|token| !token.kind.is_trivia())
+            .map_or(self.tokens.len(), |idx| self.cursor + idx)
+    }
+
+    /// Remembers where a statement starts so it can be abandoned if it fails
+    /// to parse.
+    pub(crate) fn start_stmt(&mut self) -> StmtStart {
+        StmtStart {
+            checkpoint: self.checkpoint(),
+            depth: self.builder.depth(),
+            position: self.position(),
+        }
+    }
+
+    /// Turns a statement that failed to parse into an error node. Nodes the
+    /// statement left unfinished are closed and wrapped together with the
+    /// tokens skipped by [`Parser::recover`].
+    pub(crate) fn abandon_stmt(&mut self, start: StmtStart, in_block: bool) {
+        while self.builder.depth() > start.depth {
+            self.builder.finish_node();
+        }
+
+        self.start_node_at(start.checkpoint, NodeKind::Error);
+        self.recover(start.position, in_block);
+        self.finish_node();
+    }
+
+    /// Skips tokens until the start of the next statement, which is a `let`,
+    /// `fn`, `struct` or `enum` keyword or the first token on a new line. Braces
+    /// opened while skipping are skipped along with their contents; inside a
+    /// block we stop in front of its closing `}` so the block can still be
+    /// finished.
+    pub(crate) fn recover(&mut self, stmt_start: usize, in_block: bool) {
+        let mut depth = 0_usize;
+
+        while let Some(kind) = self.peek() {
+            if depth == 0 {
+                if self.position() > stmt_start && self.at_stmt_boundary() {
+                    break;
+                }
+                if in_block && kind == TokenKind::RBrace {
+                    break;
+                }
+            }
+
+            match kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.bump();
+        }
+    }
+
+    /// Byte offset just past the last token the parser has consumed.
+    pub(crate) fn consumed(&self) -> usize {
+        self.consumed
+    }
+
+    fn at_stmt_boundary(&self) -> bool {
+        let position = self.position();
+        let after_newline = self.tokens[..position]
+            .iter()
+            .rev()
+            .take_while(|token| token.kind.is_trivia())
+            .any(|token| token.text.contains('\n'));
+
+        after_newline || matches!(
+            self.tokens[position].kind,
+            TokenKind::LetKw | TokenKind::FnKw | TokenKind::StructKw | TokenKind::EnumKw
+        )
+    }
+
+    fn eat_trivia(&mut self) {
+        while let Some(token) = self.tokens.get(self.cursor).filter(|token| token.kind.is_trivia()) {
+            self.builder.token(token.kind, token.text);
+            self.cursor += 1;
+        }
+    }
+}
+
+/// Runs the grammar function `parse` over the tokens of `s` and lowers the
+/// node it produced with `lower`, handing back whatever source text follows
+/// the last consumed token. The first error reported along the way is
+/// returned even if `parse` itself recovered from it.
+pub(crate) fn parse_with<'a, T>(
+    s: &'a str,
+    parse: impl FnOnce(&mut Parser<'a>) -> Result<(), ParseError>,
+    lower: impl FnOnce(&SyntaxNode) -> Option<T>,
+) -> Result<(&'a str, T), String> {
+    parse_root_with(s, parse, |root| root.children().next().and_then(|node| lower(&node)))
+}
+
+/// Like [`parse_with`], but lowers the root node wrapping whatever `parse`
+/// produced, for grammar functions that emit bare tokens.
+pub(crate) fn parse_root_with<'a, T>(
+    s: &'a str,
+    parse: impl FnOnce(&mut Parser<'a>) -> Result<(), ParseError>,
+    lower: impl FnOnce(&SyntaxNode) -> Option<T>,
+) -> Result<(&'a str, T), String> {
+    let mut p = Parser::new(s);
+    parse(&mut p).map_err(|error| error.message)?;
+
+    if let Some(error) = p.errors().first() {
+        return Err(error.message.clone());
+    }
+
+    let consumed = p.consumed();
+    let (root, _) = p.finish();
+    let parsed = lower(&root).expect("tree without errors should always lower");
+
+    Ok((&s[consumed..], parsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_trivia_before_tokens() {
+        let mut p = Parser::new("  # comment\n  let");
+        assert_eq!(p.peek(), Some(TokenKind::LetKw));
+        assert_eq!(p.bump().text, "let");
+        assert_eq!(p.peek(), None);
+    }
+
+    #[test]
+    fn keep_trailing_text_after_last_consumed_token() {
+        assert_eq!(
+            parse_root_with(
+                "let  rest",
+                |p| p.expect(TokenKind::LetKw, "let").map(|_| ()),
+                |_| Some(())
+            ),
+            Ok(("  rest", ()))
+        );
+    }
+
+    #[test]
+    fn report_expected_token() {
+        let mut p = Parser::new("  fn");
+        assert_eq!(
+            p.expect(TokenKind::LetKw, "let"),
+            Err(ParseError {
+                message: "expected let".to_string(),
+                span: Span { start: 2, end: 4 },
+            })
+        );
+    }
+
+    #[test]
+    fn report_error_at_end_of_input() {
+        let mut p = Parser::new("let ");
+        p.bump();
+        assert_eq!(
+            p.expect(TokenKind::Ident, "identifier"),
+            Err(ParseError {
+                message: "expected identifier".to_string(),
+                span: Span { start: 4, end: 4 },
+            })
+        );
+    }
+
+    #[test]
+    fn only_adjacent_token_is_at_adjacent() {
+        let mut p = Parser::new("a( b (");
+        p.bump();
+        assert!(p.at_adjacent(TokenKind::LParen));
+        p.bump();
+        assert!(!p.at_adjacent(TokenKind::Ident));
+        assert!(p.at(TokenKind::Ident));
+    }
+
+    #[test]
+    fn nth_on_same_line_looks_past_spaces_only() {
+        let p = Parser::new("a {\n}");
+        assert!(p.nth_on_same_line(1));
+        assert!(!p.nth_on_same_line(2));
+        assert!(!p.nth_on_same_line(3));
+    }
+
+    #[test]
+    fn recover_at_next_line() {
+        let mut p = Parser::new("a b c\nd");
+        let start = p.position();
+        p.recover(start, false);
+        assert_eq!(p.bump().text, "d");
+    }
+
+    #[test]
+    fn recover_at_keyword() {
+        let mut p = Parser::new("a b let");
+        let start = p.position();
+        p.recover(start, false);
+        assert_eq!(p.bump().text, "let");
+    }
+
+    #[test]
+    fn recover_always_makes_progress() {
+        let mut p = Parser::new("let let");
+        let start = p.position();
+        p.recover(start, false);
+        assert_eq!(p.position(), 2);
+    }
+
+    #[test]
+    fn recover_skips_nested_blocks() {
+        let mut p = Parser::new("a { \n b } c\nd");
+        let start = p.position();
+        p.recover(start, false);
+        assert_eq!(p.bump().text, "d");
+    }
+
+    #[test]
+    fn recover_stops_before_closing_brace_in_block() {
+        let mut p = Parser::new("a b }");
+        let start = p.position();
+        p.recover(start, true);
+        assert!(p.at(TokenKind::RBrace));
+    }
+}
@@ -0,0 +1,715 @@
+//! Patterns of `match` arms.
+//!
+//! A pattern tests the shape of a value and binds names to its parts.
+//! Besides matching values, this module finds values that none of the arms
+//! of a `match` would match, using the usefulness algorithm described by
+//! Maranget in "Warnings for pattern matching".
+
+use crate::enum_def;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::val::Val;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `_`, which matches anything.
+    Wildcard,
+    /// A name, which matches anything and binds it.
+    Binding(PatBinding),
+    Number(i32),
+    /// `Shape::Rect(w, h)`, matching the values a variant holds by position.
+    Variant {
+        enum_name: String,
+        variant: String,
+        fields: Vec<Pattern>,
+    },
+    /// `(a, b)`, which matches a tuple of exactly that many values.
+    Tuple(Vec<Pattern>),
+    /// `[first, ..rest, last]`. Without a rest, the list has to have exactly
+    /// as many values as `prefix` and `suffix` is empty.
+    List {
+        prefix: Vec<Pattern>,
+        rest: Option<RestPattern>,
+        suffix: Vec<Pattern>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatBinding {
+    pub name: String,
+    /// The slot the resolver gave the binding in the scope of its arm.
+    /// Bindings without one are stored by name.
+    pub slot: Option<usize>,
+}
+
+/// The `..` of a list pattern, which matches any number of values in the
+/// middle and binds them as a list if it is followed by a name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestPattern {
+    pub binding: Option<PatBinding>,
+}
+
+impl Pattern {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        Self::parse_binding_names(p, &mut Vec::new())
+    }
+
+    /// Parses a pattern, reporting names that `names`, the names bound by
+    /// the pattern so far, already contains.
+    fn parse_binding_names(p: &mut Parser, names: &mut Vec<String>) -> Result<(), ParseError> {
+        match p.peek() {
+            Some(TokenKind::Underscore) => {
+                p.start_node(NodeKind::WildcardPat);
+                p.bump();
+                p.finish_node();
+            }
+            Some(TokenKind::Number | TokenKind::Minus) => {
+                p.start_node(NodeKind::LiteralPat);
+                let minus = p.at(TokenKind::Minus).then(|| p.bump());
+                let token = p.expect(TokenKind::Number, "digits")?;
+                p.finish_node();
+
+                let text = format!("{}{}", minus.map_or("", |minus| minus.text), token.text);
+                if text.parse::<i32>().is_err() {
+                    return Err(ParseError {
+                        message: format!("number literal '{}' is too large", text),
+                        span: token.span,
+                    });
+                }
+            }
+            Some(TokenKind::Ident)
+                if p.nth(1) == Some(TokenKind::ColonColon) || p.peek_text().and_then(enum_def::prelude_enum).is_some() =>
+            {
+                p.start_node(NodeKind::VariantPat);
+                if p.nth(1) == Some(TokenKind::ColonColon) {
+                    p.bump();
+                    p.bump();
+                }
+                p.expect(TokenKind::Ident, "variant name")?;
+                if p.at_adjacent(TokenKind::LParen) {
+                    p.bump();
+                    Self::parse_list(p, names, TokenKind::RParen, ", or )")?;
+                }
+                p.finish_node();
+            }
+            Some(TokenKind::Ident) => {
+                p.start_node(NodeKind::BindPat);
+                let name = p.bump();
+                p.finish_node();
+                bind(p, names, name.text, name.span);
+            }
+            Some(TokenKind::LParen) => {
+                p.start_node(NodeKind::TuplePat);
+                p.bump();
+                Self::parse_list(p, names, TokenKind::RParen, ", or )")?;
+                p.finish_node();
+            }
+            Some(TokenKind::LBracket) => {
+                p.start_node(NodeKind::ListPat);
+                p.bump();
+                Self::parse_list(p, names, TokenKind::RBracket, ", or ]")?;
+                p.finish_node();
+            }
+            _ => return Err(p.error("expected pattern".to_string())),
+        }
+        Ok(())
+    }
+
+    /// Parses comma separated patterns up to `close`. Only a list pattern,
+    /// closed by `]`, may have a rest among them.
+    fn parse_list(
+        p: &mut Parser,
+        names: &mut Vec<String>,
+        close: TokenKind,
+        expected: &str,
+    ) -> Result<(), ParseError> {
+        let mut has_rest = false;
+        while !p.at(close) {
+            if p.at(TokenKind::DotDot) && close == TokenKind::RBracket {
+                p.start_node(NodeKind::RestPat);
+                let dots = p.bump();
+                let name = p.at(TokenKind::Ident).then(|| p.bump());
+                p.finish_node();
+
+                if has_rest {
+                    p.report(ParseError {
+                        message: "a list pattern can only have one ..".to_string(),
+                        span: dots.span,
+                    });
+                }
+                has_rest = true;
+                if let Some(name) = name {
+                    bind(p, names, name.text, name.span);
+                }
+            } else {
+                Self::parse_binding_names(p, names)?;
+            }
+
+            if !p.at(TokenKind::Comma) {
+                break;
+            }
+            p.bump();
+        }
+
+        p.expect(close, expected)?;
+        Ok(())
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let idents = || {
+            node.child_tokens()
+                .filter(|token| token.kind() == TokenKind::Ident)
+                .map(|token| token.text().to_string())
+        };
+        let children = || node.children().map(|child| Self::lower(&child)).collect::<Option<Vec<_>>>();
+
+        match node.kind() {
+            NodeKind::WildcardPat => Some(Self::Wildcard),
+            NodeKind::BindPat => Some(Self::Binding(PatBinding {
+                name: idents().next()?,
+                slot: None,
+            })),
+            NodeKind::LiteralPat => {
+                let text: String = node.child_tokens().map(|token| token.text().to_string()).collect();
+                text.parse().ok().map(Self::Number)
+            }
+            NodeKind::VariantPat => {
+                let (enum_name, variant) = enum_def::lower_path(node)?;
+                Some(Self::Variant {
+                    enum_name,
+                    variant,
+                    fields: children()?,
+                })
+            }
+            // A single pattern in parentheses is just that pattern, while
+            // `(a,)` is a tuple of one.
+            NodeKind::TuplePat => {
+                let mut items = children()?;
+                let has_comma = node.child_tokens().any(|token| token.kind() == TokenKind::Comma);
+                match items.len() {
+                    1 if !has_comma => items.pop(),
+                    _ => Some(Self::Tuple(items)),
+                }
+            }
+            NodeKind::ListPat => {
+                let mut prefix = Vec::new();
+                let mut rest = None;
+                let mut suffix = Vec::new();
+                for child in node.children() {
+                    if child.kind() == NodeKind::RestPat {
+                        let binding = child
+                            .child_tokens()
+                            .find(|token| token.kind() == TokenKind::Ident)
+                            .map(|name| PatBinding {
+                                name: name.text().to_string(),
+                                slot: None,
+                            });
+                        rest = Some(RestPattern { binding });
+                    } else if rest.is_some() {
+                        suffix.push(Self::lower(&child)?);
+                    } else {
+                        prefix.push(Self::lower(&child)?);
+                    }
+                }
+                Some(Self::List { prefix, rest, suffix })
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether `val` matches the pattern, adding the values of the names it
+    /// binds to `bound` if it does.
+    pub(crate) fn matches<'p>(&'p self, val: &Val, bound: &mut Vec<(&'p PatBinding, Val)>) -> bool {
+        match (self, val) {
+            (Self::Wildcard, _) => true,
+            (Self::Binding(binding), val) => {
+                bound.push((binding, val.clone()));
+                true
+            }
+            (Self::Number(n), Val::Number(val)) => n == val,
+            (Self::Variant { enum_name, variant, fields }, Val::Enum(val)) => {
+                val.name == *enum_name && val.variant == *variant && matches_all(fields, &val.fields, bound)
+            }
+            (Self::Tuple(items), Val::Tuple(vals)) => matches_all(items, vals, bound),
+            (Self::List { prefix, rest: None, .. }, Val::List(vals)) => matches_all(prefix, vals, bound),
+            (Self::List { prefix, rest: Some(rest), suffix }, Val::List(vals)) => {
+                let Some(middle) = vals.len().checked_sub(prefix.len() + suffix.len()) else {
+                    return false;
+                };
+                let (start, rest_vals) = vals.split_at(prefix.len());
+                let (middle, end) = rest_vals.split_at(middle);
+                if !matches_all(prefix, start, bound) || !matches_all(suffix, end, bound) {
+                    return false;
+                }
+                if let Some(binding) = &rest.binding {
+                    bound.push((binding, Val::List(middle.into())));
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The names the pattern binds, in source order.
+    pub(crate) fn bindings(&self) -> Vec<&PatBinding> {
+        match self {
+            Self::Wildcard | Self::Number(_) => Vec::new(),
+            Self::Binding(binding) => vec![binding],
+            Self::Variant { fields: items, .. } | Self::Tuple(items) => items.iter().flat_map(Self::bindings).collect(),
+            Self::List { prefix, rest, suffix } => {
+                let rest = rest.as_ref().and_then(|rest| rest.binding.as_ref());
+                let mut bindings: Vec<_> = prefix.iter().flat_map(Self::bindings).collect();
+                bindings.extend(rest);
+                bindings.extend(suffix.iter().flat_map(Self::bindings));
+                bindings
+            }
+        }
+    }
+
+    /// Like [`Pattern::bindings`], but lets the slots of the names be set.
+    pub(crate) fn bindings_mut(&mut self) -> Vec<&mut PatBinding> {
+        match self {
+            Self::Wildcard | Self::Number(_) => Vec::new(),
+            Self::Binding(binding) => vec![binding],
+            Self::Variant { fields: items, .. } | Self::Tuple(items) => {
+                items.iter_mut().flat_map(Self::bindings_mut).collect()
+            }
+            Self::List { prefix, rest, suffix } => {
+                let rest = rest.as_mut().and_then(|rest| rest.binding.as_mut());
+                let mut bindings: Vec<_> = prefix.iter_mut().flat_map(Self::bindings_mut).collect();
+                bindings.extend(rest);
+                bindings.extend(suffix.iter_mut().flat_map(Self::bindings_mut));
+                bindings
+            }
+        }
+    }
+}
+
+/// Reports a name that the pattern being parsed binds twice.
+fn bind(p: &mut Parser, names: &mut Vec<String>, name: &str, span: crate::lexer::Span) {
+    if names.iter().any(|bound| bound == name) {
+        p.report(ParseError {
+            message: format!("'{}' is bound twice in the same pattern", name),
+            span,
+        });
+    }
+    names.push(name.to_string());
+}
+
+fn matches_all<'p>(patterns: &'p [Pattern], vals: &[Val], bound: &mut Vec<(&'p PatBinding, Val)>) -> bool {
+    patterns.len() == vals.len() && patterns.iter().zip(vals).all(|(pattern, val)| pattern.matches(val, bound))
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wildcard => write!(f, "_"),
+            Self::Binding(binding) => write!(f, "{}", binding.name),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Variant { enum_name, variant, fields } => {
+                if enum_def::prelude_enum(variant) != Some(enum_name.as_str()) {
+                    write!(f, "{}::", enum_name)?;
+                }
+                write!(f, "{}", variant)?;
+                if fields.is_empty() {
+                    return Ok(());
+                }
+                write!(f, "(")?;
+                fmt_list(f, fields.iter().map(ToString::to_string))?;
+                write!(f, ")")
+            }
+            Self::Tuple(items) => {
+                write!(f, "(")?;
+                fmt_list(f, items.iter().map(ToString::to_string))?;
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Self::List { prefix, rest, suffix } => {
+                let rest = rest.iter().map(|rest| match &rest.binding {
+                    Some(binding) => format!("..{}", binding.name),
+                    None => "..".to_string(),
+                });
+                let items = prefix
+                    .iter()
+                    .map(ToString::to_string)
+                    .chain(rest)
+                    .chain(suffix.iter().map(ToString::to_string));
+                write!(f, "[")?;
+                fmt_list(f, items)?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn fmt_list(f: &mut fmt::Formatter<'_>, items: impl Iterator<Item = String>) -> fmt::Result {
+    for (idx, item) in items.enumerate() {
+        if idx > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Looks up the variants of an enum by name, with how many values each
+/// holds.
+pub(crate) type Variants<'a> = dyn Fn(&str) -> Option<Vec<(String, usize)>> + 'a;
+
+/// A value that none of `patterns` match, written as a pattern, or `None`
+/// if they cover every value. `variants` gives the variants of an enum, with
+/// how many values each holds. The variants of enums it does not know are
+/// taken to be those the patterns mention, so that nothing is reported that
+/// may not be missing.
+pub(crate) fn uncovered(
+    patterns: &[&Pattern],
+    variants: &Variants,
+) -> Option<Pattern> {
+    let rows = patterns.iter().map(|pattern| vec![(*pattern).clone()]).collect();
+    missing(rows, 1, variants).map(|mut witness| witness.remove(0))
+}
+
+/// What a pattern requires of the outermost shape of a value.
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    Number(i32),
+    Variant { enum_name: String, variant: String, arity: usize },
+    Tuple(usize),
+    /// A list of `len` values, or of at least that many if `open`.
+    List { len: usize, open: bool },
+}
+
+impl Ctor {
+    fn of(pattern: &Pattern) -> Option<Self> {
+        match pattern {
+            Pattern::Wildcard | Pattern::Binding(_) => None,
+            Pattern::Number(n) => Some(Self::Number(*n)),
+            Pattern::Variant { enum_name, variant, fields } => Some(Self::Variant {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                arity: fields.len(),
+            }),
+            Pattern::Tuple(items) => Some(Self::Tuple(items.len())),
+            Pattern::List { prefix, rest, suffix } => Some(Self::List {
+                len: prefix.len() + suffix.len(),
+                open: rest.is_some(),
+            }),
+        }
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            Self::Number(_) => 0,
+            Self::Variant { arity, .. } => *arity,
+            Self::Tuple(arity) => *arity,
+            Self::List { len, .. } => *len,
+        }
+    }
+
+    /// The pattern of a value of this shape whose parts are `fields`.
+    fn pattern(&self, fields: Vec<Pattern>) -> Pattern {
+        match self {
+            Self::Number(n) => Pattern::Number(*n),
+            Self::Variant { enum_name, variant, .. } => Pattern::Variant {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                fields,
+            },
+            Self::Tuple(_) => Pattern::Tuple(fields),
+            Self::List { open, .. } => Pattern::List {
+                prefix: fields,
+                rest: open.then_some(RestPattern { binding: None }),
+                suffix: Vec::new(),
+            },
+        }
+    }
+}
+
+/// Values of `width` parts, written as patterns, that none of `rows` match.
+fn missing(
+    rows: Vec<Vec<Pattern>>,
+    width: usize,
+    variants: &Variants,
+) -> Option<Vec<Pattern>> {
+    if width == 0 {
+        return rows.is_empty().then(Vec::new);
+    }
+
+    let heads: Vec<_> = rows.iter().filter_map(|row| Ctor::of(&row[0])).collect();
+    match signature(&heads, variants) {
+        Some(ctors) => ctors.into_iter().find_map(|ctor| {
+            let specialized = rows.iter().filter_map(|row| specialize(row, &ctor)).collect();
+            let mut fields = missing(specialized, ctor.arity() + width - 1, variants)?;
+            let rest = fields.split_off(ctor.arity());
+            Some([ctor.pattern(fields)].into_iter().chain(rest).collect())
+        }),
+        None => {
+            let default = rows
+                .iter()
+                .filter(|row| Ctor::of(&row[0]).is_none())
+                .map(|row| row[1..].to_vec())
+                .collect();
+            let rest = missing(default, width - 1, variants)?;
+            Some([missing_head(&heads)].into_iter().chain(rest).collect())
+        }
+    }
+}
+
+/// Every shape a value matched by `heads` can have, or `None` if there are
+/// more of them than patterns can list, as for numbers.
+fn signature(heads: &[Ctor], variants: &Variants) -> Option<Vec<Ctor>> {
+    match heads.first()? {
+        Ctor::Number(_) => None,
+        Ctor::Variant { enum_name, .. } => {
+            let ctors = match variants(enum_name) {
+                Some(variants) => variants
+                    .into_iter()
+                    .map(|(variant, arity)| Ctor::Variant {
+                        enum_name: enum_name.clone(),
+                        variant,
+                        arity,
+                    })
+                    .collect(),
+                None => {
+                    let mut ctors = Vec::new();
+                    for ctor in heads {
+                        if !ctors.contains(ctor) {
+                            ctors.push(ctor.clone());
+                        }
+                    }
+                    ctors
+                }
+            };
+            Some(ctors)
+        }
+        Ctor::Tuple(arity) => Some(vec![Ctor::Tuple(*arity)]),
+        // Lists longer than every pattern without a rest, and long enough
+        // for every pattern with one, are all matched alike.
+        Ctor::List { .. } => {
+            if !heads.iter().any(|ctor| matches!(ctor, Ctor::List { open: true, .. })) {
+                return None;
+            }
+            let max_len = heads.iter().map(Ctor::arity).max().unwrap_or_default();
+            let mut ctors: Vec<_> = (0..=max_len).map(|len| Ctor::List { len, open: false }).collect();
+            ctors.push(Ctor::List {
+                len: max_len + 1,
+                open: true,
+            });
+            Some(ctors)
+        }
+    }
+}
+
+/// A pattern for the values of a column that `heads` do not cover, which
+/// are missing from a signature that is not complete.
+fn missing_head(heads: &[Ctor]) -> Pattern {
+    let lens: Vec<_> = heads
+        .iter()
+        .filter_map(|ctor| match ctor {
+            Ctor::List { len, .. } => Some(*len),
+            _ => None,
+        })
+        .collect();
+    if lens.is_empty() {
+        return Pattern::Wildcard;
+    }
+
+    let len = (0..).find(|len| !lens.contains(len)).unwrap_or_default();
+    Ctor::List { len, open: false }.pattern(vec![Pattern::Wildcard; len])
+}
+
+/// The rest of `row` once its first value is known to have the shape
+/// `ctor`, with the parts of that value first, or `None` if the row does not
+/// match such a value.
+fn specialize(row: &[Pattern], ctor: &Ctor) -> Option<Vec<Pattern>> {
+    let (head, tail) = row.split_first()?;
+    let mut fields = match head {
+        Pattern::Wildcard | Pattern::Binding(_) => vec![Pattern::Wildcard; ctor.arity()],
+        Pattern::Number(n) if *ctor == Ctor::Number(*n) => Vec::new(),
+        Pattern::Variant { fields, .. } if Ctor::of(head).as_ref() == Some(ctor) => fields.clone(),
+        Pattern::Tuple(items) if *ctor == Ctor::Tuple(items.len()) => items.clone(),
+        Pattern::List { prefix, rest: None, .. } if matches!(ctor, Ctor::List { len, .. } if *len == prefix.len()) => {
+            prefix.clone()
+        }
+        Pattern::List { prefix, rest: Some(_), suffix } => {
+            let Ctor::List { len, .. } = ctor else {
+                return None;
+            };
+            let middle = len.checked_sub(prefix.len() + suffix.len())?;
+            let mut fields = prefix.clone();
+            fields.extend(vec![Pattern::Wildcard; middle]);
+            fields.extend(suffix.iter().cloned());
+            fields
+        }
+        _ => return None,
+    };
+    fields.extend(tail.iter().cloned());
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::val::EnumVal;
+    use std::rc::Rc;
+
+    fn pattern(s: &str) -> Pattern {
+        Pattern::new(s).unwrap().1
+    }
+
+    fn bound(pattern: &Pattern, val: &Val) -> Option<Vec<(String, Val)>> {
+        let mut bound = Vec::new();
+        pattern.matches(val, &mut bound).then(|| {
+            bound
+                .into_iter()
+                .map(|(binding, val)| (binding.name.clone(), val))
+                .collect()
+        })
+    }
+
+    fn list(vals: &[i32]) -> Val {
+        Val::List(vals.iter().copied().map(Val::Number).collect())
+    }
+
+    fn tuple(vals: &[i32]) -> Val {
+        Val::Tuple(vals.iter().copied().map(Val::Number).collect())
+    }
+
+    fn shapes(name: &str) -> Option<Vec<(String, usize)>> {
+        (name == "Shape").then(|| {
+            vec![
+                ("Circle".to_string(), 1),
+                ("Rect".to_string(), 2),
+                ("Empty".to_string(), 0),
+            ]
+        })
+    }
+
+    fn uncovered_by(patterns: &[&str]) -> Option<String> {
+        let patterns: Vec<_> = patterns.iter().map(|src| pattern(src)).collect();
+        let patterns: Vec<_> = patterns.iter().collect();
+        uncovered(&patterns, &shapes).map(|witness| witness.to_string())
+    }
+
+    #[test]
+    fn parse_and_display_patterns() {
+        for src in ["_", "x", "-5", "Shape::Rect(w, _)", "Shape::Empty", "(a, [b, ..])", "(a,)", "[first, ..rest, last]", "[]"] {
+            assert_eq!(pattern(src).to_string(), src);
+        }
+        assert_eq!(pattern("(x)"), pattern("x"));
+    }
+
+    #[test]
+    fn refuse_malformed_patterns() {
+        assert_eq!(Pattern::new("(a, a)"), Err("'a' is bound twice in the same pattern".to_string()));
+        assert_eq!(Pattern::new("[.., ..]"), Err("a list pattern can only have one ..".to_string()));
+        assert_eq!(Pattern::new("(a, ..)"), Err("expected pattern".to_string()));
+        assert_eq!(Pattern::new("+"), Err("expected pattern".to_string()));
+    }
+
+    #[test]
+    fn match_numbers_and_bind_names() {
+        assert_eq!(bound(&pattern("1"), &Val::Number(1)), Some(vec![]));
+        assert_eq!(bound(&pattern("-1"), &Val::Number(1)), None);
+        assert_eq!(bound(&pattern("_"), &Val::Unit), Some(vec![]));
+        assert_eq!(bound(&pattern("x"), &Val::Unit), Some(vec![("x".to_string(), Val::Unit)]));
+    }
+
+    #[test]
+    fn match_variants_by_enum_name_and_position() {
+        let rect = Val::Enum(Rc::new(EnumVal {
+            name: "Shape".to_string(),
+            variant: "Rect".to_string(),
+            fields: vec![Val::Number(1), Val::Number(2)],
+        }));
+        assert_eq!(
+            bound(&pattern("Shape::Rect(w, 2)"), &rect),
+            Some(vec![("w".to_string(), Val::Number(1))])
+        );
+        assert_eq!(bound(&pattern("Shape::Rect(w, 3)"), &rect), None);
+        assert_eq!(bound(&pattern("Shape::Circle(r)"), &rect), None);
+        assert_eq!(bound(&pattern("Form::Rect(w, h)"), &rect), None);
+    }
+
+    #[test]
+    fn match_prelude_variants_without_enum_name() {
+        assert_eq!(pattern("Some(x)"), pattern("Option::Some(x)"));
+        assert_eq!(pattern("Option::None").to_string(), "None");
+        assert_eq!(
+            bound(&pattern("Ok(Some(x))"), &Val::ok(Val::some(1))),
+            Some(vec![("x".to_string(), Val::Number(1))])
+        );
+        assert_eq!(bound(&pattern("Err(_)"), &Val::ok(1)), None);
+    }
+
+    #[test]
+    fn match_tuples_and_lists() {
+        assert_eq!(
+            bound(&pattern("(a, 2)"), &tuple(&[1, 2])),
+            Some(vec![("a".to_string(), Val::Number(1))])
+        );
+        assert_eq!(bound(&pattern("(a, b)"), &tuple(&[1, 2, 3])), None);
+        assert_eq!(bound(&pattern("(a, b)"), &list(&[1, 2])), None);
+        assert_eq!(bound(&pattern("[a, b]"), &tuple(&[1, 2])), None);
+        assert_eq!(
+            bound(&pattern("[first, ..rest, last]"), &list(&[1, 2, 3, 4])),
+            Some(vec![
+                ("first".to_string(), Val::Number(1)),
+                ("last".to_string(), Val::Number(4)),
+                ("rest".to_string(), list(&[2, 3])),
+            ])
+        );
+        assert_eq!(bound(&pattern("[a, .., b]"), &list(&[1])), None);
+        assert_eq!(bound(&pattern("[..]"), &list(&[])), Some(vec![]));
+        assert_eq!(bound(&pattern("[a]"), &Val::Number(1)), None);
+    }
+
+    #[test]
+    fn list_bindings_in_source_order() {
+        let names: Vec<_> = pattern("(a, [b, ..c, d], Shape::Rect(e, _))")
+            .bindings()
+            .into_iter()
+            .map(|binding| binding.name.clone())
+            .collect();
+        assert_eq!(names, vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn find_uncovered_variants() {
+        assert_eq!(uncovered_by(&["Shape::Circle(_)", "Shape::Rect(_, _)", "Shape::Empty"]), None);
+        assert_eq!(
+            uncovered_by(&["Shape::Circle(_)", "Shape::Empty"]),
+            Some("Shape::Rect(_, _)".to_string())
+        );
+        assert_eq!(
+            uncovered_by(&["Shape::Circle(1)", "Shape::Rect(_, _)", "Shape::Empty"]),
+            Some("Shape::Circle(_)".to_string())
+        );
+        assert_eq!(uncovered_by(&["Shape::Circle(_)", "other"]), None);
+        // Unknown enums are not reported.
+        assert_eq!(uncovered_by(&["Light::Red"]), None);
+    }
+
+    #[test]
+    fn find_uncovered_numbers_tuples_and_lists() {
+        assert_eq!(uncovered_by(&["1", "2"]), Some("_".to_string()));
+        assert_eq!(uncovered_by(&[]), Some("_".to_string()));
+        assert_eq!(
+            uncovered_by(&["(0, _)", "(_, Shape::Empty)"]),
+            Some("(_, Shape::Circle(_))".to_string())
+        );
+        assert_eq!(uncovered_by(&["[]", "[_]"]), Some("[_, _]".to_string()));
+        assert_eq!(uncovered_by(&["[]", "[_, ..]"]), None);
+        assert_eq!(uncovered_by(&["[]", "[.., 1]"]), Some("[_]".to_string()));
+        assert_eq!(uncovered_by(&["[]", "[_]", "[_, _, _, ..]"]), Some("[_, _]".to_string()));
+        assert_eq!(uncovered_by(&["[_, _, ..]", "[x]"]), Some("[]".to_string()));
+    }
+}
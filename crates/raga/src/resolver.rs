@@ -0,0 +1,562 @@
+//! Resolves names before evaluation.
+//!
+//! A name refers to the closest binding or function defined before it in an
+//! enclosing block or function body, just like the compiler resolves it for
+//! the virtual machine. Function bodies can also call functions defined
+//! later in an enclosing block, as they can later globals. Bindings found this way are given slots, so that
+//! evaluation reads them by index. Everything else is a global, which is
+//! only known once the environment the program runs in is.
+
+use crate::expr::{Expr, NameLoc};
+use crate::stmt::Stmt;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// What the resolver found out about a program. Names are referred to by
+/// their index among every binding usage and callee of the program, in
+/// source order.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Resolution {
+    /// Names that refer to neither a scope nor a global of the program
+    /// itself, so they have to be defined by the environment.
+    pub(crate) globals: Vec<GlobalRef>,
+    /// Names used as something they are not, with an error message.
+    pub(crate) errors: Vec<(usize, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GlobalRef {
+    pub(crate) idx: usize,
+    pub(crate) name: String,
+    pub(crate) is_call: bool,
+}
+
+/// Resolves every name of a program, filling in where to find it.
+pub(crate) fn resolve(stmts: &mut [Stmt]) -> Resolution {
+    let mut resolver = Resolver::default();
+    for stmt in stmts {
+        resolver.stmt(stmt);
+    }
+
+    // Function bodies run once the whole program has, so they can use any
+    // of its globals. They are only known to be misused if no definition
+    // of the global fits.
+    let Resolver {
+        globals,
+        mut in_funcs,
+        mut resolution,
+        ..
+    } = resolver;
+    in_funcs.retain(|global| {
+        let Some(kinds) = globals.get(&global.name) else {
+            return true;
+        };
+        let mut misuses = kinds.iter().map(|kind| kind.misuse(&global.name, global.is_call));
+        if let Some(message) = misuses.try_fold(None, |_, misuse| misuse.map(Some)).flatten() {
+            resolution.errors.push((global.idx, message));
+        }
+        false
+    });
+    resolution.globals.append(&mut in_funcs);
+    resolution.globals.sort_by_key(|global| global.idx);
+    resolution
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Symbol {
+    Local(usize),
+    Func,
+    Struct,
+    Enum,
+}
+
+impl Symbol {
+    fn kind(self) -> Kind {
+        match self {
+            Self::Local(_) => Kind::Binding,
+            Self::Func => Kind::Func,
+            Self::Struct => Kind::Struct,
+            Self::Enum => Kind::Enum,
+        }
+    }
+}
+
+/// What a name is defined as, which decides how it can be used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Binding,
+    Func,
+    Struct,
+    Enum,
+}
+
+impl Kind {
+    /// The error of using a name of this kind as a binding, or of calling
+    /// it if `is_call`, unless it can be used that way.
+    fn misuse(self, name: &str, is_call: bool) -> Option<String> {
+        let wanted = if is_call { "function" } else { "binding" };
+        match (self, is_call) {
+            (Self::Binding, false) | (Self::Func, true) => None,
+            (Self::Binding, true) => Some(format!("'{}' is a binding, not a function", name)),
+            (Self::Func, false) => Some(format!("'{}' is a function, not a binding", name)),
+            (Self::Struct, _) => Some(format!("'{}' is a struct, not a {}", name, wanted)),
+            (Self::Enum, _) => Some(format!("'{}' is an enum, not a {}", name, wanted)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Scope {
+    names: HashMap<String, Symbol>,
+    num_slots: usize,
+    /// Functions the block defines anywhere in it, which function bodies
+    /// in the block may call before they are defined.
+    funcs: HashSet<String>,
+    /// How many function bodies the scope itself is in.
+    func_depth: usize,
+}
+
+#[derive(Debug, Default)]
+struct Resolver {
+    scopes: Vec<Scope>,
+    /// How many function bodies the resolver is in.
+    func_depth: usize,
+    /// Globals the program has defined so far, with what each definition
+    /// of them was, in order.
+    globals: HashMap<String, Vec<Kind>>,
+    /// References to globals from function bodies, checked at the end.
+    in_funcs: Vec<GlobalRef>,
+    next_idx: usize,
+    resolution: Resolution,
+}
+
+impl Resolver {
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::BindingDef(binding_def) => {
+                self.expr(&mut binding_def.val);
+                binding_def.slot = self.declare_local(&binding_def.name);
+            }
+            Stmt::FuncDef(func_def) => {
+                // The function is in scope inside its own body, so it can
+                // call itself.
+                match self.scopes.last_mut() {
+                    Some(scope) => {
+                        scope.names.insert(func_def.name.clone(), Symbol::Func);
+                    }
+                    None => self.define_global(&func_def.name, Kind::Func),
+                }
+
+                let mut scope = Scope::default();
+                for param in &func_def.params {
+                    declare(&mut scope, param);
+                }
+                self.scopes.push(scope);
+                self.func_depth += 1;
+                self.stmt(Rc::make_mut(&mut func_def.body));
+                self.func_depth -= 1;
+                self.scopes.pop();
+            }
+            // Structs are looked up by name when a value of one is built,
+            // but hide bindings and functions of the same name all the same.
+            Stmt::StructDef(struct_def) => match self.scopes.last_mut() {
+                Some(scope) => {
+                    scope.names.insert(struct_def.name.clone(), Symbol::Struct);
+                }
+                None => self.define_global(&struct_def.name, Kind::Struct),
+            },
+            Stmt::EnumDef(enum_def) => match self.scopes.last_mut() {
+                Some(scope) => {
+                    scope.names.insert(enum_def.name.clone(), Symbol::Enum);
+                }
+                None => self.define_global(&enum_def.name, Kind::Enum),
+            },
+            Stmt::Expr(expr) => self.expr(expr),
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Number(_) | Expr::Bool(_) => {}
+            Expr::Operation { lhs, rhs, .. } => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::BindingUsage(binding_usage) => {
+                binding_usage.loc = Some(self.name(&binding_usage.name, false));
+            }
+            Expr::Block(block) => {
+                self.scopes.push(Scope {
+                    funcs: block
+                        .stmts
+                        .iter()
+                        .filter_map(|stmt| match stmt {
+                            Stmt::FuncDef(func_def) => Some(func_def.name.clone()),
+                            _ => None,
+                        })
+                        .collect(),
+                    func_depth: self.func_depth,
+                    ..Scope::default()
+                });
+                for stmt in &mut block.stmts {
+                    self.stmt(stmt);
+                }
+                self.scopes.pop();
+            }
+            Expr::FuncCall(func_call) => {
+                func_call.loc = Some(self.name(&func_call.callee, true));
+                for arg in &mut func_call.args {
+                    self.expr(arg);
+                }
+            }
+            Expr::FieldAccess(field_access) => self.expr(&mut field_access.target),
+            Expr::Try(try_expr) => self.expr(&mut try_expr.inner),
+            Expr::MethodCall(method_call) => {
+                self.expr(&mut method_call.target);
+                for arg in &mut method_call.args {
+                    self.expr(arg);
+                }
+            }
+            Expr::StructLit(struct_lit) => {
+                for (_, val) in &mut struct_lit.fields {
+                    self.expr(val);
+                }
+                if let Some(base) = &mut struct_lit.base {
+                    self.expr(base);
+                }
+            }
+            Expr::VariantLit(variant_lit) => {
+                for arg in &mut variant_lit.args {
+                    self.expr(arg);
+                }
+            }
+            Expr::Tuple(tuple) => {
+                for item in &mut tuple.items {
+                    self.expr(item);
+                }
+            }
+            // Each arm is a scope, holding the names its pattern binds.
+            Expr::Match(match_expr) => {
+                self.expr(&mut match_expr.scrutinee);
+                for arm in &mut match_expr.arms {
+                    let mut scope = Scope::default();
+                    for binding in arm.pattern.bindings_mut() {
+                        binding.slot = Some(declare(&mut scope, &binding.name));
+                    }
+                    self.scopes.push(scope);
+                    if let Some(guard) = &mut arm.guard {
+                        self.expr(guard);
+                    }
+                    self.expr(&mut arm.body);
+                    self.scopes.pop();
+                }
+            }
+        }
+    }
+
+    /// Gives `name` a slot in the innermost scope, or returns `None` if it
+    /// is a global.
+    fn declare_local(&mut self, name: &str) -> Option<usize> {
+        match self.scopes.last_mut() {
+            Some(scope) => Some(declare(scope, name)),
+            None => {
+                self.define_global(name, Kind::Binding);
+                None
+            }
+        }
+    }
+
+    fn define_global(&mut self, name: &str, kind: Kind) {
+        self.globals.entry(name.to_string()).or_default().push(kind);
+    }
+
+    /// Resolves a name that is used as a binding, or called if `is_call`.
+    fn name(&mut self, name: &str, is_call: bool) -> NameLoc {
+        let idx = self.next_idx;
+        self.next_idx += 1;
+
+        let func_depth = self.func_depth;
+        let found = self.scopes.iter().rev().enumerate().find_map(|(depth, scope)| {
+            let symbol = match scope.names.get(name) {
+                Some(symbol) => *symbol,
+                None if scope.func_depth < func_depth && scope.funcs.contains(name) => Symbol::Func,
+                None => return None,
+            };
+            Some((depth, symbol))
+        });
+
+        match found {
+            Some((depth, symbol)) => {
+                if let Some(message) = symbol.kind().misuse(name, is_call) {
+                    self.error(idx, message);
+                }
+                match symbol {
+                    Symbol::Local(slot) => NameLoc::Local { depth, slot },
+                    _ => NameLoc::Named { depth },
+                }
+            }
+            None => {
+                let global = GlobalRef {
+                    idx,
+                    name: name.to_string(),
+                    is_call,
+                };
+                if self.func_depth > 0 {
+                    self.in_funcs.push(global);
+                } else if let Some(kinds) = self.globals.get(name) {
+                    // Outside of functions, the latest definition is the
+                    // one in effect.
+                    let kind = *kinds.last().expect("globals are defined at least once");
+                    if let Some(message) = kind.misuse(name, is_call) {
+                        self.error(idx, message);
+                    }
+                } else {
+                    self.resolution.globals.push(global);
+                }
+                NameLoc::Named {
+                    depth: self.scopes.len(),
+                }
+            }
+        }
+    }
+
+    fn error(&mut self, idx: usize, message: String) {
+        self.resolution.errors.push((idx, message));
+    }
+}
+
+/// Gives `name` a slot in `scope`. Defining a name again in the same scope
+/// reuses its slot, just like it replaces a binding stored by name.
+fn declare(scope: &mut Scope, name: &str) -> usize {
+    if let Some(Symbol::Local(slot)) = scope.names.get(name) {
+        return *slot;
+    }
+
+    let slot = scope.num_slots;
+    scope.num_slots += 1;
+    scope.names.insert(name.to_string(), Symbol::Local(slot));
+    slot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::FuncCall;
+
+    fn resolve_src(src: &str) -> (Vec<Stmt>, Resolution) {
+        let parse = crate::parse(src);
+        assert!(parse.errors().is_empty(), "{:?}", parse.errors());
+        let mut stmts = parse.stmts().to_vec();
+        let resolution = resolve(&mut stmts);
+        (stmts, resolution)
+    }
+
+    fn global(idx: usize, name: &str, is_call: bool) -> GlobalRef {
+        GlobalRef {
+            idx,
+            name: name.to_string(),
+            is_call,
+        }
+    }
+
+    #[test]
+    fn give_block_bindings_slots() {
+        let (stmts, _) = resolve_src("{\n    let a = 1\n    let b = a\n    let a = b\n    a\n}");
+        let Stmt::Expr(Expr::Block(block)) = &stmts[0] else {
+            panic!("expected block, got {:?}", stmts[0]);
+        };
+
+        let slots: Vec<_> = block
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::BindingDef(binding_def) => binding_def.slot,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(slots, vec![0, 1, 0]);
+        assert_eq!(
+            block.stmts[3],
+            Stmt::Expr(Expr::BindingUsage(crate::expr::BindingUsage {
+                name: "a".to_string(),
+                loc: Some(NameLoc::Local { depth: 0, slot: 0 }),
+            }))
+        );
+    }
+
+    #[test]
+    fn resolve_params_and_outer_bindings() {
+        let (stmts, resolution) = resolve_src("fn outer x => {\n    fn inner y => x + y\n    inner(x)\n}");
+        assert_eq!(resolution, Resolution::default());
+
+        let Stmt::FuncDef(outer) = &stmts[0] else {
+            panic!("expected function, got {:?}", stmts[0]);
+        };
+        let Stmt::Expr(Expr::Block(body)) = &*outer.body else {
+            panic!("expected block, got {:?}", outer.body);
+        };
+
+        let Stmt::FuncDef(inner) = &body.stmts[0] else {
+            panic!("expected function, got {:?}", body.stmts[0]);
+        };
+        let Stmt::Expr(Expr::Operation { lhs, rhs, .. }) = &*inner.body else {
+            panic!("expected operation, got {:?}", inner.body);
+        };
+        assert!(matches!(
+            **lhs,
+            Expr::BindingUsage(ref usage) if usage.loc == Some(NameLoc::Local { depth: 2, slot: 0 })
+        ));
+        assert!(matches!(
+            **rhs,
+            Expr::BindingUsage(ref usage) if usage.loc == Some(NameLoc::Local { depth: 0, slot: 0 })
+        ));
+
+        let Stmt::Expr(Expr::FuncCall(FuncCall { loc, args, .. })) = &body.stmts[1] else {
+            panic!("expected call, got {:?}", body.stmts[1]);
+        };
+        assert_eq!(*loc, Some(NameLoc::Named { depth: 0 }));
+        assert!(matches!(
+            args[0],
+            Expr::BindingUsage(ref usage) if usage.loc == Some(NameLoc::Local { depth: 1, slot: 0 })
+        ));
+    }
+
+    #[test]
+    fn report_globals_program_does_not_define() {
+        let (_, resolution) = resolve_src("let a = b\nf(a, c)\nlet c = 1\nfn g => h() + c");
+        assert_eq!(
+            resolution.globals,
+            vec![global(0, "b", false), global(1, "f", true), global(3, "c", false), global(4, "h", true)]
+        );
+    }
+
+    #[test]
+    fn let_functions_use_later_globals() {
+        let (stmts, resolution) = resolve_src("fn f => {\n    g()\n}\nfn g => a\nlet a = 1");
+        assert_eq!(resolution, Resolution::default());
+
+        let Stmt::FuncDef(f) = &stmts[0] else {
+            panic!("expected function, got {:?}", stmts[0]);
+        };
+        let Stmt::Expr(Expr::Block(body)) = &*f.body else {
+            panic!("expected block, got {:?}", f.body);
+        };
+        assert!(matches!(
+            body.stmts[0],
+            Stmt::Expr(Expr::FuncCall(ref call)) if call.loc == Some(NameLoc::Named { depth: 2 })
+        ));
+    }
+
+    #[test]
+    fn let_block_functions_call_later_ones() {
+        let (stmts, resolution) = resolve_src("{\n    fn g => h()\n    fn h => 3\n    g()\n}");
+        assert_eq!(resolution, Resolution::default());
+
+        let Stmt::Expr(Expr::Block(block)) = &stmts[0] else {
+            panic!("expected block, got {:?}", stmts[0]);
+        };
+        let Stmt::FuncDef(g) = &block.stmts[0] else {
+            panic!("expected function, got {:?}", block.stmts[0]);
+        };
+        assert!(matches!(
+            *g.body,
+            Stmt::Expr(Expr::FuncCall(ref call)) if call.loc == Some(NameLoc::Named { depth: 1 })
+        ));
+
+        // The block itself only sees functions once they are defined.
+        let (_, resolution) = resolve_src("{\n    h()\n    fn h => 3\n}");
+        assert_eq!(resolution.globals, vec![global(0, "h", true)]);
+    }
+
+    #[test]
+    fn report_misused_names() {
+        let (_, resolution) = resolve_src("{\n    let a = 1\n    fn f => 2\n    a() + f\n}");
+        assert_eq!(
+            resolution.errors,
+            vec![
+                (0, "'a' is a binding, not a function".to_string()),
+                (1, "'f' is a function, not a binding".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_misused_globals() {
+        let (_, resolution) = resolve_src("fn f => 1\nlet g = 1\nf + 1\ng()\nfn h => f + g() + i\nlet i = 2");
+        assert_eq!(
+            resolution.errors,
+            vec![
+                (0, "'f' is a function, not a binding".to_string()),
+                (1, "'g' is a binding, not a function".to_string()),
+                (2, "'f' is a function, not a binding".to_string()),
+                (3, "'g' is a binding, not a function".to_string()),
+            ]
+        );
+        assert_eq!(resolution.globals, Vec::new());
+    }
+
+    #[test]
+    fn let_globals_change_kind() {
+        let (_, resolution) = resolve_src("let f = 1\nf\nfn f => 2\nf()\nfn g => f + f()");
+        assert_eq!(resolution, Resolution::default());
+    }
+
+    #[test]
+    fn report_structs_used_as_names() {
+        let (_, resolution) = resolve_src("fn f => {\n    struct P { x }\n    P + P()\n}");
+        assert_eq!(
+            resolution.errors,
+            vec![
+                (0, "'P' is a struct, not a binding".to_string()),
+                (1, "'P' is a struct, not a function".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn give_match_arm_bindings_slots_of_their_own() {
+        let (stmts, resolution) = resolve_src("let x = 1
+match x {
+    (a, b) if a => b,
+    [c, ..d] => c + x,
+}");
+        assert_eq!(resolution, Resolution::default());
+
+        let Stmt::Expr(Expr::Match(match_expr)) = &stmts[1] else {
+            panic!("expected match, got {:?}", stmts[1]);
+        };
+        let slots: Vec<Vec<_>> = match_expr
+            .arms
+            .iter()
+            .map(|arm| arm.pattern.bindings().iter().map(|binding| binding.slot).collect())
+            .collect();
+        assert_eq!(slots, vec![vec![Some(0), Some(1)], vec![Some(0), Some(1)]]);
+
+        let Some(Expr::BindingUsage(guard)) = &match_expr.arms[0].guard else {
+            panic!("expected binding usage, got {:?}", match_expr.arms[0].guard);
+        };
+        assert_eq!(guard.loc, Some(NameLoc::Local { depth: 0, slot: 0 }));
+        let Expr::Operation { rhs, .. } = &match_expr.arms[1].body else {
+            panic!("expected operation, got {:?}", match_expr.arms[1].body);
+        };
+        assert!(matches!(
+            **rhs,
+            Expr::BindingUsage(ref usage) if usage.loc == Some(NameLoc::Named { depth: 1 })
+        ));
+    }
+
+    #[test]
+    fn report_enums_used_as_names() {
+        let (_, resolution) = resolve_src("{
+    enum E { A }
+    E + E(E::A)
+}");
+        assert_eq!(
+            resolution.errors,
+            vec![
+                (0, "'E' is an enum, not a binding".to_string()),
+                (1, "'E' is an enum, not a function".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,796 @@
+//! Serde support for [`Val`], enabled by the `serde` feature.
+//!
+//! Besides `Serialize` and `Deserialize` impls for `Val` itself, [`to_val`]
+//! and [`from_val`] convert between `Val` and any serde type directly, much
+//! like `serde_json::to_value` does. Structs and maps become `Map`s,
+//! sequences and tuples `List`s and `None` and `()` become `Unit`, though
+//! `Option`s read back from the built-in `Some` and `None` too. Enum
+//! variants are externally tagged: a unit variant is the `String` of its
+//! name, any other variant a `Map` with its name as the only key. Variants
+//! of enums a script defined are converted the same way.
+
+use crate::val::{ConversionError, EnumVal};
+use crate::Val;
+use serde::de::value::{MapDeserializer, SeqDeserializer, StringDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer, Serializer};
+use std::rc::Rc;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Converts any serializable value into a [`Val`].
+pub fn to_val<T: Serialize + ?Sized>(value: &T) -> Result<Val, ConversionError> {
+    value.serialize(ValSerializer)
+}
+
+/// Converts a [`Val`] into any deserializable type.
+pub fn from_val<T: DeserializeOwned>(val: Val) -> Result<T, ConversionError> {
+    T::deserialize(val)
+}
+
+impl ser::Error for ConversionError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl de::Error for ConversionError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl Serialize for Val {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Number(n) => serializer.serialize_i32(*n),
+            Self::Float(n) => serializer.serialize_f64(*n),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Str(s) => serializer.serialize_str(s),
+            Self::List(vals) | Self::Tuple(vals) => serializer.collect_seq(vals.iter()),
+            Self::Map(entries) => serializer.collect_map(entries.iter()),
+            Self::Struct(val) => serializer.collect_map(val.fields.iter().map(|(name, val)| (name, val))),
+            Self::Enum(val) => tagged_variant(val).serialize(serializer),
+            Self::Object(object) => Err(ser::Error::custom(format!(
+                "cannot serialize host object {}",
+                object.type_name()
+            ))),
+            Self::Unit => serializer.serialize_unit(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Val {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValVisitor)
+    }
+}
+
+struct ValVisitor;
+
+impl<'de> Visitor<'de> for ValVisitor {
+    type Value = Val;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a raga value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Val, E> {
+        Ok(Val::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<Val, E> {
+        Val::try_from(n).map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Val, E> {
+        Val::try_from(n).map_err(E::custom)
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Val, E> {
+        Ok(Val::Float(n))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Val, E> {
+        Ok(Val::Str(s.into()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Val, E> {
+        Ok(Val::Str(s.into()))
+    }
+
+    fn visit_unit<E>(self) -> Result<Val, E> {
+        Ok(Val::Unit)
+    }
+
+    fn visit_none<E>(self) -> Result<Val, E> {
+        Ok(Val::Unit)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Val, D::Error> {
+        Val::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Val, A::Error> {
+        let mut vals = Vec::new();
+        while let Some(val) = seq.next_element()? {
+            vals.push(val);
+        }
+        Ok(Val::List(vals.into()))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Val, A::Error> {
+        let mut entries = BTreeMap::new();
+        while let Some((key, val)) = map.next_entry()? {
+            entries.insert(key, val);
+        }
+        Ok(Val::Map(Rc::new(entries)))
+    }
+}
+
+struct ValSerializer;
+
+impl Serializer for ValSerializer {
+    type Ok = Val;
+    type Error = ConversionError;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, b: bool) -> Result<Val, ConversionError> {
+        Ok(Val::Bool(b))
+    }
+
+    fn serialize_i8(self, n: i8) -> Result<Val, ConversionError> {
+        Ok(n.into())
+    }
+
+    fn serialize_i16(self, n: i16) -> Result<Val, ConversionError> {
+        Ok(n.into())
+    }
+
+    fn serialize_i32(self, n: i32) -> Result<Val, ConversionError> {
+        Ok(n.into())
+    }
+
+    fn serialize_i64(self, n: i64) -> Result<Val, ConversionError> {
+        n.try_into()
+    }
+
+    fn serialize_i128(self, n: i128) -> Result<Val, ConversionError> {
+        n.try_into()
+    }
+
+    fn serialize_u8(self, n: u8) -> Result<Val, ConversionError> {
+        Ok(n.into())
+    }
+
+    fn serialize_u16(self, n: u16) -> Result<Val, ConversionError> {
+        Ok(n.into())
+    }
+
+    fn serialize_u32(self, n: u32) -> Result<Val, ConversionError> {
+        n.try_into()
+    }
+
+    fn serialize_u64(self, n: u64) -> Result<Val, ConversionError> {
+        n.try_into()
+    }
+
+    fn serialize_u128(self, n: u128) -> Result<Val, ConversionError> {
+        n.try_into()
+    }
+
+    fn serialize_f32(self, n: f32) -> Result<Val, ConversionError> {
+        Ok(n.into())
+    }
+
+    fn serialize_f64(self, n: f64) -> Result<Val, ConversionError> {
+        Ok(n.into())
+    }
+
+    fn serialize_char(self, c: char) -> Result<Val, ConversionError> {
+        Ok(Val::Str(c.to_string().into()))
+    }
+
+    fn serialize_str(self, s: &str) -> Result<Val, ConversionError> {
+        Ok(s.into())
+    }
+
+    fn serialize_bytes(self, bytes: &[u8]) -> Result<Val, ConversionError> {
+        Ok(bytes.to_vec().into())
+    }
+
+    fn serialize_none(self) -> Result<Val, ConversionError> {
+        Ok(Val::Unit)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Val, ConversionError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Val, ConversionError> {
+        Ok(Val::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Val, ConversionError> {
+        Ok(Val::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Val, ConversionError> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Val, ConversionError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Val, ConversionError> {
+        Ok(tagged(variant, to_val(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, ConversionError> {
+        Ok(SerializeList {
+            variant: None,
+            vals: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, ConversionError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, ConversionError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, ConversionError> {
+        Ok(SerializeList {
+            variant: Some(variant),
+            vals: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, ConversionError> {
+        Ok(SerializeMap {
+            variant: None,
+            entries: BTreeMap::new(),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap, ConversionError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeMap, ConversionError> {
+        Ok(SerializeMap {
+            variant: Some(variant),
+            entries: BTreeMap::new(),
+            next_key: None,
+        })
+    }
+}
+
+/// Wraps the contents of an enum variant in a map keyed by its name.
+fn tagged(variant: &str, val: Val) -> Val {
+    Val::Map(Rc::new(BTreeMap::from([(variant.to_string(), val)])))
+}
+
+struct SerializeList {
+    variant: Option<&'static str>,
+    vals: Vec<Val>,
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConversionError> {
+        self.vals.push(to_val(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Val, ConversionError> {
+        let list = Val::List(self.vals.into());
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, list),
+            None => list,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Val;
+    type Error = ConversionError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConversionError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Val, ConversionError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Val;
+    type Error = ConversionError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConversionError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Val, ConversionError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Val;
+    type Error = ConversionError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConversionError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Val, ConversionError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Val;
+    type Error = ConversionError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConversionError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Val, ConversionError> {
+        self.finish()
+    }
+}
+
+struct SerializeMap {
+    variant: Option<&'static str>,
+    entries: BTreeMap<String, Val>,
+    next_key: Option<String>,
+}
+
+impl SerializeMap {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), ConversionError> {
+        self.entries.insert(key, to_val(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Val, ConversionError> {
+        let map = Val::Map(Rc::new(self.entries));
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, map),
+            None => map,
+        })
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Val;
+    type Error = ConversionError;
+
+    /// Map keys have to be strings; numbers and other scalars are turned
+    /// into their text.
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ConversionError> {
+        let key = match to_val(key)? {
+            Val::Str(s) => s.to_string(),
+            key @ (Val::Number(_) | Val::Float(_) | Val::Bool(_)) => key.to_string(),
+            key => {
+                return Err(ConversionError::WrongType {
+                    expected: "String",
+                    found: key.type_name().to_string(),
+                })
+            }
+        };
+        self.next_key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConversionError> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| ConversionError::Custom("map value without key".to_string()))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Val, ConversionError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Val;
+    type Error = ConversionError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ConversionError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Val, ConversionError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = Val;
+    type Error = ConversionError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ConversionError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Val, ConversionError> {
+        self.finish()
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ConversionError> for Val {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Val {
+    type Error = ConversionError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConversionError> {
+        match self {
+            Self::Number(n) => visitor.visit_i32(n),
+            Self::Float(n) => visitor.visit_f64(n),
+            Self::Bool(b) => visitor.visit_bool(b),
+            Self::Str(s) => visitor.visit_str(&s),
+            Self::List(vals) | Self::Tuple(vals) => visit_list(vals, visitor),
+            Self::Map(entries) => visit_map(Rc::unwrap_or_clone(entries), visitor),
+            Self::Struct(val) => visit_map(Rc::unwrap_or_clone(val).fields, visitor),
+            Self::Enum(val) => tagged_variant(&val).deserialize_any(visitor),
+            Self::Object(object) => Err(de::Error::custom(format!(
+                "cannot deserialize host object {}",
+                object.type_name()
+            ))),
+            Self::Unit => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConversionError> {
+        match self {
+            Self::Unit => visitor.visit_none(),
+            Self::Enum(val) if val.name == "Option" && val.variant == "None" => visitor.visit_none(),
+            Self::Enum(val) if val.name == "Option" && val.variant == "Some" && val.fields.len() == 1 => {
+                visitor.visit_some(val.fields[0].clone())
+            }
+            val => visitor.visit_some(val),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ConversionError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConversionError> {
+        let val = match self {
+            Self::Enum(val) => tagged_variant(&val),
+            val => val,
+        };
+        let (variant, value) = match val {
+            Self::Str(variant) => (variant.to_string(), None),
+            Self::Map(entries) if entries.len() == 1 => {
+                let (variant, value) = Rc::unwrap_or_clone(entries).into_iter().next().unwrap();
+                (variant, Some(value))
+            }
+            other => {
+                return Err(ConversionError::WrongType {
+                    expected: "enum variant",
+                    found: other.type_name().to_string(),
+                })
+            }
+        };
+
+        visitor.visit_enum(EnumDeserializer { variant, value })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// The externally tagged form of a variant of a script enum.
+fn tagged_variant(val: &EnumVal) -> Val {
+    match val.fields.as_slice() {
+        [] => Val::Str(val.variant.as_str().into()),
+        [value] => tagged(&val.variant, value.clone()),
+        fields => tagged(&val.variant, Val::List(fields.into())),
+    }
+}
+
+fn visit_list<'de, V: Visitor<'de>>(vals: Rc<[Val]>, visitor: V) -> Result<V::Value, ConversionError> {
+    let mut seq = SeqDeserializer::new(vals.iter().cloned());
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+fn visit_map<'de, V: Visitor<'de>>(
+    entries: impl IntoIterator<Item = (String, Val)>,
+    visitor: V,
+) -> Result<V::Value, ConversionError> {
+    let mut map = MapDeserializer::new(entries.into_iter());
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Option<Val>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = ConversionError;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantDeserializer), ConversionError> {
+        let variant: StringDeserializer<ConversionError> = self.variant.into_deserializer();
+        let variant = seed.deserialize(variant)?;
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer {
+    value: Option<Val>,
+}
+
+impl VariantDeserializer {
+    fn contents(self, expected: &'static str) -> Result<Val, ConversionError> {
+        self.value.ok_or(ConversionError::WrongType {
+            expected,
+            found: "unit variant".to_string(),
+        })
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = ConversionError;
+
+    fn unit_variant(self) -> Result<(), ConversionError> {
+        match self.value {
+            None | Some(Val::Unit) => Ok(()),
+            Some(other) => Err(ConversionError::WrongType {
+                expected: "unit variant",
+                found: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ConversionError> {
+        seed.deserialize(self.contents("newtype variant")?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, ConversionError> {
+        match self.contents("tuple variant")? {
+            Val::List(vals) => visit_list(vals, visitor),
+            other => Err(ConversionError::WrongType {
+                expected: "List",
+                found: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConversionError> {
+        match self.contents("struct variant")? {
+            Val::Map(entries) => visit_map(Rc::unwrap_or_clone(entries), visitor),
+            other => Err(ConversionError::WrongType {
+                expected: "Map",
+                found: other.type_name().to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Action {
+        Allow,
+        Limit(u16),
+        Redirect { to: String },
+        Split(i32, i32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Rule {
+        name: String,
+        weight: f64,
+        enabled: bool,
+        tags: Vec<String>,
+        parent: Option<u32>,
+        actions: Vec<Action>,
+    }
+
+    fn rule() -> Rule {
+        Rule {
+            name: "burst".to_string(),
+            weight: 0.5,
+            enabled: true,
+            tags: vec!["api".to_string()],
+            parent: None,
+            actions: vec![
+                Action::Allow,
+                Action::Limit(10),
+                Action::Redirect {
+                    to: "/slow".to_string(),
+                },
+                Action::Split(1, 2),
+            ],
+        }
+    }
+
+    #[test]
+    fn convert_struct_to_val_and_back() {
+        let val = to_val(&rule()).unwrap();
+        assert_eq!(
+            val.to_string(),
+            r#"{"actions": ["Allow", {"Limit": 10}, {"Redirect": {"to": "/slow"}}, {"Split": [1, 2]}], "enabled": true, "name": "burst", "parent": Unit, "tags": ["api"], "weight": 0.5}"#
+        );
+        assert_eq!(from_val::<Rule>(val).unwrap(), rule());
+    }
+
+    #[test]
+    fn report_mismatched_val() {
+        let err = from_val::<Rule>(Val::Number(1)).unwrap_err();
+        assert_eq!(err.to_string(), "invalid type: integer `1`, expected struct Rule");
+    }
+
+    #[test]
+    fn refuse_integers_out_of_range() {
+        assert_eq!(
+            to_val(&u64::MAX),
+            Err(ConversionError::OutOfRange {
+                value: u64::MAX.to_string(),
+                target: "Number",
+            })
+        );
+    }
+
+    #[test]
+    fn convert_script_struct_like_map() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let val = Val::Struct(Rc::new(crate::StructVal {
+            name: "Point".to_string(),
+            fields: vec![("x".to_string(), Val::Number(1)), ("y".to_string(), Val::Number(2))],
+        }));
+        assert_eq!(serde_json::to_string(&val).unwrap(), r#"{"x":1,"y":2}"#);
+        assert_eq!(from_val::<Point>(val).unwrap(), Point { x: 1, y: 2 });
+    }
+
+    #[test]
+    fn convert_script_tuple_like_sequence() {
+        let val = Val::Tuple(vec![Val::Number(1), Val::Str("a".into())].into());
+        assert_eq!(serde_json::to_string(&val).unwrap(), r#"[1,"a"]"#);
+        assert_eq!(from_val::<(i32, String)>(val).unwrap(), (1, "a".to_string()));
+    }
+
+    #[test]
+    fn serialize_and_deserialize_val() {
+        let val = to_val(&rule()).unwrap();
+        let json = serde_json::to_string(&val).unwrap();
+        assert_eq!(serde_json::from_str::<Val>(&json).unwrap(), val);
+    }
+
+    #[test]
+    fn convert_script_enum_like_tagged_variant() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Shape {
+            Circle(i32),
+            Rect(i32, i32),
+            Empty,
+        }
+
+        let variant = |variant: &str, fields| {
+            Val::Enum(Rc::new(EnumVal {
+                name: "Shape".to_string(),
+                variant: variant.to_string(),
+                fields,
+            }))
+        };
+        let circle = variant("Circle", vec![Val::Number(1)]);
+        let rect = variant("Rect", vec![Val::Number(1), Val::Number(2)]);
+        assert_eq!(serde_json::to_string(&circle).unwrap(), r#"{"Circle":1}"#);
+        assert_eq!(serde_json::to_string(&rect).unwrap(), r#"{"Rect":[1,2]}"#);
+        assert_eq!(from_val::<Shape>(circle).unwrap(), Shape::Circle(1));
+        assert_eq!(from_val::<Shape>(rect).unwrap(), Shape::Rect(1, 2));
+        assert_eq!(from_val::<Shape>(variant("Empty", vec![])).unwrap(), Shape::Empty);
+    }
+
+    #[test]
+    fn read_options_from_prelude_variants() {
+        assert_eq!(from_val::<Option<i32>>(Val::some(1)).unwrap(), Some(1));
+        assert_eq!(from_val::<Option<i32>>(Val::none()).unwrap(), None);
+        assert_eq!(from_val::<Option<i32>>(Val::Unit).unwrap(), None);
+    }
+}
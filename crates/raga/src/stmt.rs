@@ -1,25 +1,82 @@
 use crate::binding_def::BindingDef;
+use crate::enum_def::EnumDef;
 use crate::func_def::FuncDef;
+use crate::struct_def::StructDef;
 use crate::env::Env;
+use crate::error::EvalError;
 use crate::expr::Expr;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{NodeKind, SyntaxNode};
 use crate::val::Val;
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Stmt {
     BindingDef(BindingDef),
     FuncDef(FuncDef),
+    StructDef(StructDef),
+    EnumDef(EnumDef),
     Expr(Expr),
 }
 
 impl Stmt {
     pub fn new(s: &str) -> Result<(&str, Self), String> {
-        BindingDef::new(s)
-            .map(|(s, binding_def)| (s, Self::BindingDef(binding_def)))
-            .or_else(|_| FuncDef::new(s).map(|(s, func_def)| (s, Self::FuncDef(func_def))))
-            .or_else(|_| Expr::new(s).map(|(s, expr)| (s, Self::Expr(expr))))
+        parser::parse_with(s, Self::parse, Self::lower)
     }
 
-    pub fn eval(&self, env: &mut Env) -> Result<Val, String> {
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        match p.peek() {
+            Some(TokenKind::LetKw) => BindingDef::parse(p),
+            Some(TokenKind::FnKw) => FuncDef::parse(p),
+            Some(TokenKind::StructKw) => StructDef::parse(p),
+            Some(TokenKind::EnumKw) => EnumDef::parse(p),
+            _ => Expr::parse(p),
+        }
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        match node.kind() {
+            NodeKind::BindingDef => BindingDef::lower(node).map(Self::BindingDef),
+            NodeKind::FuncDef => FuncDef::lower(node).map(Self::FuncDef),
+            NodeKind::StructDef => StructDef::lower(node).map(Self::StructDef),
+            NodeKind::EnumDef => EnumDef::lower(node).map(Self::EnumDef),
+            _ => Expr::lower(node).map(Self::Expr),
+        }
+    }
+
+    /// Parses statements up to the end of input, or up to the closing `}`
+    /// when `in_block` is set. A statement that fails to parse is reported
+    /// to the parser and kept in an error node so the ones after it are
+    /// still parsed.
+    pub(crate) fn parse_sequence(p: &mut Parser, in_block: bool) {
+        loop {
+            match p.peek() {
+                None => break,
+                Some(TokenKind::RBrace) if in_block => break,
+                _ => {}
+            }
+
+            let start = p.start_stmt();
+            if let Err(error) = Self::parse(p) {
+                p.report(error);
+                p.abandon_stmt(start, in_block);
+            }
+        }
+    }
+
+    /// Lowers the statements directly under `node`, leaving out those that
+    /// ended up in error nodes.
+    pub(crate) fn lower_sequence(node: &SyntaxNode) -> Vec<Self> {
+        node.children()
+            .filter(|child| child.kind() != NodeKind::Error)
+            .filter_map(|child| Self::lower(&child))
+            .collect()
+    }
+
+    pub fn eval(&self, env: &mut Env) -> Result<Val, EvalError> {
+        env.tick()?;
+
         match self {
             Self::BindingDef(binding_def) => {
                 binding_def.eval(env)?;
@@ -29,17 +86,44 @@ impl Stmt {
                 func_def.eval(env)?;
                 Ok(Val::Unit)
             },
+            Self::StructDef(struct_def) => {
+                struct_def.eval(env)?;
+                Ok(Val::Unit)
+            }
+            Self::EnumDef(enum_def) => {
+                enum_def.eval(env)?;
+                Ok(Val::Unit)
+            }
             Self::Expr(expr) => expr.eval(env),
         }
     }
 }
 
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BindingDef(binding_def) => write!(f, "{}", binding_def),
+            Self::FuncDef(func_def) => write!(f, "{}", func_def),
+            Self::StructDef(struct_def) => write!(f, "{}", struct_def),
+            Self::EnumDef(enum_def) => write!(f, "{}", enum_def),
+            Self::Expr(expr) => write!(f, "{}", expr),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::expr::binding_usage::BindingUsage;
     use super::*;
-    use crate::expr::Number;
+    use crate::expr::{Bool, Number};
     use crate::expr::Op;
+    use crate::enum_def::VariantDef;
+    use crate::func_def::Signature;
+    use crate::pattern::{PatBinding, Pattern, RestPattern};
+    use crate::struct_def::FieldDef;
+    use crate::test_gen::Rng;
+    use crate::type_ann::TypeAnn;
+    use std::rc::Rc;
 
     #[test]
     fn parse_binding_def() {
@@ -49,7 +133,9 @@ mod tests {
                 "",
                 Stmt::BindingDef(BindingDef {
                     name: "a".to_string(),
+                    ty: None,
                     val: Expr::Number(Number(10)),
+                    slot: None,
                 })
             ))
         )
@@ -64,14 +150,30 @@ mod tests {
                 Stmt::FuncDef(FuncDef {
                     name: "me".to_string(),
                     params: vec!["id".to_string()],
-                    body: Box::new(Stmt::Expr(Expr::BindingUsage(BindingUsage {
-                        name: "id".to_string()
+                    signature: None,
+                    body: Rc::new(Stmt::Expr(Expr::BindingUsage(BindingUsage {
+                        name: "id".to_string(),
+                        loc: None,
                     }))),
                 })
             ))
         )
     }
 
+    #[test]
+    fn parse_expr_starting_with_keyword_prefix() {
+        assert_eq!(
+            Stmt::new("letter"),
+            Ok((
+                "",
+                Stmt::Expr(Expr::BindingUsage(BindingUsage {
+                    name: "letter".to_string(),
+                    loc: None,
+                }))
+            ))
+        )
+    }
+
     #[test]
     fn parse_expr() {
         assert_eq!(
@@ -92,7 +194,9 @@ mod tests {
         assert_eq!(
             Stmt::BindingDef(BindingDef {
                 name: "some_def".to_string(),
+                ty: None,
                 val: Expr::Number(Number(10)),
+                slot: None,
             })
                 .eval(&mut Env::default()),
             Ok(Val::Unit)
@@ -106,4 +210,287 @@ mod tests {
             Ok(Val::Number(5))
         )
     }
+
+    #[test]
+    fn display_func_def_with_block_body() {
+        let stmt = Stmt::FuncDef(FuncDef {
+            name: "add".to_string(),
+            params: vec!["x".to_string(), "y".to_string()],
+            signature: None,
+            body: Rc::new(Stmt::Expr(Expr::Block(crate::expr::Block {
+                stmts: vec![
+                    Stmt::BindingDef(BindingDef {
+                        name: "z".to_string(),
+                        ty: None,
+                        val: Expr::Operation {
+                            lhs: Box::new(Expr::BindingUsage(BindingUsage {
+                                name: "x".to_string(),
+                                loc: None,
+                            })),
+                            rhs: Box::new(Expr::BindingUsage(BindingUsage {
+                                name: "y".to_string(),
+                                loc: None,
+                            })),
+                            op: Op::Add,
+                        },
+                        slot: None,
+                    }),
+                    Stmt::Expr(Expr::BindingUsage(BindingUsage {
+                        name: "z".to_string(),
+                        loc: None,
+                    })),
+                ],
+            }))),
+        });
+
+        assert_eq!(stmt.to_string(), "fn add x y => {\n    let z = x + y\n    z\n}");
+    }
+
+    /// Generator of syntax trees, to check the round-trip property over
+    /// many of them.
+    struct Gen(Rng);
+
+    impl Gen {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0.below(n)
+        }
+
+        fn name(&mut self) -> String {
+            let names = ["a", "b", "x", "y2", "foo", "letter", "fnord", "trueish"];
+            names[self.below(names.len() as u64) as usize].to_string()
+        }
+
+        /// Distinct names, as the fields of a struct have to be.
+        fn distinct_names(&mut self) -> Vec<String> {
+            let mut names = Vec::new();
+            for _ in 0..self.below(4) {
+                let name = self.name();
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            names
+        }
+
+        fn struct_name(&mut self) -> String {
+            ["Point", "Pair"][self.below(2) as usize].to_string()
+        }
+
+        fn type_ann(&mut self) -> Option<TypeAnn> {
+            let names = ["Int", "Unit", "Request"];
+            match self.below(6) as usize {
+                idx if idx < names.len() => Some(TypeAnn {
+                    name: names[idx].to_string(),
+                }),
+                _ => None,
+            }
+        }
+
+        fn enum_name(&mut self) -> String {
+            ["Shape", "Light"][self.below(2) as usize].to_string()
+        }
+
+        fn variant_name(&mut self) -> String {
+            ["Circle", "Empty", "Red"][self.below(3) as usize].to_string()
+        }
+
+        /// A pattern binding none of the names in `bound`, which it adds
+        /// its own to.
+        fn pattern(&mut self, depth: u32, bound: &mut Vec<String>) -> Pattern {
+            match self.below(if depth == 0 { 3 } else { 6 }) {
+                0 => Pattern::Wildcard,
+                1 => {
+                    let name = self.name();
+                    if bound.contains(&name) {
+                        return Pattern::Wildcard;
+                    }
+                    bound.push(name.clone());
+                    Pattern::Binding(PatBinding { name, slot: None })
+                }
+                2 => Pattern::Number(self.below(200) as i32 - 100),
+                3 => Pattern::Variant {
+                    enum_name: self.enum_name(),
+                    variant: self.variant_name(),
+                    fields: (0..self.below(3)).map(|_| self.pattern(depth - 1, bound)).collect(),
+                },
+                4 => Pattern::Tuple((0..self.below(3)).map(|_| self.pattern(depth - 1, bound)).collect()),
+                _ => {
+                    let prefix = (0..self.below(3)).map(|_| self.pattern(depth - 1, bound)).collect();
+                    let rest = match self.below(3) {
+                        0 => None,
+                        1 => Some(RestPattern { binding: None }),
+                        _ => {
+                            let name = self.name();
+                            let binding = (!bound.contains(&name)).then_some(PatBinding { name, slot: None });
+                            bound.extend(binding.iter().map(|binding| binding.name.clone()));
+                            Some(RestPattern { binding })
+                        }
+                    };
+                    let suffix = match rest {
+                        Some(_) => (0..self.below(3)).map(|_| self.pattern(depth - 1, bound)).collect(),
+                        None => Vec::new(),
+                    };
+                    Pattern::List { prefix, rest, suffix }
+                }
+            }
+        }
+
+        fn expr(&mut self, depth: u32) -> Expr {
+            match self.below(if depth == 0 { 2 } else { 13 }) {
+                0 if self.below(4) == 0 => Expr::Bool(Bool(self.below(2) == 0)),
+                0 => Expr::Number(Number(self.below(2000) as i32 - 1000)),
+                1 => Expr::BindingUsage(BindingUsage { name: self.name(), loc: None }),
+                2 | 3 => {
+                    let ops = [
+                        Op::Add,
+                        Op::Sub,
+                        Op::Mul,
+                        Op::Div,
+                        Op::Eq,
+                        Op::Ne,
+                        Op::Lt,
+                        Op::Le,
+                        Op::Gt,
+                        Op::Ge,
+                    ];
+                    Expr::Operation {
+                        lhs: Box::new(self.expr(depth - 1)),
+                        rhs: Box::new(self.expr(depth - 1)),
+                        op: ops[self.below(ops.len() as u64) as usize].clone(),
+                    }
+                }
+                4 => Expr::Block(crate::expr::Block {
+                    stmts: (0..self.below(4)).map(|_| self.stmt(depth - 1)).collect(),
+                }),
+                5 => Expr::FuncCall(crate::expr::FuncCall {
+                    callee: self.name(),
+                    args: (0..self.below(3)).map(|_| self.expr(depth - 1)).collect(),
+                    loc: None,
+                }),
+                6 => Expr::FieldAccess(crate::expr::FieldAccess {
+                    target: Box::new(self.expr(depth - 1)),
+                    field: self.name(),
+                }),
+                7 => Expr::MethodCall(crate::expr::MethodCall {
+                    target: Box::new(self.expr(depth - 1)),
+                    method: self.name(),
+                    args: (0..self.below(3)).map(|_| self.expr(depth - 1)).collect(),
+                }),
+                8 => Expr::VariantLit(crate::expr::VariantLit {
+                    enum_name: self.enum_name(),
+                    variant: self.variant_name(),
+                    args: (0..self.below(3)).map(|_| self.expr(depth - 1)).collect(),
+                }),
+                9 => Expr::Tuple(crate::expr::Tuple {
+                    items: (0..self.below(3) + 1).map(|_| self.expr(depth - 1)).collect(),
+                }),
+                10 => Expr::Match(crate::expr::Match {
+                    scrutinee: Box::new(self.expr(depth - 1)),
+                    arms: (0..self.below(3))
+                        .map(|_| crate::expr::match_expr::MatchArm {
+                            pattern: self.pattern(depth - 1, &mut Vec::new()),
+                            guard: (self.below(3) == 0).then(|| self.expr(depth - 1)),
+                            body: self.expr(depth - 1),
+                        })
+                        .collect(),
+                }),
+                11 => Expr::Try(crate::expr::Try { inner: Box::new(self.expr(depth - 1)) }),
+                _ => Expr::StructLit(crate::expr::StructLit {
+                    name: self.struct_name(),
+                    fields: self
+                        .distinct_names()
+                        .into_iter()
+                        .map(|name| (name, self.expr(depth - 1)))
+                        .collect(),
+                    base: (self.below(2) == 0).then(|| Box::new(self.expr(depth - 1))),
+                }),
+            }
+        }
+
+        fn stmt(&mut self, depth: u32) -> Stmt {
+            match self.below(if depth == 0 { 2 } else { 6 }) {
+                0 => Stmt::BindingDef(BindingDef {
+                    name: self.name(),
+                    ty: self.type_ann(),
+                    val: self.expr(depth),
+                    slot: None,
+                }),
+                2 => {
+                    let params: Vec<_> = (0..self.below(3)).map(|_| self.name()).collect();
+                    let signature = Signature {
+                        params: params.iter().map(|_| self.type_ann()).collect(),
+                        ret: self.type_ann(),
+                    };
+                    let is_annotated = signature.ret.is_some() || signature.params.iter().any(Option::is_some);
+                    Stmt::FuncDef(FuncDef {
+                        name: self.name(),
+                        params,
+                        signature: is_annotated.then(|| Rc::new(signature)),
+                        body: Rc::new(self.stmt(depth - 1)),
+                    })
+                }
+                3 => Stmt::StructDef(StructDef {
+                    name: self.struct_name(),
+                    fields: self
+                        .distinct_names()
+                        .into_iter()
+                        .map(|name| FieldDef {
+                            name,
+                            ty: self.type_ann(),
+                        })
+                        .collect(),
+                }),
+                4 => {
+                    let mut variants = Vec::new();
+                    for name in ["Circle", "Empty", "Red"] {
+                        if self.below(2) == 0 {
+                            let fields = self
+                                .distinct_names()
+                                .into_iter()
+                                .map(|name| FieldDef {
+                                    name,
+                                    ty: self.type_ann(),
+                                })
+                                .collect();
+                            variants.push(VariantDef {
+                                name: name.to_string(),
+                                fields,
+                            });
+                        }
+                    }
+                    Stmt::EnumDef(EnumDef {
+                        name: self.enum_name(),
+                        variants,
+                    })
+                }
+                _ => Stmt::Expr(self.expr(depth)),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_displayed_stmt_back_into_same_ast() {
+        let mut gen = Gen(Rng::new(0x9e37_79b9_7f4a_7c15));
+
+        for _ in 0..1000 {
+            let stmt = gen.stmt(4);
+            let src = stmt.to_string();
+            assert_eq!(Stmt::new(&src), Ok(("", stmt)), "source:\n{}", src);
+        }
+    }
+
+    #[test]
+    fn parse_displayed_program_back_into_same_ast() {
+        let mut gen = Gen(Rng::new(42));
+
+        for _ in 0..200 {
+            let mut stmts: Vec<_> = (0..gen.below(5)).map(|_| gen.stmt(3)).collect();
+            let src = stmts.iter().map(Stmt::to_string).collect::<Vec<_>>().join("\n");
+            crate::resolver::resolve(&mut stmts);
+
+            let parse = crate::parse(&src);
+            assert!(parse.errors().is_empty(), "source:\n{}", src);
+            assert_eq!(parse.stmts(), &stmts[..], "source:\n{}", src);
+        }
+    }
 }
@@ -0,0 +1,262 @@
+use crate::env::Env;
+use crate::error::EvalError;
+use crate::lexer::TokenKind;
+use crate::parser::{self, ParseError, Parser};
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::type_ann::{Subject, TypeAnn};
+use crate::val::{StructVal, Val};
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+    pub name: String,
+    pub fields: Vec<FieldDef>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDef {
+    pub name: String,
+    pub ty: Option<TypeAnn>,
+}
+
+impl StructDef {
+    pub fn new(s: &str) -> Result<(&str, Self), String> {
+        parser::parse_with(s, Self::parse, Self::lower)
+    }
+
+    pub(crate) fn parse(p: &mut Parser) -> Result<(), ParseError> {
+        p.start_node(NodeKind::StructDef);
+        p.expect(TokenKind::StructKw, "struct")?;
+        p.expect(TokenKind::Ident, "identifier")?;
+        p.expect(TokenKind::LBrace, "{")?;
+
+        FieldDef::parse_list(p)?;
+        p.expect(TokenKind::RBrace, ", or }")?;
+        p.finish_node();
+        Ok(())
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let name = node.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+        Some(Self {
+            name: name.text().to_string(),
+            fields: FieldDef::lower_list(node)?,
+        })
+    }
+
+    pub(crate) fn eval(&self, env: &mut Env) -> Result<(), EvalError> {
+        env.store_struct(self.clone());
+        Ok(())
+    }
+
+    /// Builds a value of the struct from the fields a literal gives, taking
+    /// those it leaves out from `base`, which has to be a value of the same
+    /// struct.
+    pub(crate) fn construct(&self, mut fields: Vec<(String, Val)>, base: Option<Val>) -> Result<Val, EvalError> {
+        let base = match base {
+            None => None,
+            Some(Val::Struct(base)) if base.name == self.name => Some(base),
+            Some(val) => {
+                return Err(format!("cannot update {} from {}", self.name, val.type_name()).into())
+            }
+        };
+
+        if let Some((name, _)) = fields
+            .iter()
+            .find(|(name, _)| !self.fields.iter().any(|field| field.name == *name))
+        {
+            return Err(format!("{} has no field '{}'", self.name, name).into());
+        }
+
+        let mut vals = Vec::with_capacity(self.fields.len());
+        for field in &self.fields {
+            let val = match fields.iter().position(|(name, _)| *name == field.name) {
+                Some(idx) => fields.swap_remove(idx).1,
+                None => base
+                    .as_ref()
+                    .and_then(|base| base.get(&field.name))
+                    .cloned()
+                    .ok_or_else(|| format!("missing field '{}' in {}", field.name, self.name))?,
+            };
+            if let Some(ty) = &field.ty {
+                ty.check(&val, || Subject::Field {
+                    name: self.name.clone(),
+                    field: field.name.clone(),
+                })?;
+            }
+            vals.push((field.name.clone(), val));
+        }
+
+        Ok(Val::Struct(Rc::new(StructVal {
+            name: self.name.clone(),
+            fields: vals,
+        })))
+    }
+}
+
+impl FieldDef {
+    /// Parses comma separated fields, each a name with an optional type
+    /// annotation, up to the first token that cannot start one.
+    pub(crate) fn parse_list(p: &mut Parser) -> Result<(), ParseError> {
+        let mut names = Vec::new();
+        while p.at(TokenKind::Ident) {
+            p.start_node(NodeKind::FieldDef);
+            let name = p.bump();
+            if p.at(TokenKind::Colon) {
+                TypeAnn::parse(p, TokenKind::Colon, ":")?;
+            }
+            p.finish_node();
+
+            if names.contains(&name.text) {
+                p.report(ParseError {
+                    message: format!("field '{}' is declared twice", name.text),
+                    span: name.span,
+                });
+            }
+            names.push(name.text);
+
+            if !p.at(TokenKind::Comma) {
+                break;
+            }
+            p.bump();
+        }
+        Ok(())
+    }
+
+    /// Lowers the fields directly under `node`.
+    pub(crate) fn lower_list(node: &SyntaxNode) -> Option<Vec<Self>> {
+        node.children()
+            .filter(|child| child.kind() == NodeKind::FieldDef)
+            .map(|field| {
+                let name = field.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+                let ty = field.children().next().and_then(|ty| TypeAnn::lower(&ty));
+                Some(Self {
+                    name: name.text().to_string(),
+                    ty,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Prints `x: Int`, or just the name of a field without an annotation.
+impl fmt::Display for FieldDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(ty) = &self.ty {
+            write!(f, ": {}", ty)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for StructDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.fields.is_empty() {
+            return write!(f, "struct {} {{}}", self.name);
+        }
+
+        write!(f, "struct {} {{ ", self.name)?;
+        for (idx, field) in self.fields.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", field)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point() -> StructDef {
+        StructDef::new("struct Point { x: Int, y }").unwrap().1
+    }
+
+    fn fields(fields: &[(&str, Val)]) -> Vec<(String, Val)> {
+        fields.iter().map(|(name, val)| (name.to_string(), val.clone())).collect()
+    }
+
+    #[test]
+    fn parse_struct_def() {
+        assert_eq!(
+            point(),
+            StructDef {
+                name: "Point".to_string(),
+                fields: vec![
+                    FieldDef {
+                        name: "x".to_string(),
+                        ty: Some(TypeAnn {
+                            name: "Int".to_string(),
+                        }),
+                    },
+                    FieldDef {
+                        name: "y".to_string(),
+                        ty: None,
+                    },
+                ],
+            }
+        );
+        assert_eq!(point().to_string(), "struct Point { x: Int, y }");
+    }
+
+    #[test]
+    fn parse_struct_def_over_several_lines() {
+        let (rest, def) = StructDef::new("struct Empty {}").unwrap();
+        assert_eq!((rest, def.fields.len()), ("", 0));
+
+        let (rest, def) = StructDef::new("struct Pair {\n    a,\n    b,\n}").unwrap();
+        assert_eq!((rest, def.to_string()), ("", "struct Pair { a, b }".to_string()));
+    }
+
+    #[test]
+    fn refuse_field_declared_twice() {
+        assert_eq!(
+            StructDef::new("struct Point { x, y, x }"),
+            Err("field 'x' is declared twice".to_string())
+        );
+    }
+
+    #[test]
+    fn construct_fields_in_declared_order() {
+        let val = point()
+            .construct(fields(&[("y", Val::Unit), ("x", Val::Number(1))]), None)
+            .unwrap();
+        assert_eq!(val.to_string(), "Point { x: 1, y: Unit }");
+    }
+
+    #[test]
+    fn take_left_out_fields_from_base() {
+        let base = point()
+            .construct(fields(&[("x", Val::Number(1)), ("y", Val::Number(2))]), None)
+            .unwrap();
+        assert_eq!(
+            point().construct(fields(&[("x", Val::Number(5))]), Some(base)).unwrap().to_string(),
+            "Point { x: 5, y: 2 }"
+        );
+    }
+
+    #[test]
+    fn refuse_wrong_fields() {
+        let point = point();
+        assert_eq!(
+            point.construct(fields(&[("x", Val::Number(1))]), None),
+            Err("missing field 'y' in Point".into())
+        );
+        assert_eq!(
+            point.construct(fields(&[("x", Val::Number(1)), ("z", Val::Unit)]), None),
+            Err("Point has no field 'z'".into())
+        );
+        assert_eq!(
+            point.construct(fields(&[("x", Val::Unit), ("y", Val::Unit)]), None),
+            Err("expected Int for field 'x' of 'Point', found Unit".into())
+        );
+        assert_eq!(
+            point.construct(fields(&[]), Some(Val::Number(1))),
+            Err("cannot update Point from Number".into())
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! Lossless concrete syntax tree.
+//!
+//! The parser builds an immutable *green* tree that stores every token of
+//! the input, trivia included, with only relative sizes. [`SyntaxNode`] is
+//! the *red* view on top of it: it is created on demand while walking the
+//! tree and knows its parent and absolute position. Printing a root node
+//! always gives back the exact text it was parsed from.
+
+use crate::lexer::{Span, TokenKind};
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Root,
+    BindingDef,
+    FuncDef,
+    ParamList,
+    TypeAnn,
+    StructDef,
+    FieldDef,
+    EnumDef,
+    VariantDef,
+    Block,
+    BinaryExpr,
+    ParenExpr,
+    CallExpr,
+    ArgList,
+    FieldExpr,
+    MethodCallExpr,
+    TryExpr,
+    StructLit,
+    FieldInit,
+    StructBase,
+    VariantLit,
+    TupleExpr,
+    MatchExpr,
+    MatchArmList,
+    MatchArm,
+    MatchGuard,
+    WildcardPat,
+    BindPat,
+    LiteralPat,
+    VariantPat,
+    TuplePat,
+    ListPat,
+    RestPat,
+    Literal,
+    NameRef,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: TokenKind,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    fn text_len(&self) -> usize {
+        match self {
+            Self::Node(node) => node.text_len,
+            Self::Token(token) => token.text.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: NodeKind,
+    text_len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    fn new(kind: NodeKind, children: Vec<GreenElement>) -> Self {
+        Self {
+            kind,
+            text_len: children.iter().map(GreenElement::text_len).sum(),
+            children,
+        }
+    }
+}
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => write!(f, "{}", node)?,
+                GreenElement::Token(token) => write!(f, "{}", token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Position among the children of the innermost open node, used to wrap
+/// already built children in a node started later.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Checkpoint(usize);
+
+#[derive(Debug, Default)]
+pub(crate) struct GreenNodeBuilder {
+    parents: Vec<(NodeKind, usize)>,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNodeBuilder {
+    pub(crate) fn start_node(&mut self, kind: NodeKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    pub(crate) fn start_node_at(&mut self, checkpoint: Checkpoint, kind: NodeKind) {
+        self.parents.push((kind, checkpoint.0));
+    }
+
+    pub(crate) fn token(&mut self, kind: TokenKind, text: &str) {
+        self.children.push(GreenElement::Token(Rc::new(GreenToken {
+            kind,
+            text: text.to_string(),
+        })));
+    }
+
+    pub(crate) fn finish_node(&mut self) {
+        let (kind, first_child) = self.parents.pop().expect("no node to finish");
+        let children = self.children.split_off(first_child);
+        self.children
+            .push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    /// Number of nodes that have been started but not finished yet.
+    pub(crate) fn depth(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub(crate) fn finish(mut self) -> Rc<GreenNode> {
+        assert!(self.parents.is_empty(), "unfinished nodes left in builder");
+        match self.children.pop() {
+            Some(GreenElement::Node(node)) if self.children.is_empty() => node,
+            _ => panic!("builder must produce exactly one root node"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(PartialEq, Eq)]
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    offset: usize,
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.0.green.kind
+    }
+
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.0.offset,
+            end: self.0.offset + self.0.green.text_len,
+        }
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    pub fn children_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.0.offset;
+
+        self.0.green.children.iter().map(move |child| {
+            let child_offset = offset;
+            offset += child.text_len();
+
+            match child {
+                GreenElement::Node(green) => SyntaxElement::Node(Self(Rc::new(NodeData {
+                    green: Rc::clone(green),
+                    parent: Some(self.clone()),
+                    offset: child_offset,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    parent: self.clone(),
+                    green: Rc::clone(green),
+                    offset: child_offset,
+                }),
+            }
+        })
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children_with_tokens().filter_map(SyntaxElement::into_node)
+    }
+
+    /// Tokens that are direct children of this node.
+    pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken> + '_ {
+        self.children_with_tokens().filter_map(SyntaxElement::into_token)
+    }
+
+    /// Every token under this node in source order, trivia included.
+    pub fn descendant_tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.descendant_tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        let span = self.span();
+        writeln!(f, "{:indent$}{:?}@{}..{}", "", self.kind(), span.start, span.end)?;
+
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => node.fmt_tree(f, indent + 2)?,
+                SyntaxElement::Token(token) => writeln!(f, "{:indent$}{:?}", "", token, indent = indent + 2)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+/// Prints the tree with one node or token per line, indented by depth.
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    parent: SyntaxNode,
+    green: Rc<GreenToken>,
+    offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> TokenKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.offset,
+            end: self.offset + self.green.text.len(),
+        }
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(f, "{:?}@{}..{} {:?}", self.kind(), span.start, span.end, self.text())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn into_node(self) -> Option<SyntaxNode> {
+        match self {
+            Self::Node(node) => Some(node),
+            Self::Token(_) => None,
+        }
+    }
+
+    pub fn into_token(self) -> Option<SyntaxToken> {
+        match self {
+            Self::Node(_) => None,
+            Self::Token(token) => Some(token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_one_plus_two() -> SyntaxNode {
+        let mut builder = GreenNodeBuilder::default();
+        builder.start_node(NodeKind::Root);
+
+        let checkpoint = builder.checkpoint();
+        builder.start_node(NodeKind::Literal);
+        builder.token(TokenKind::Number, "1");
+        builder.finish_node();
+
+        builder.start_node_at(checkpoint, NodeKind::BinaryExpr);
+        builder.token(TokenKind::Whitespace, " ");
+        builder.token(TokenKind::Plus, "+");
+        builder.token(TokenKind::Whitespace, " ");
+        builder.start_node(NodeKind::Literal);
+        builder.token(TokenKind::Number, "2");
+        builder.finish_node();
+        builder.finish_node();
+
+        builder.finish_node();
+        SyntaxNode::new_root(builder.finish())
+    }
+
+    #[test]
+    fn print_tree_back_to_source() {
+        assert_eq!(build_one_plus_two().to_string(), "1 + 2");
+    }
+
+    #[test]
+    fn wrap_children_at_checkpoint() {
+        assert_eq!(
+            format!("{:?}", build_one_plus_two()),
+            r#"Root@0..5
+  BinaryExpr@0..5
+    Literal@0..1
+      Number@0..1 "1"
+    Whitespace@1..2 " "
+    Plus@2..3 "+"
+    Whitespace@3..4 " "
+    Literal@4..5
+      Number@4..5 "2"
+"#
+        );
+    }
+
+    #[test]
+    fn children_know_their_parent_and_span() {
+        let root = build_one_plus_two();
+        let binary_expr = root.children().next().unwrap();
+        let rhs = binary_expr.children().nth(1).unwrap();
+
+        assert_eq!(rhs.span(), Span { start: 4, end: 5 });
+        assert_eq!(rhs.parent(), Some(&binary_expr));
+        assert_eq!(rhs.parent().and_then(SyntaxNode::parent), Some(&root));
+    }
+
+    #[test]
+    fn collect_descendant_tokens_in_order() {
+        let texts: Vec<_> = build_one_plus_two()
+            .descendant_tokens()
+            .iter()
+            .map(|token| token.text().to_string())
+            .collect();
+        assert_eq!(texts, vec!["1", " ", "+", " ", "2"]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Random inputs for the tests that check two ways of running or printing
+//! code against each other.
+
+use crate::Engine;
+
+/// Xorshift generator, so that properties can be checked over many inputs
+/// without pulling in a dependency.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// A number in `0..n`.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+}
+
+/// What sets the programs of one test apart from those of another.
+pub(crate) struct Profile {
+    /// The number literals to pick from.
+    pub(crate) numbers: &'static [&'static str],
+    /// Whether a `let` now and then defines a name that is already visible.
+    pub(crate) redefine: bool,
+}
+
+/// Generator of programs that define names before using them, with the odd
+/// mistake, such as a missing name or the wrong number of arguments, so
+/// that errors are compared too.
+struct SourceGen {
+    rng: Rng,
+    profile: Profile,
+    next_name: usize,
+    /// Names defined in each enclosing scope, with the arity of the
+    /// functions among them.
+    scopes: Vec<Vec<(String, Option<usize>)>>,
+}
+
+impl SourceGen {
+    fn below(&mut self, n: u64) -> u64 {
+        self.rng.below(n)
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.next_name += 1;
+        format!("{}{}", prefix, self.next_name)
+    }
+
+    fn pick(&mut self, is_func: bool) -> Option<(String, Option<usize>)> {
+        let visible: Vec<_> = self
+            .scopes
+            .iter()
+            .flatten()
+            .filter(|(_, arity)| arity.is_some() == is_func)
+            .cloned()
+            .collect();
+        if visible.is_empty() {
+            return None;
+        }
+        Some(visible[self.below(visible.len() as u64) as usize].clone())
+    }
+
+    fn program(&mut self) -> String {
+        let stmts: Vec<_> = (0..1 + self.below(5)).map(|_| self.stmt(4)).collect();
+        stmts.join("\n")
+    }
+
+    fn expr(&mut self, depth: u32) -> String {
+        match self.below(if depth == 0 { 8 } else { 22 }) {
+            0..=3 => {
+                let numbers = self.profile.numbers;
+                numbers[self.below(numbers.len() as u64) as usize].to_string()
+            }
+            4..=7 => match self.pick(false) {
+                _ if self.below(30) == 0 => "missing".to_string(),
+                Some((name, _)) => name,
+                None => "1".to_string(),
+            },
+            8..=12 => {
+                let op = ["+", "-", "*", "/", "<", "=="][self.below(6) as usize];
+                format!("({} {} {})", self.expr(depth - 1), op, self.expr(depth - 1))
+            }
+            13..=15 => {
+                self.scopes.push(Vec::new());
+                let stmts: Vec<_> = (0..self.below(4)).map(|_| self.stmt(depth - 1)).collect();
+                self.scopes.pop();
+                format!("{{\n{}\n}}", stmts.join("\n"))
+            }
+            16..=18 => {
+                let (callee, arity) = match self.pick(true) {
+                    _ if self.below(30) == 0 => ("missing".to_string(), self.below(2) as usize),
+                    Some((name, arity)) => (name, arity.unwrap_or_default()),
+                    None => return "1".to_string(),
+                };
+                let argc = if self.below(30) == 0 { arity + 1 } else { arity };
+                let args: Vec<_> = (0..argc).map(|_| self.expr(depth - 1)).collect();
+                format!("{}({})", callee, args.join(", "))
+            }
+            19 => format!("{}.field", self.expr(depth - 1)),
+            _ => {
+                let scrutinee = self.expr(depth - 1);
+                let first = self.expr(depth - 1);
+                let name = self.fresh("m");
+                self.scopes.push(vec![(name.clone(), None)]);
+                let guard = match self.below(3) {
+                    0 => format!(" if {} < {}", name, self.expr(depth - 1)),
+                    _ => String::new(),
+                };
+                let second = self.expr(depth - 1);
+                self.scopes.pop();
+                let rest = match self.below(2) {
+                    0 => format!("_ => {},\n", self.expr(depth - 1)),
+                    _ => String::new(),
+                };
+                format!(
+                    "match ({}, {}) {{\n(0, _) => {},\n({}, 1){} => {},\n{}}}",
+                    scrutinee,
+                    self.below(3),
+                    first,
+                    name,
+                    guard,
+                    second,
+                    rest
+                )
+            }
+        }
+    }
+
+    fn stmt(&mut self, depth: u32) -> String {
+        match self.below(if depth == 0 { 2 } else { 3 }) {
+            0 => {
+                let val = self.expr(depth);
+                let name = match self.pick(false) {
+                    Some((name, _)) if self.profile.redefine && self.below(8) == 0 => name,
+                    _ => self.fresh("v"),
+                };
+                self.scopes.last_mut().unwrap().push((name.clone(), None));
+                format!("let {} = {}", name, val)
+            }
+            1 => self.expr(depth),
+            _ => {
+                let name = self.fresh("f");
+                let params: Vec<_> = (0..self.below(3)).map(|_| self.fresh("p")).collect();
+                self.scopes
+                    .push(params.iter().map(|param| (param.clone(), None)).collect());
+                let body = self.stmt(depth - 1);
+                self.scopes.pop();
+
+                self.scopes.last_mut().unwrap().push((name.clone(), Some(params.len())));
+                format!("fn {} {} => {}", name, params.join(" "), body)
+            }
+        }
+    }
+}
+
+/// Runs generated programs on the engines `engines` makes and checks that
+/// each engine gives the result or error the first one does. Every round
+/// starts with new engines and runs five programs one after the other, so
+/// later ones use the globals of earlier ones.
+pub(crate) fn check_agreement(seed: u64, profile: Profile, rounds: usize, engines: impl Fn() -> Vec<Engine>) {
+    let mut gen = SourceGen {
+        rng: Rng::new(seed),
+        profile,
+        next_name: 0,
+        scopes: Vec::new(),
+    };
+
+    for _ in 0..rounds {
+        let mut engines = engines();
+        gen.scopes = vec![Vec::new()];
+
+        for _ in 0..5 {
+            let globals = gen.scopes[0].clone();
+            let src = gen.program();
+
+            let (first, rest) = engines.split_first_mut().expect("there is an engine to compare with");
+            let expected = first.eval(&src).map_err(|err| err.to_string());
+            for engine in rest {
+                assert_eq!(engine.eval(&src).map_err(|err| err.to_string()), expected, "source:\n{}", src);
+            }
+
+            // A failed program defines nothing.
+            if expected.is_err() {
+                gen.scopes[0] = globals;
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Type annotations, like the `Int` of `let x: Int = 5`.
+//!
+//! Annotations are checked as values are bound: the value of a binding when
+//! it is defined, the arguments and result of a function at each call, and
+//! the fields of a struct whenever one is built.
+//! A type is named the way [`Val::type_name`] names it, structs and host
+//! objects included, and `Int` is another name for `Number`.
+
+use crate::error::EvalError;
+use crate::lexer::TokenKind;
+use crate::parser::{ParseError, Parser};
+use crate::syntax::{NodeKind, SyntaxNode};
+use crate::val::Val;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAnn {
+    pub name: String,
+}
+
+impl TypeAnn {
+    /// Parses the type after `lead`, which is `:` for bindings and
+    /// parameters and `->` for results.
+    pub(crate) fn parse(p: &mut Parser, lead: TokenKind, what: &str) -> Result<(), ParseError> {
+        p.start_node(NodeKind::TypeAnn);
+        p.expect(lead, what)?;
+        p.expect(TokenKind::Ident, "type name")?;
+        p.finish_node();
+        Ok(())
+    }
+
+    pub(crate) fn lower(node: &SyntaxNode) -> Option<Self> {
+        let name = node.child_tokens().find(|token| token.kind() == TokenKind::Ident)?;
+        Some(Self {
+            name: name.text().to_string(),
+        })
+    }
+
+    pub fn matches(&self, val: &Val) -> bool {
+        match self.name.as_str() {
+            "Int" => matches!(val, Val::Number(_)),
+            name => name == val.type_name(),
+        }
+    }
+
+    /// Fails unless `val` has the annotated type, naming what `val` is in
+    /// the error.
+    pub(crate) fn check(&self, val: &Val, subject: impl FnOnce() -> Subject) -> Result<(), EvalError> {
+        if self.matches(val) {
+            Ok(())
+        } else {
+            Err(format!("expected {} for {}, found {}", self, subject(), val.type_name()).into())
+        }
+    }
+}
+
+impl fmt::Display for TypeAnn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// What a value checked against an annotation is.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Subject {
+    Binding(String),
+    Param { func: String, param: String },
+    Result(String),
+    Field { name: String, field: String },
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Binding(name) => write!(f, "binding '{}'", name),
+            Self::Param { func, param } => write!(f, "parameter '{}' of '{}'", param, func),
+            Self::Result(func) => write!(f, "result of '{}'", func),
+            Self::Field { name, field } => write!(f, "field '{}' of '{}'", field, name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ann(name: &str) -> TypeAnn {
+        TypeAnn {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn match_values_by_type_name() {
+        assert!(ann("Number").matches(&Val::Number(1)));
+        assert!(ann("Int").matches(&Val::Number(1)));
+        assert!(ann("Unit").matches(&Val::Unit));
+        assert!(ann("String").matches(&Val::from("raga")));
+        assert!(!ann("Int").matches(&Val::Float(1.0)));
+        assert!(!ann("Point").matches(&Val::Unit));
+    }
+
+    #[test]
+    fn name_subject_in_mismatch() {
+        assert_eq!(
+            ann("Int").check(&Val::Unit, || Subject::Param {
+                func: "add".to_string(),
+                param: "a".to_string(),
+            }),
+            Err("expected Int for parameter 'a' of 'add', found Unit".into())
+        );
+    }
+}
@@ -0,0 +1,1210 @@
+//! Static type checking.
+//!
+//! Types are inferred in the style of Hindley-Milner. Functions are not
+//! values in raga, so only bindings have types; functions have type schemes
+//! instead, made of the types of their parameters and result and generalized
+//! over whatever their body leaves open. `fn id x => x` can therefore be
+//! called with a number in one place and a block in another. Enums are
+//! generic in the same way over the fields of their variants that are not
+//! annotated, so `Some(1)` and `Some({})` are options of different types.
+//!
+//! The checker works on the syntax tree so that errors point into the
+//! source. Whatever cannot be known before evaluation, like the fields of a
+//! host object or a global that the program only defines after using it,
+//! gets a fresh type that fits anything, so it is never reported as a
+//! mismatch.
+
+use crate::enum_def::{self, EnumDef};
+use crate::env::{Env, NamedInfo};
+use crate::expr::{Bool, Op};
+use crate::lexer::{Span, TokenKind};
+use crate::pattern::{self, Pattern};
+use crate::struct_def::StructDef;
+use crate::syntax::{NodeKind, SyntaxElement, SyntaxNode};
+use crate::type_ann::TypeAnn;
+use crate::{ParseError, Val};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    /// A type that is not known yet.
+    Var(usize),
+    Number,
+    Float,
+    Bool,
+    String,
+    List,
+    /// A fixed number of values, each of its own type.
+    Tuple(Vec<Type>),
+    Map,
+    Unit,
+    /// A struct, enum or host object, by its type name. An enum has a type
+    /// argument for each field of its variants that is not annotated, as
+    /// `Option` has for the value of `Some`. Without any, the arguments are
+    /// not known, and the type fits the same enum with any.
+    Named(String, Vec<Type>),
+}
+
+impl Type {
+    fn of(val: &Val) -> Self {
+        match val {
+            Val::Number(_) => Self::Number,
+            Val::Float(_) => Self::Float,
+            Val::Bool(_) => Self::Bool,
+            Val::Str(_) => Self::String,
+            Val::List(_) => Self::List,
+            Val::Tuple(vals) => Self::Tuple(vals.iter().map(Self::of).collect()),
+            Val::Map(_) => Self::Map,
+            Val::Struct(val) => Self::Named(val.name.clone(), Vec::new()),
+            Val::Enum(val) => Self::Named(val.name.clone(), Vec::new()),
+            Val::Object(object) => Self::Named(object.type_name().to_string(), Vec::new()),
+            Val::Unit => Self::Unit,
+        }
+    }
+
+    /// The type an annotation names. Names that are not built in are taken
+    /// to be structs or host objects.
+    fn annotated(ty: &TypeAnn) -> Self {
+        match ty.name.as_str() {
+            "Int" | "Number" => Self::Number,
+            "Float" => Self::Float,
+            "Bool" => Self::Bool,
+            "String" => Self::String,
+            "List" => Self::List,
+            "Map" => Self::Map,
+            "Unit" => Self::Unit,
+            name => Self::Named(name.to_string(), Vec::new()),
+        }
+    }
+}
+
+/// Prints types the way [`Val::type_name`] names them.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Var(_) => write!(f, "_"),
+            Self::Number => write!(f, "Number"),
+            Self::Float => write!(f, "Float"),
+            Self::Bool => write!(f, "Bool"),
+            Self::String => write!(f, "String"),
+            Self::List => write!(f, "List"),
+            Self::Tuple(items) => {
+                write!(f, "(")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Self::Map => write!(f, "Map"),
+            Self::Unit => write!(f, "Unit"),
+            Self::Named(name, _) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FuncType {
+    params: Vec<Type>,
+    ret: Type,
+}
+
+/// The type of a function, in which `vars` stand for any type and are
+/// replaced by fresh ones at each call.
+#[derive(Debug, Clone)]
+struct Scheme {
+    vars: Vec<usize>,
+    ty: FuncType,
+}
+
+#[derive(Debug, Clone)]
+enum Symbol {
+    Binding(Type),
+    Func(Scheme),
+    /// The fields of a struct, with the types they are annotated with.
+    Struct(Vec<(String, Option<Type>)>),
+    /// The variants of an enum, with the types their fields are annotated
+    /// with.
+    Enum(Vec<(String, Vec<Option<Type>>)>),
+}
+
+/// Checks the program `root` would run in `env`, returning every mismatch
+/// found. The program must not have syntax errors.
+pub(crate) fn check(root: &SyntaxNode, env: &Env) -> Vec<ParseError> {
+    let mut defined_later = HashSet::new();
+    let mut defined_twice = HashSet::new();
+    for name in root.children().filter_map(|stmt| defined_name(&stmt)) {
+        if !defined_later.insert(name.clone()) {
+            defined_twice.insert(name);
+        }
+    }
+    let mut checker = Checker {
+        env,
+        defined_later,
+        defined_twice,
+        scopes: vec![HashMap::new()],
+        returns: Vec::new(),
+        subst: Vec::new(),
+        errors: Vec::new(),
+    };
+    checker.stmts(root);
+    checker.errors
+}
+
+struct Checker<'a> {
+    env: &'a Env<'a>,
+    /// Globals the program defines that are not defined yet at the point
+    /// being checked. Function bodies look these up when they are called,
+    /// so neither `env` nor the program knows their type beforehand.
+    defined_later: HashSet<String>,
+    /// Globals the program defines more than once, which a function body
+    /// may see with a different type each time it is called.
+    defined_twice: HashSet<String>,
+    scopes: Vec<HashMap<String, Symbol>>,
+    /// The result types of the functions whose bodies are being checked,
+    /// innermost last.
+    returns: Vec<Type>,
+    /// What each type variable has been found to be so far.
+    subst: Vec<Option<Type>>,
+    errors: Vec<ParseError>,
+}
+
+impl Checker<'_> {
+    /// Checks the statements directly under `node`, returning the type of
+    /// the last one.
+    fn stmts(&mut self, node: &SyntaxNode) -> Type {
+        let mut ty = Type::Unit;
+        for stmt in node.children() {
+            ty = self.stmt(&stmt);
+        }
+        ty
+    }
+
+    fn stmt(&mut self, node: &SyntaxNode) -> Type {
+        match node.kind() {
+            NodeKind::BindingDef => {
+                let mut ty = match node.children().find(|child| child.kind() != NodeKind::TypeAnn) {
+                    Some(val) => self.expr(&val),
+                    None => self.fresh(),
+                };
+                if let Some((annotated, val)) = annotation(node).zip(node.children().last()) {
+                    self.unify(&annotated, &ty, val.span());
+                    ty = annotated;
+                }
+                if let Some(name) = ident(node) {
+                    self.define(name, Symbol::Binding(ty));
+                }
+                Type::Unit
+            }
+            NodeKind::FuncDef => {
+                self.func_def(node);
+                Type::Unit
+            }
+            NodeKind::StructDef => {
+                if let Some(def) = StructDef::lower(node) {
+                    self.define(def.name.clone(), Symbol::Struct(field_types(&def)));
+                }
+                Type::Unit
+            }
+            NodeKind::EnumDef => {
+                if let Some(def) = EnumDef::lower(node) {
+                    self.define(def.name.clone(), Symbol::Enum(variant_types(&def)));
+                }
+                Type::Unit
+            }
+            _ => self.expr(node),
+        }
+    }
+
+    fn func_def(&mut self, node: &SyntaxNode) {
+        let mut children = node.children();
+        let param_list = children.next();
+        let body = children.find(|child| child.kind() != NodeKind::TypeAnn);
+        let (Some(name), Some(param_list), Some(body)) = (ident(node), param_list, body) else {
+            return;
+        };
+
+        // Parameters without an annotation start out as anything.
+        let mut params = Vec::new();
+        let mut param_types = Vec::new();
+        for element in param_list.children_with_tokens() {
+            match element {
+                SyntaxElement::Token(token) if token.kind() == TokenKind::Ident => {
+                    params.push(token.text().to_string());
+                    param_types.push(self.fresh());
+                }
+                SyntaxElement::Node(ty) if ty.kind() == NodeKind::TypeAnn => {
+                    if let (Some(last), Some(ty)) = (param_types.last_mut(), TypeAnn::lower(&ty)) {
+                        *last = Type::annotated(&ty);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let ty = FuncType {
+            params: param_types,
+            ret: annotation(node).unwrap_or_else(|| self.fresh()),
+        };
+
+        // Inside its own body the function is not generalized yet, so
+        // recursive calls have to agree with each other.
+        let own = Scheme {
+            vars: Vec::new(),
+            ty: ty.clone(),
+        };
+        self.define(name.clone(), Symbol::Func(own));
+
+        let scope = params
+            .into_iter()
+            .zip(&ty.params)
+            .map(|(param, ty)| (param, Symbol::Binding(ty.clone())))
+            .collect();
+        self.scopes.push(scope);
+        self.returns.push(ty.ret.clone());
+        let ret = self.stmt(&body);
+        self.returns.pop();
+        self.scopes.pop();
+        self.unify(&ty.ret, &ret, body.span());
+
+        self.scopes.last_mut().unwrap().remove(&name);
+        let scheme = self.generalize(ty);
+        self.define(name, Symbol::Func(scheme));
+    }
+
+    fn expr(&mut self, node: &SyntaxNode) -> Type {
+        match node.kind() {
+            NodeKind::Literal if Bool::lower(node).is_some() => Type::Bool,
+            NodeKind::Literal => Type::Number,
+            NodeKind::NameRef => self.binding(node),
+            NodeKind::Block => {
+                self.scopes.push(HashMap::new());
+                let ty = self.stmts(node);
+                self.scopes.pop();
+                ty
+            }
+            NodeKind::ParenExpr => match node.children().next() {
+                Some(inner) => self.expr(&inner),
+                None => self.fresh(),
+            },
+            NodeKind::BinaryExpr => {
+                // Any two values of the same type can be compared for
+                // equality, but only numbers can be ordered or added.
+                let op = node.child_tokens().find_map(|token| Op::lower(&token));
+                let is_equality = matches!(op, Some(Op::Eq | Op::Ne));
+                let mut lhs = None;
+                for operand in node.children() {
+                    let ty = self.expr(&operand);
+                    if !is_equality {
+                        self.unify(&Type::Number, &ty, operand.span());
+                    } else if let Some(lhs) = &lhs {
+                        self.unify(lhs, &ty, operand.span());
+                    } else {
+                        lhs = Some(ty);
+                    }
+                }
+                match op {
+                    Some(op) if op.is_comparison() => Type::Bool,
+                    _ => Type::Number,
+                }
+            }
+            NodeKind::CallExpr => self.call(node),
+            NodeKind::FieldExpr => {
+                let target = self.target(node);
+                let field = ident(node).unwrap_or_default();
+                match target {
+                    Type::Named(name, _) => {
+                        if let Some(fields) = self.struct_fields(&name) {
+                            match fields.into_iter().find(|(name, _)| *name == field) {
+                                Some((_, Some(ty))) => return ty,
+                                Some((_, None)) => {}
+                                None => self.error(node.span(), format!("{} has no field '{}'", name, field)),
+                            }
+                        } else if self.enum_variants(&name).is_some() {
+                            self.error(node.span(), format!("cannot access field '{}' of {}", field, name));
+                        }
+                    }
+                    Type::Var(_) | Type::Map => {}
+                    ty => self.error(node.span(), format!("cannot access field '{}' of {}", field, ty)),
+                }
+                self.fresh()
+            }
+            NodeKind::MethodCallExpr => {
+                let target = self.target(node);
+                for arg in args(node) {
+                    self.expr(&arg);
+                }
+                match target {
+                    Type::Named(name, _) if self.struct_fields(&name).is_none() && self.enum_variants(&name).is_none() => {}
+                    Type::Var(_) => {}
+                    ty => self.error(
+                        node.span(),
+                        format!("cannot call method '{}' on {}", ident(node).unwrap_or_default(), ty),
+                    ),
+                }
+                self.fresh()
+            }
+            NodeKind::StructLit => self.struct_lit(node),
+            NodeKind::VariantLit => self.variant_lit(node),
+            NodeKind::TupleExpr => Type::Tuple(node.children().map(|item| self.expr(&item)).collect()),
+            NodeKind::MatchExpr => self.match_expr(node),
+            NodeKind::TryExpr => self.try_expr(node),
+            _ => self.fresh(),
+        }
+    }
+
+    /// Checks the values a variant is given against the enum, if it is
+    /// known.
+    fn variant_lit(&mut self, node: &SyntaxNode) -> Type {
+        let args: Vec<_> = args(node).collect();
+        let arg_types: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+        let Some((name, variant)) = enum_def::lower_path(node) else {
+            return self.fresh();
+        };
+
+        let Some(variants) = self.enum_variants(&name) else {
+            return Type::Named(name, Vec::new());
+        };
+        let (ty, variants) = self.instantiate_enum(&name, variants);
+        match variants.into_iter().find(|(known, _)| *known == variant) {
+            None => self.error(node.span(), format!("{} has no variant '{}'", name, variant)),
+            Some((_, fields)) if fields.len() != args.len() => self.error(
+                node.span(),
+                format!(
+                    "variant '{}::{}' takes {} value(s) but {} were given",
+                    name,
+                    variant,
+                    fields.len(),
+                    args.len()
+                ),
+            ),
+            Some((_, fields)) => {
+                for ((field, arg_type), arg) in fields.iter().zip(&arg_types).zip(&args) {
+                    self.unify(field, arg_type, arg.span());
+                }
+            }
+        }
+        ty
+    }
+
+    /// The type of a value of the enum `name`, with fresh type arguments,
+    /// and the types of the fields of each of its `variants` in terms of
+    /// them.
+    fn instantiate_enum(
+        &mut self,
+        name: &str,
+        variants: Vec<(String, Vec<Option<Type>>)>,
+    ) -> (Type, Vec<(String, Vec<Type>)>) {
+        let mut args = Vec::new();
+        let variants = variants
+            .into_iter()
+            .map(|(variant, fields)| {
+                let fields = fields
+                    .into_iter()
+                    .map(|field| {
+                        field.unwrap_or_else(|| {
+                            let arg = self.fresh();
+                            args.push(arg.clone());
+                            arg
+                        })
+                    })
+                    .collect();
+                (variant, fields)
+            })
+            .collect();
+        (Type::Named(name.to_string(), args), variants)
+    }
+
+    /// Checks that `?` is applied to an `Option` or `Result`, which the
+    /// function it is in may then return, keeping the error of a `Result`.
+    /// It gives the value of `Some` or `Ok`.
+    fn try_expr(&mut self, node: &SyntaxNode) -> Type {
+        let Some(inner) = node.children().next() else {
+            return self.fresh();
+        };
+        let ty = self.expr(&inner);
+        match self.resolve(&ty) {
+            Type::Named(name, args) if name == "Option" || name == "Result" => {
+                let mut returned = args.clone();
+                if let Some(value) = returned.first_mut() {
+                    *value = self.fresh();
+                }
+                if let Some(ret) = self.returns.last().cloned() {
+                    self.unify(&ret, &Type::Named(name, returned), node.span());
+                }
+                if let Some(value) = args.first() {
+                    return value.clone();
+                }
+            }
+            Type::Var(_) => {}
+            ty => self.error(node.span(), format!("cannot use ? on {}", ty)),
+        }
+        self.fresh()
+    }
+
+    /// Checks each arm against the type of the scrutinee, giving every body
+    /// the same type, and reports values that no arm matches. Arms with a
+    /// guard may not match the values their pattern does, so they cover
+    /// nothing. Coverage is only checked if the arms are otherwise well
+    /// typed, as patterns that do not fit the scrutinee make it meaningless.
+    fn match_expr(&mut self, node: &SyntaxNode) -> Type {
+        let mut children = node.children();
+        let scrutinee = match children.next() {
+            Some(scrutinee) => self.expr(&scrutinee),
+            None => self.fresh(),
+        };
+        let ty = self.fresh();
+        let errors_before = self.errors.len();
+
+        let mut covering = Vec::new();
+        for arm in children.next().iter().flat_map(SyntaxNode::children) {
+            let mut parts = arm.children();
+            let Some(pattern) = parts.next() else {
+                continue;
+            };
+
+            self.scopes.push(HashMap::new());
+            self.pattern(&pattern, &scrutinee);
+            let mut is_guarded = false;
+            for part in parts {
+                if part.kind() == NodeKind::MatchGuard {
+                    if let Some(guard) = part.children().next() {
+                        let guard_type = self.expr(&guard);
+                        self.unify(&Type::Bool, &guard_type, guard.span());
+                    }
+                    is_guarded = true;
+                } else {
+                    let body = self.expr(&part);
+                    self.unify(&ty, &body, part.span());
+                }
+            }
+            self.scopes.pop();
+
+            if !is_guarded {
+                covering.extend(Pattern::lower(&pattern));
+            }
+        }
+
+        if self.errors.len() == errors_before {
+            let covering: Vec<_> = covering.iter().collect();
+            let variants = |name: &str| {
+                let variants = self.enum_variants(name)?;
+                Some(variants.into_iter().map(|(name, fields)| (name, fields.len())).collect())
+            };
+            if let Some(missing) = pattern::uncovered(&covering, &variants) {
+                self.error(node.span(), format!("match does not cover {}", missing));
+            }
+        }
+        ty
+    }
+
+    /// Checks that a pattern fits values of type `ty`, defining the names
+    /// it binds in the innermost scope.
+    fn pattern(&mut self, node: &SyntaxNode, ty: &Type) {
+        match node.kind() {
+            NodeKind::BindPat => {
+                if let Some(name) = ident(node) {
+                    self.define(name, Symbol::Binding(ty.clone()));
+                }
+            }
+            NodeKind::LiteralPat => self.unify(ty, &Type::Number, node.span()),
+            NodeKind::VariantPat => {
+                let Some((name, variant)) = enum_def::lower_path(node) else {
+                    return;
+                };
+                let (enum_type, variants) = match self.enum_variants(&name) {
+                    Some(variants) => {
+                        let (enum_type, variants) = self.instantiate_enum(&name, variants);
+                        (enum_type, Some(variants))
+                    }
+                    None => (Type::Named(name.clone(), Vec::new()), None),
+                };
+                self.unify(ty, &enum_type, node.span());
+
+                let items: Vec<_> = node.children().collect();
+                let mut fields = Vec::new();
+                if let Some(variants) = variants {
+                    match variants.into_iter().find(|(known, _)| *known == variant) {
+                        None => self.error(node.span(), format!("{} has no variant '{}'", name, variant)),
+                        Some((_, known)) if known.len() != items.len() => self.error(
+                            node.span(),
+                            format!(
+                                "{}::{} has {} field(s) but the pattern has {}",
+                                name,
+                                variant,
+                                known.len(),
+                                items.len()
+                            ),
+                        ),
+                        Some((_, known)) => fields = known,
+                    }
+                }
+                for (idx, item) in items.iter().enumerate() {
+                    let field = match fields.get(idx) {
+                        Some(field) => field.clone(),
+                        None => self.fresh(),
+                    };
+                    self.pattern(item, &field);
+                }
+            }
+            NodeKind::TuplePat => {
+                let items: Vec<_> = node.children().collect();
+                let has_comma = node.child_tokens().any(|token| token.kind() == TokenKind::Comma);
+                if items.len() == 1 && !has_comma {
+                    return self.pattern(&items[0], ty);
+                }
+
+                // Items are checked against what is known of the tuple, so
+                // that mismatches are reported where they are.
+                let item_types = match self.resolve(ty) {
+                    Type::Tuple(known) if known.len() == items.len() => known,
+                    _ => items.iter().map(|_| self.fresh()).collect(),
+                };
+                for (item, item_type) in items.iter().zip(&item_types) {
+                    self.pattern(item, item_type);
+                }
+                self.unify(ty, &Type::Tuple(item_types), node.span());
+            }
+            NodeKind::ListPat => {
+                let items: Vec<_> = node.children().collect();
+                self.unify(ty, &Type::List, node.span());
+                for item in items {
+                    if item.kind() == NodeKind::RestPat {
+                        if let Some(name) = ident(&item) {
+                            self.define(name, Symbol::Binding(Type::List));
+                        }
+                    } else {
+                        let item_type = self.fresh();
+                        self.pattern(&item, &item_type);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks the fields a struct literal gives against the struct, if it
+    /// is known.
+    fn struct_lit(&mut self, node: &SyntaxNode) -> Type {
+        let Some(name) = ident(node) else {
+            return self.fresh();
+        };
+        let fields = self.struct_fields(&name);
+
+        let mut given = Vec::new();
+        let mut has_base = false;
+        for child in node.children() {
+            let Some(val) = child.children().next() else {
+                continue;
+            };
+            let ty = self.expr(&val);
+            match child.kind() {
+                NodeKind::FieldInit => {
+                    let field = ident(&child).unwrap_or_default();
+                    match fields.iter().flatten().find(|(name, _)| *name == field) {
+                        Some((_, Some(expected))) => self.unify(expected, &ty, val.span()),
+                        Some((_, None)) => {}
+                        None if fields.is_some() => {
+                            self.error(child.span(), format!("{} has no field '{}'", name, field));
+                        }
+                        None => {}
+                    }
+                    given.push(field);
+                }
+                NodeKind::StructBase => {
+                    self.unify(&Type::Named(name.clone(), Vec::new()), &ty, val.span());
+                    has_base = true;
+                }
+                _ => {}
+            }
+        }
+
+        if !has_base {
+            for (field, _) in fields.iter().flatten() {
+                if !given.contains(field) {
+                    self.error(node.span(), format!("missing field '{}' in {}", field, name));
+                }
+            }
+        }
+        Type::Named(name, Vec::new())
+    }
+
+    /// The fields of the struct `name`, unless it is not known yet.
+    fn struct_fields(&self, name: &str) -> Option<Vec<(String, Option<Type>)>> {
+        match self.lookup(name) {
+            _ if self.redefined(name) => None,
+            Some(Symbol::Struct(fields)) => Some(fields),
+            Some(_) => None,
+            None if self.defined_later.contains(name) => None,
+            None => match self.env.find(name) {
+                Some((_, NamedInfo::Struct(def))) => Some(field_types(def)),
+                _ => None,
+            },
+        }
+    }
+
+    /// The variants of the enum `name`, unless it is not known yet.
+    fn enum_variants(&self, name: &str) -> Option<Vec<(String, Vec<Option<Type>>)>> {
+        match self.lookup(name) {
+            _ if self.redefined(name) => None,
+            Some(Symbol::Enum(variants)) => Some(variants),
+            Some(_) => None,
+            None if self.defined_later.contains(name) => None,
+            None => match self.env.find(name) {
+                Some((_, NamedInfo::Enum(def))) => Some(variant_types(def)),
+                Some(_) => None,
+                None => enum_def::prelude(name).map(|def| variant_types(&def)),
+            },
+        }
+    }
+
+    /// The type of the target of a field access or method call, as far as
+    /// it is known.
+    fn target(&mut self, node: &SyntaxNode) -> Type {
+        let ty = match node.children().next() {
+            Some(target) => self.expr(&target),
+            None => self.fresh(),
+        };
+        self.resolve(&ty)
+    }
+
+    fn binding(&mut self, node: &SyntaxNode) -> Type {
+        let Some(name) = ident(node) else {
+            return self.fresh();
+        };
+
+        match self.lookup(&name) {
+            _ if self.redefined(&name) => self.fresh(),
+            Some(Symbol::Binding(ty)) => ty,
+            // Using a function, struct or enum as a binding is a name error.
+            Some(Symbol::Func(_) | Symbol::Struct(_) | Symbol::Enum(_)) => self.fresh(),
+            None if self.defined_later.contains(&name) => self.fresh(),
+            None => match self.env.find(&name) {
+                Some((_, NamedInfo::Binding(val))) => Type::of(val),
+                _ => self.fresh(),
+            },
+        }
+    }
+
+    fn call(&mut self, node: &SyntaxNode) -> Type {
+        let args: Vec<_> = args(node).collect();
+        let arg_types: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+        let Some(callee) = node.children().next().and_then(|name_ref| ident(&name_ref)) else {
+            return self.fresh();
+        };
+
+        let env = self.env;
+        let func = match self.lookup(&callee) {
+            _ if self.redefined(&callee) => None,
+            Some(Symbol::Func(scheme)) => Some(self.instantiate(&scheme)),
+            Some(Symbol::Binding(_) | Symbol::Struct(_) | Symbol::Enum(_)) => None,
+            None if self.defined_later.contains(&callee) => None,
+            None => match env.find(&callee) {
+                Some((_, NamedInfo::Func { params, .. })) => Some(self.unknown_func(params.len())),
+                Some((_, NamedInfo::NativeFunc(func))) => func.arity().map(|arity| self.unknown_func(arity)),
+                _ => None,
+            },
+        };
+        let Some(func) = func else {
+            return self.fresh();
+        };
+
+        if func.params.len() != args.len() {
+            self.error(
+                node.span(),
+                format!(
+                    "function '{}' takes {} argument(s) but {} were given",
+                    callee,
+                    func.params.len(),
+                    args.len()
+                ),
+            );
+            return func.ret;
+        }
+
+        for ((param, arg_type), arg) in func.params.iter().zip(&arg_types).zip(&args) {
+            self.unify(param, arg_type, arg.span());
+        }
+        func.ret
+    }
+
+    fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned()
+    }
+
+    /// Whether `name` is a global that the function body being checked
+    /// may see with another type than the one it has here.
+    fn redefined(&self, name: &str) -> bool {
+        !self.returns.is_empty()
+            && self.defined_twice.contains(name)
+            && !self.scopes[1..].iter().any(|scope| scope.contains_key(name))
+    }
+
+    fn define(&mut self, name: String, symbol: Symbol) {
+        self.defined_later.remove(&name);
+        self.scopes.last_mut().unwrap().insert(name, symbol);
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.subst.push(None);
+        Type::Var(self.subst.len() - 1)
+    }
+
+    /// A function whose body the checker cannot see, like a native one.
+    fn unknown_func(&mut self, arity: usize) -> FuncType {
+        FuncType {
+            params: (0..arity).map(|_| self.fresh()).collect(),
+            ret: self.fresh(),
+        }
+    }
+
+    /// Follows what type variables have been found to be, inside tuples and
+    /// type arguments too, until reaching known types or variables that are still open.
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(var) => match &self.subst[*var] {
+                Some(ty) => self.resolve(ty),
+                None => ty.clone(),
+            },
+            Type::Tuple(items) => Type::Tuple(items.iter().map(|item| self.resolve(item)).collect()),
+            Type::Named(name, args) => Type::Named(name.clone(), args.iter().map(|arg| self.resolve(arg)).collect()),
+            ty => ty.clone(),
+        }
+    }
+
+    /// Makes `found` the same type as `expected`, reporting the mismatch at
+    /// `span` if it cannot be.
+    fn unify(&mut self, expected: &Type, found: &Type, span: Span) {
+        let expected = self.resolve(expected);
+        let found = self.resolve(found);
+
+        match (&expected, &found) {
+            (Type::Var(a), Type::Var(b)) if a == b => {}
+            (Type::Var(var), _) if !occurs(*var, &found) => self.subst[*var] = Some(found),
+            (_, Type::Var(var)) if !occurs(*var, &expected) => self.subst[*var] = Some(expected),
+            (Type::Tuple(expected_items), Type::Tuple(found_items)) if expected_items.len() == found_items.len() => {
+                for (expected, found) in expected_items.iter().zip(found_items) {
+                    self.unify(expected, found, span);
+                }
+            }
+            (Type::Named(expected_name, expected_args), Type::Named(found_name, found_args))
+                if expected_name == found_name =>
+            {
+                if expected_args.len() == found_args.len() {
+                    for (expected, found) in expected_args.iter().zip(found_args) {
+                        self.unify(expected, found, span);
+                    }
+                }
+            }
+            _ if expected == found => {}
+            _ => self.error(span, format!("expected {}, found {}", expected, found)),
+        }
+    }
+
+    /// Turns the variables of `ty` that nothing in scope refers to into
+    /// variables of the scheme.
+    fn generalize(&self, ty: FuncType) -> Scheme {
+        let mut in_scope = HashSet::new();
+        for symbol in self.scopes.iter().flat_map(HashMap::values) {
+            match symbol {
+                Symbol::Binding(ty) => self.free_vars(ty, &mut in_scope),
+                Symbol::Func(scheme) => {
+                    let mut vars = HashSet::new();
+                    for ty in scheme.ty.params.iter().chain([&scheme.ty.ret]) {
+                        self.free_vars(ty, &mut vars);
+                    }
+                    in_scope.extend(vars.into_iter().filter(|var| !scheme.vars.contains(var)));
+                }
+                Symbol::Struct(_) | Symbol::Enum(_) => {}
+            }
+        }
+
+        let ty = FuncType {
+            params: ty.params.iter().map(|ty| self.resolve(ty)).collect(),
+            ret: self.resolve(&ty.ret),
+        };
+        let mut vars = HashSet::new();
+        for ty in ty.params.iter().chain([&ty.ret]) {
+            self.free_vars(ty, &mut vars);
+        }
+        let mut vars: Vec<_> = vars.into_iter().filter(|var| !in_scope.contains(var)).collect();
+        vars.sort_unstable();
+        Scheme { vars, ty }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> FuncType {
+        let fresh: HashMap<_, _> = scheme.vars.iter().map(|var| (*var, self.fresh())).collect();
+        FuncType {
+            params: scheme.ty.params.iter().map(|ty| self.replace(ty, &fresh)).collect(),
+            ret: self.replace(&scheme.ty.ret, &fresh),
+        }
+    }
+
+    /// `ty` with the variables in `vars` replaced by what they map to.
+    fn replace(&self, ty: &Type, vars: &HashMap<usize, Type>) -> Type {
+        match self.resolve(ty) {
+            Type::Var(var) => vars.get(&var).cloned().unwrap_or(Type::Var(var)),
+            Type::Tuple(items) => Type::Tuple(items.iter().map(|item| self.replace(item, vars)).collect()),
+            Type::Named(name, args) => Type::Named(name, args.iter().map(|arg| self.replace(arg, vars)).collect()),
+            ty => ty,
+        }
+    }
+
+    fn free_vars(&self, ty: &Type, vars: &mut HashSet<usize>) {
+        match self.resolve(ty) {
+            Type::Var(var) => {
+                vars.insert(var);
+            }
+            Type::Tuple(items) | Type::Named(_, items) => {
+                for item in &items {
+                    self.free_vars(item, vars);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn error(&mut self, span: Span, message: String) {
+        self.errors.push(ParseError { message, span });
+    }
+}
+
+/// Whether the variable `var` is part of `ty`, which would then have to
+/// contain itself to be made the same as it.
+fn occurs(var: usize, ty: &Type) -> bool {
+    match ty {
+        Type::Var(other) => *other == var,
+        Type::Tuple(items) | Type::Named(_, items) => items.iter().any(|item| occurs(var, item)),
+        _ => false,
+    }
+}
+
+/// The first identifier directly in `node`: the name of a definition, a
+/// name reference, or the field or method of a member expression.
+fn ident(node: &SyntaxNode) -> Option<String> {
+    node.child_tokens()
+        .find(|token| token.kind() == TokenKind::Ident)
+        .map(|token| token.text().to_string())
+}
+
+/// The type a binding or function result is annotated with.
+fn annotation(node: &SyntaxNode) -> Option<Type> {
+    let ty = node.children().find(|child| child.kind() == NodeKind::TypeAnn)?;
+    TypeAnn::lower(&ty).map(|ty| Type::annotated(&ty))
+}
+
+fn defined_name(stmt: &SyntaxNode) -> Option<String> {
+    match stmt.kind() {
+        NodeKind::BindingDef | NodeKind::FuncDef | NodeKind::StructDef | NodeKind::EnumDef => ident(stmt),
+        _ => None,
+    }
+}
+
+fn field_types(def: &StructDef) -> Vec<(String, Option<Type>)> {
+    def.fields
+        .iter()
+        .map(|field| (field.name.clone(), field.ty.as_ref().map(Type::annotated)))
+        .collect()
+}
+
+fn variant_types(def: &EnumDef) -> Vec<(String, Vec<Option<Type>>)> {
+    def.variants
+        .iter()
+        .map(|variant| {
+            let fields = variant.fields.iter().map(|field| field.ty.as_ref().map(Type::annotated)).collect();
+            (variant.name.clone(), fields)
+        })
+        .collect()
+}
+
+fn args(node: &SyntaxNode) -> impl Iterator<Item = SyntaxNode> {
+    node.children()
+        .find(|arg_list| arg_list.kind() == NodeKind::ArgList)
+        .into_iter()
+        .flat_map(|arg_list| arg_list.children().collect::<Vec<_>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::HostObject;
+    use crate::Error;
+
+    fn errors_in(src: &str, env: &Env) -> Vec<(String, String)> {
+        let parse = crate::parse(src);
+        assert!(parse.errors().is_empty(), "{:?}", parse.errors());
+        parse
+            .type_errors(env)
+            .into_iter()
+            .map(|error| (error.message, src[error.span.start..error.span.end].to_string()))
+            .collect()
+    }
+
+    fn errors(src: &str) -> Vec<(String, String)> {
+        errors_in(src, &Env::default())
+    }
+
+    fn error(message: &str, at: &str) -> (String, String) {
+        (message.to_string(), at.to_string())
+    }
+
+    #[test]
+    fn report_adding_number_to_unit() {
+        assert_eq!(
+            errors("let a = {\n    let b = 1\n}\n1 + a"),
+            vec![error("expected Number, found Unit", "a")]
+        );
+        assert_eq!(errors("{} * 2"), vec![error("expected Number, found Unit", "{}")]);
+    }
+
+    #[test]
+    fn accept_well_typed_program() {
+        let src = "fn add x y => x + y\nlet a = add(1, 2)\nlet b = {\n    let c = a * 2\n    c - 1\n}\nadd(a, b)";
+        assert_eq!(errors(src), vec![]);
+    }
+
+    #[test]
+    fn infer_parameter_types_from_body() {
+        assert_eq!(
+            errors("fn inc x => x + 1\ninc({})"),
+            vec![error("expected Number, found Unit", "{}")]
+        );
+    }
+
+    #[test]
+    fn infer_result_types_of_functions() {
+        assert_eq!(
+            errors("fn nothing => {}\n1 + nothing()"),
+            vec![error("expected Number, found Unit", "nothing()")]
+        );
+    }
+
+    #[test]
+    fn generalize_functions() {
+        assert_eq!(errors("fn id x => x\nlet a = id(1) + 1\nlet b = id({})"), vec![]);
+        assert_eq!(
+            errors("fn id x => x\nid({}) + 1"),
+            vec![error("expected Number, found Unit", "id({})")]
+        );
+    }
+
+    #[test]
+    fn keep_outer_types_of_nested_functions() {
+        // `inner` is generic in `y` but not in what it returns, which is `x`.
+        let src = "fn outer x => {\n    fn inner y => x\n    inner(1) + inner({})\n}\nouter({})";
+        assert_eq!(errors(src), vec![error("expected Number, found Unit", "{}")]);
+    }
+
+    #[test]
+    fn check_recursive_calls_against_each_other() {
+        assert_eq!(
+            errors("fn f x => f(x + 1) + f({})"),
+            vec![error("expected Number, found Unit", "{}")]
+        );
+    }
+
+    #[test]
+    fn report_wrong_number_of_arguments() {
+        assert_eq!(
+            errors("fn add x y => x + y\nadd(1)"),
+            vec![error("function 'add' takes 2 argument(s) but 1 were given", "add(1)")]
+        );
+    }
+
+    #[test]
+    fn use_types_of_globals_in_env() {
+        let mut env = Env::default();
+        env.store_binding("name".to_string(), Val::from("raga"));
+        env.store_func("twice".to_string(), vec!["x".to_string()], crate::stmt::Stmt::new("x + x").unwrap().1);
+
+        assert_eq!(
+            errors_in("name + twice(1, 2)", &env),
+            vec![
+                error("expected Number, found String", "name"),
+                error("function 'twice' takes 1 argument(s) but 2 were given", "twice(1, 2)"),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_members_of_values_without_them() {
+        struct Request;
+
+        impl HostObject for Request {
+            fn type_name(&self) -> &'static str {
+                "Request"
+            }
+
+            fn get_field(&self, name: &str) -> Result<Val, Error> {
+                Err(Error::Eval(format!("Request has no field '{}'", name).into()))
+            }
+        }
+
+        let mut env = Env::default();
+        env.store_binding("req".to_string(), Val::object(Request));
+
+        assert_eq!(
+            errors_in("let a = req.path\nlet b = req.depth()\n1.path + {}.len()", &env),
+            vec![
+                error("cannot access field 'path' of Number", "1.path"),
+                error("cannot call method 'len' on Unit", "{}.len()"),
+            ]
+        );
+    }
+
+    #[test]
+    fn use_annotated_types() {
+        assert_eq!(
+            errors("let a: Int = {}\nfn f x: Int -> Unit => x\nf(a)\nf({})"),
+            vec![
+                error("expected Number, found Unit", "{}"),
+                error("expected Unit, found Number", "x"),
+                error("expected Number, found Unit", "{}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_struct_fields() {
+        let src = "struct Point { x: Int, y }\nlet p = Point { x: 1, y: {} }\np.x + p.y\n{} + p.x\nPoint { x: {}, ..p }\nPoint { x: 1, z: 2 }\np.z\np + 1";
+        assert_eq!(
+            errors(src),
+            vec![
+                error("expected Number, found Unit", "{}"),
+                error("expected Number, found Unit", "{}"),
+                error("Point has no field 'z'", "z: 2"),
+                error("missing field 'y' in Point", "Point { x: 1, z: 2 }"),
+                error("Point has no field 'z'", "p.z"),
+                error("expected Number, found Point", "p"),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_comparisons() {
+        let src = "let a = 1 < 2\na + 1\n1 == {}\ntrue > false\n{} != {}";
+        assert_eq!(
+            errors(src),
+            vec![
+                error("expected Number, found Bool", "a"),
+                error("expected Number, found Unit", "{}"),
+                error("expected Number, found Bool", "true"),
+                error("expected Number, found Bool", "false"),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_enum_variants() {
+        let src = "enum Shape { Circle(r: Int), Rect(w, h), Empty }\nlet a = Shape::Circle({})\nlet b = Shape::Rect(1)\nlet c = Shape::Square\nShape::Empty + 1\nShape::Empty.r";
+        assert_eq!(
+            errors(src),
+            vec![
+                error("expected Number, found Unit", "{}"),
+                error("variant 'Shape::Rect' takes 2 value(s) but 1 were given", "Shape::Rect(1)"),
+                error("Shape has no variant 'Square'", "Shape::Square"),
+                error("expected Number, found Shape", "Shape::Empty"),
+                error("cannot access field 'r' of Shape", "Shape::Empty.r"),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_match_arms() {
+        let src = "enum Shape { Circle(r: Int), Empty }\nlet s = Shape::Empty\nmatch s {\n    Shape::Circle(r) if r => r,\n    Shape::Circle(a, b) => 1,\n    Shape::Empty => {},\n    (x, [y, ..z]) => x,\n}";
+        assert_eq!(
+            errors(src),
+            vec![
+                error("expected Bool, found Number", "r"),
+                error("Shape::Circle has 1 field(s) but the pattern has 2", "Shape::Circle(a, b)"),
+                error("expected Number, found Unit", "{}"),
+                error("expected Shape, found (_, List)", "(x, [y, ..z])"),
+            ]
+        );
+        assert_eq!(errors("let n = match 1 {\n    2 => 3,\n    n => n,\n}\nn + 1"), vec![]);
+    }
+
+    #[test]
+    fn report_values_no_arm_matches() {
+        let src = "enum Shape { Circle(r), Rect(w, h), Empty }\nfn area s => match s {\n    Shape::Circle(r) => r,\n    Shape::Rect(w, h) if t => w,\n    Shape::Empty => 0,\n}";
+        assert_eq!(
+            errors(src),
+            vec![error(
+                "match does not cover Shape::Rect(_, _)",
+                "match s {\n    Shape::Circle(r) => r,\n    Shape::Rect(w, h) if t => w,\n    Shape::Empty => 0,\n}"
+            )]
+        );
+        assert_eq!(
+            errors("match (1, 2) {\n    (0, _) => 1,\n}"),
+            vec![error("match does not cover (_, _)", "match (1, 2) {\n    (0, _) => 1,\n}")]
+        );
+        assert_eq!(errors("fn first l => match l {\n    [] => 0,\n    [x, ..] => x,\n}"), vec![]);
+    }
+
+    #[test]
+    fn check_tuples_item_by_item() {
+        assert_eq!(
+            errors("match (1, {}) {\n    (a, b) => a + b,\n}"),
+            vec![error("expected Number, found Unit", "b")]
+        );
+        assert_eq!(
+            errors("let t = (1, (2,))\nmatch t {\n    (a, b, c) => a,\n}\nmatch t {\n    (a, (b,)) => a + b,\n}\nt + 1"),
+            vec![
+                error("expected (Number, (Number,)), found (_, _, _)", "(a, b, c)"),
+                error("expected Number, found (Number, (Number,))", "t"),
+            ]
+        );
+        assert_eq!(
+            errors("match (1, 2) {\n    [] => 0,\n    [x, ..] => x,\n}"),
+            vec![
+                error("expected (Number, Number), found List", "[]"),
+                error("expected (Number, Number), found List", "[x, ..]"),
+            ]
+        );
+        // Functions are generic over the items of the tuples they take.
+        assert_eq!(errors("fn swap t => match t {\n    (a, b) => (b, a),\n}\nswap((1, {}))\nswap(({}, 2))"), vec![]);
+    }
+
+    #[test]
+    fn check_prelude_enums_and_tries() {
+        let src = "fn half x => match x {\n    Some(n) => Some(n / 2),\n    None => None,\n}\nfn twice x => Some(half(x)? + half(x)?)\nfn bad x => 1?\nfn mixed x => half(x)? + 1\nmatch Ok(1) {\n    Ok(n) => n,\n}";
+        assert_eq!(
+            errors(src),
+            vec![
+                error("cannot use ? on Number", "1?"),
+                error("expected Option, found Number", "half(x)? + 1"),
+                error("match does not cover Err(_)", "match Ok(1) {\n    Ok(n) => n,\n}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_values_enums_hold() {
+        assert_eq!(
+            errors("match Some(1) {\n    Some(x) => x,\n    None => {},\n}"),
+            vec![error("expected Number, found Unit", "{}")]
+        );
+        let src = "enum Cell { Full(v), Empty }\nlet c = Cell::Full({})\nmatch c {\n    Cell::Full(v) => v + 1,\n    Cell::Empty => 0,\n}";
+        assert_eq!(errors(src), vec![error("expected Number, found Unit", "v")]);
+        let src = "fn inc o => match o {\n    Some(n) => Some(n + 1),\n    None => None,\n}\ninc(Some(1))\ninc(Some({}))";
+        assert_eq!(errors(src), vec![error("expected Number, found Unit", "Some({})")]);
+    }
+
+    #[test]
+    fn keep_error_type_through_try() {
+        let src = "fn fail x => Err(1)\nfn both x => Ok(fail(x)? + 1)\nmatch both(1) {\n    Ok(n) => {},\n    Err(e) => e,\n}";
+        assert_eq!(errors(src), vec![error("expected Unit, found Number", "e")]);
+        assert_eq!(errors("let a = Some(1)? + 1\nlet b = Ok({})? + 1"), vec![error("expected Number, found Unit", "Ok({})?")]);
+    }
+
+    #[test]
+    fn accept_globals_defined_after_use() {
+        assert_eq!(errors("fn f => g() + a\nfn g => 1\nlet a = 2\nf()"), vec![]);
+        assert_eq!(errors("let a = {}\nfn g => a + 1\nlet a = 2\ng()"), vec![]);
+    }
+}
@@ -2,14 +2,6 @@ pub(crate) fn extract_digits(s: &str) -> Result<(&str, &str), String> {
     take_while_err(|c| c.is_ascii_digit(), s, "expected digits".to_string())
 }
 
-pub(crate) fn extract_whitespace(s: &str) -> Result<(&str, &str), String> {
-    take_while_err(|c| c == ' ', s, "expected a space".to_string())
-}
-
-pub(crate) fn skip_whitespace(s: &str) -> (&str, &str) {
-    take_while(|c| c.is_whitespace(), s)
-}
-
 pub(crate) fn take_while_err(
     accept: impl Fn(char) -> bool,
     s: &str,
@@ -47,31 +39,6 @@ pub(crate) fn extract_ident(s: &str) -> Result<(&str, &str), String> {
     }
 }
 
-pub(crate) fn tag<'a, 'b>(starting_text: &'a str, s: &'b str) -> Result<&'b str, String> {
-    if let Some(end) = s.strip_prefix(starting_text) {
-        Ok(end)
-    } else {
-        Err(format!("expected {}", starting_text))
-    }
-}
-
-pub(crate) fn sequence<T>(
-    parser: impl Fn(&str) -> Result<(&str, T), String>,
-    mut s: &str,
-) -> Result<(&str, Vec<T>), String> {
-    let mut items = vec![];
-
-    while let Ok((new_s, item)) = parser(s) {
-        s = new_s;
-        items.push(item);
-
-        let (new_s, _) = skip_whitespace(s);
-        s = new_s;
-    }
-
-    Ok((s, items))
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -101,16 +68,6 @@ mod test {
         assert_eq!(extract_digits("100"), Ok(("", "100")))
     }
 
-    #[test]
-    fn extract_spaces() {
-        assert_eq!(extract_whitespace("    1"), Ok(("1", "    ")))
-    }
-
-    #[test]
-    fn do_not_extract_spaces_when_input_does_not_have_space() {
-        assert_eq!(extract_whitespace("1"), Err("expected a space".to_string()))
-    }
-
     #[test]
     fn extract_alphabetic_ident() {
         assert_eq!(extract_ident("absc break"), Ok((" break", "absc")))
@@ -128,9 +85,4 @@ mod test {
             Err("expected identifier".to_string())
         )
     }
-
-    #[test]
-    fn tag_word() {
-        assert_eq!(tag("let", "let a"), Ok(" a"))
-    }
 }
@@ -1,17 +1,752 @@
+use crate::enum_def;
+use crate::object::HostObject;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
 use std::fmt;
 use std::fmt::Formatter;
+use std::mem;
+use std::rc::Rc;
 
+/// A value of a script. Strings, lists, tuples, maps, structs and enum
+/// variants are shared rather than copied when the value is cloned, as they
+/// are never changed in place.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Val {
     Number(i32),
+    Float(f64),
+    Bool(bool),
+    Str(Rc<str>),
+    List(Rc<[Val]>),
+    /// A fixed number of values, which unlike a list may be of different
+    /// types.
+    Tuple(Rc<[Val]>),
+    Map(Rc<BTreeMap<String, Val>>),
+    Struct(Rc<StructVal>),
+    Enum(Rc<EnumVal>),
+    Object(Rc<dyn HostObject>),
     Unit,
 }
 
+/// A value of a struct the script defined. Two are equal if they have the
+/// same name and equal fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructVal {
+    pub name: String,
+    /// The fields in the order the struct declares them.
+    pub fields: Vec<(String, Val)>,
+}
+
+impl StructVal {
+    pub fn get(&self, field: &str) -> Option<&Val> {
+        self.fields.iter().find(|(name, _)| name == field).map(|(_, val)| val)
+    }
+}
+
+/// A variant of an enum the script defined, with the values it holds. Two
+/// are equal if they are the same variant of the same enum and hold equal
+/// values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVal {
+    /// The name of the enum.
+    pub name: String,
+    pub variant: String,
+    pub fields: Vec<Val>,
+}
+
+impl Val {
+    pub fn object(object: impl HostObject) -> Self {
+        Self::Object(Rc::new(object))
+    }
+
+    /// `Some(val)` of the built-in `Option` enum.
+    pub fn some(val: impl Into<Val>) -> Self {
+        Self::prelude_variant("Option", "Some", vec![val.into()])
+    }
+
+    /// `None` of the built-in `Option` enum.
+    pub fn none() -> Self {
+        Self::prelude_variant("Option", "None", Vec::new())
+    }
+
+    /// `Ok(val)` of the built-in `Result` enum.
+    pub fn ok(val: impl Into<Val>) -> Self {
+        Self::prelude_variant("Result", "Ok", vec![val.into()])
+    }
+
+    /// `Err(error)` of the built-in `Result` enum, which native functions
+    /// can return for failures that scripts should be able to handle.
+    pub fn err(error: impl Into<Val>) -> Self {
+        Self::prelude_variant("Result", "Err", vec![error.into()])
+    }
+
+    fn prelude_variant(name: &str, variant: &str, fields: Vec<Val>) -> Self {
+        Self::Enum(Rc::new(EnumVal {
+            name: name.to_string(),
+            variant: variant.to_string(),
+            fields,
+        }))
+    }
+
+    /// The host object of type `T` this value wraps, if it is one.
+    pub fn downcast_ref<T: HostObject>(&self) -> Option<&T> {
+        match self {
+            Self::Object(object) => object.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// The name of the value's type, which for structs, enums and host
+    /// objects is their own.
+    pub fn type_name(&self) -> &str {
+        match self {
+            Self::Number(_) => "Number",
+            Self::Float(_) => "Float",
+            Self::Bool(_) => "Bool",
+            Self::Str(_) => "String",
+            Self::List(_) => "List",
+            Self::Tuple(_) => "Tuple",
+            Self::Map(_) => "Map",
+            Self::Struct(val) => &val.name,
+            Self::Enum(val) => &val.name,
+            Self::Object(object) => object.type_name(),
+            Self::Unit => "Unit",
+        }
+    }
+
+    /// Approximate number of bytes this value takes up, including the heap
+    /// memory it refers to. Host objects are counted as the pointer to them,
+    /// and memory shared between values is counted for each of them.
+    pub(crate) fn approx_size(&self) -> usize {
+        self.size(true)
+    }
+
+    /// Like [`Val::approx_size`], but leaves out heap memory that other
+    /// values share, so that holding on to one more clone of a value costs
+    /// no more than the clone itself.
+    pub(crate) fn unshared_size(&self) -> usize {
+        self.size(false)
+    }
+
+    fn size(&self, count_shared: bool) -> usize {
+        let counted = |refs: usize| count_shared || refs == 1;
+        let owned = match self {
+            Self::Str(s) if counted(Rc::strong_count(s)) => s.len(),
+            Self::List(vals) | Self::Tuple(vals) if counted(Rc::strong_count(vals)) => {
+                vals.iter().map(|val| val.size(count_shared)).sum()
+            }
+            Self::Map(entries) if counted(Rc::strong_count(entries)) => entries
+                .iter()
+                .map(|(key, val)| key.len() + val.size(count_shared))
+                .sum(),
+            Self::Struct(val) if counted(Rc::strong_count(val)) => val
+                .fields
+                .iter()
+                .map(|(name, val)| name.len() + val.size(count_shared))
+                .sum(),
+            Self::Enum(val) if counted(Rc::strong_count(val)) => {
+                val.variant.len() + val.fields.iter().map(|val| val.size(count_shared)).sum::<usize>()
+            }
+            _ => 0,
+        };
+        mem::size_of::<Self>() + owned
+    }
+}
+
 impl fmt::Display for Val {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Self::Number(val) => write!(f, "{}", val),
+            Self::Float(val) => write!(f, "{:?}", val),
+            Self::Bool(val) => write!(f, "{}", val),
+            Self::Str(val) => write!(f, "{:?}", val),
+            Self::List(vals) => {
+                write!(f, "[")?;
+                for (idx, val) in vals.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", val)?;
+                }
+                write!(f, "]")
+            }
+            Self::Tuple(vals) => {
+                write!(f, "(")?;
+                for (idx, val) in vals.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", val)?;
+                }
+                if vals.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Self::Map(entries) => {
+                write!(f, "{{")?;
+                for (idx, (key, val)) in entries.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: {}", key, val)?;
+                }
+                write!(f, "}}")
+            }
+            Self::Struct(val) => write!(f, "{}", val),
+            Self::Enum(val) => write!(f, "{}", val),
+            Self::Object(object) => write!(f, "{}", object),
             Self::Unit => write!(f, "Unit")
         }
     }
-}
\ No newline at end of file
+}
+
+/// Prints `Point { x: 1, y: 2 }`, like the literal the value could be built
+/// with.
+impl fmt::Display for StructVal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.fields.is_empty() {
+            return write!(f, "{} {{}}", self.name);
+        }
+
+        write!(f, "{} {{ ", self.name)?;
+        for (idx, (name, val)) in self.fields.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", name, val)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+/// Prints `Shape::Rect(1, 2)`, or `Shape::Empty` for a variant without
+/// values, like the expression the value could be built with. Variants of
+/// the built-in enums are printed without the enum name, as in `Some(1)`.
+impl fmt::Display for EnumVal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if enum_def::prelude_enum(&self.variant) != Some(self.name.as_str()) {
+            write!(f, "{}::", self.name)?;
+        }
+        write!(f, "{}", self.variant)?;
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+
+        write!(f, "(")?;
+        for (idx, val) in self.fields.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", val)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A [`Val`] could not be converted into a Rust value, or the other way
+/// around.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversionError {
+    WrongType {
+        expected: &'static str,
+        found: String,
+    },
+    /// An integer does not fit into the type it is converted to.
+    OutOfRange { value: String, target: &'static str },
+    /// Raised by `Serialize` and `Deserialize` impls.
+    Custom(String),
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongType { expected, found } => {
+                write!(f, "expected {} but found {}", expected, found)
+            }
+            Self::OutOfRange { value, target } => write!(f, "{} does not fit into {}", value, target),
+            Self::Custom(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Lets conversions into `Val` itself, which cannot fail, be used where a
+/// `ConversionError` is expected, e.g. for `Vec<Val>`.
+impl From<Infallible> for ConversionError {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+fn wrong_type(expected: &'static str, found: &Val) -> ConversionError {
+    ConversionError::WrongType {
+        expected,
+        found: found.type_name().to_string(),
+    }
+}
+
+macro_rules! impl_from_small_int {
+    ($($int:ty),*) => {
+        $(
+            impl From<$int> for Val {
+                fn from(n: $int) -> Self {
+                    Self::Number(n.into())
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_try_from_large_int {
+    ($($int:ty),*) => {
+        $(
+            impl TryFrom<$int> for Val {
+                type Error = ConversionError;
+
+                fn try_from(n: $int) -> Result<Self, Self::Error> {
+                    i32::try_from(n).map(Self::Number).map_err(|_| ConversionError::OutOfRange {
+                        value: n.to_string(),
+                        target: "Number",
+                    })
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_int_try_from_val {
+    ($($int:ty),*) => {
+        $(
+            impl TryFrom<Val> for $int {
+                type Error = ConversionError;
+
+                fn try_from(val: Val) -> Result<Self, Self::Error> {
+                    match val {
+                        Val::Number(n) => <$int>::try_from(n).map_err(|_| ConversionError::OutOfRange {
+                            value: n.to_string(),
+                            target: stringify!($int),
+                        }),
+                        other => Err(wrong_type("Number", &other)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_small_int!(i8, i16, i32, u8, u16);
+impl_try_from_large_int!(i64, i128, isize, u32, u64, u128, usize);
+impl_int_try_from_val!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl From<f64> for Val {
+    fn from(n: f64) -> Self {
+        Self::Float(n)
+    }
+}
+
+impl From<f32> for Val {
+    fn from(n: f32) -> Self {
+        Self::Float(n.into())
+    }
+}
+
+/// Numbers are accepted too, as every `i32` is exactly representable.
+impl TryFrom<Val> for f64 {
+    type Error = ConversionError;
+
+    fn try_from(val: Val) -> Result<Self, Self::Error> {
+        match val {
+            Val::Float(n) => Ok(n),
+            Val::Number(n) => Ok(n.into()),
+            other => Err(wrong_type("Float", &other)),
+        }
+    }
+}
+
+impl TryFrom<Val> for f32 {
+    type Error = ConversionError;
+
+    fn try_from(val: Val) -> Result<Self, Self::Error> {
+        f64::try_from(val).map(|n| n as f32)
+    }
+}
+
+impl From<bool> for Val {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl TryFrom<Val> for bool {
+    type Error = ConversionError;
+
+    fn try_from(val: Val) -> Result<Self, Self::Error> {
+        match val {
+            Val::Bool(b) => Ok(b),
+            other => Err(wrong_type("Bool", &other)),
+        }
+    }
+}
+
+impl From<String> for Val {
+    fn from(s: String) -> Self {
+        Self::Str(s.into())
+    }
+}
+
+impl From<&str> for Val {
+    fn from(s: &str) -> Self {
+        Self::Str(s.into())
+    }
+}
+
+impl TryFrom<Val> for String {
+    type Error = ConversionError;
+
+    fn try_from(val: Val) -> Result<Self, Self::Error> {
+        match val {
+            Val::Str(s) => Ok(s.to_string()),
+            other => Err(wrong_type("String", &other)),
+        }
+    }
+}
+
+impl<T: Into<Val>> From<Vec<T>> for Val {
+    fn from(vals: Vec<T>) -> Self {
+        Self::List(vals.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T> TryFrom<Val> for Vec<T>
+where
+    T: TryFrom<Val>,
+    ConversionError: From<T::Error>,
+{
+    type Error = ConversionError;
+
+    fn try_from(val: Val) -> Result<Self, Self::Error> {
+        match val {
+            Val::List(vals) => vals
+                .iter()
+                .map(|val| T::try_from(val.clone()).map_err(ConversionError::from))
+                .collect(),
+            other => Err(wrong_type("List", &other)),
+        }
+    }
+}
+
+impl<T: Into<Val>> From<HashMap<String, T>> for Val {
+    fn from(entries: HashMap<String, T>) -> Self {
+        Self::Map(Rc::new(entries.into_iter().map(|(key, val)| (key, val.into())).collect()))
+    }
+}
+
+impl<T: Into<Val>> From<BTreeMap<String, T>> for Val {
+    fn from(entries: BTreeMap<String, T>) -> Self {
+        Self::Map(Rc::new(entries.into_iter().map(|(key, val)| (key, val.into())).collect()))
+    }
+}
+
+impl<T> TryFrom<Val> for HashMap<String, T>
+where
+    T: TryFrom<Val>,
+    ConversionError: From<T::Error>,
+{
+    type Error = ConversionError;
+
+    fn try_from(val: Val) -> Result<Self, Self::Error> {
+        match val {
+            Val::Map(entries) => Rc::unwrap_or_clone(entries)
+                .into_iter()
+                .map(|(key, val)| Ok((key, T::try_from(val)?)))
+                .collect(),
+            other => Err(wrong_type("Map", &other)),
+        }
+    }
+}
+
+/// `Some` and `None` of the built-in `Option` enum.
+impl<T: Into<Val>> From<Option<T>> for Val {
+    fn from(option: Option<T>) -> Self {
+        option.map_or_else(Self::none, Self::some)
+    }
+}
+
+/// `Ok` and `Err` of the built-in `Result` enum. A native function
+/// returning a `Result<T, Error>` fails instead, see
+/// [`IntoNativeResult`](crate::IntoNativeResult).
+impl<T: Into<Val>, E: Into<Val>> From<Result<T, E>> for Val {
+    fn from(result: Result<T, E>) -> Self {
+        result.map_or_else(Self::err, Self::ok)
+    }
+}
+
+/// `Unit` converts to `None` as well, so that optional arguments can be
+/// left out by passing `Unit`.
+fn option_try_from<T>(val: Val) -> Result<Option<T>, ConversionError>
+where
+    T: TryFrom<Val>,
+    ConversionError: From<T::Error>,
+{
+    match val {
+        Val::Unit => Ok(None),
+        Val::Enum(option) if option.name == "Option" => match (option.variant.as_str(), option.fields.as_slice()) {
+            ("None", []) => Ok(None),
+            ("Some", [val]) => Ok(Some(T::try_from(val.clone())?)),
+            _ => Ok(Some(T::try_from(Val::Enum(option))?)),
+        },
+        val => Ok(Some(T::try_from(val)?)),
+    }
+}
+
+/// Implemented per type, as a blanket impl would overlap with the one the
+/// standard library derives from `From<Val> for Option<Val>`. That one
+/// stays, so an `Option<Val>` is always `Some`.
+macro_rules! impl_option_try_from_val {
+    ($($ty:ty),*) => {
+        $(
+            impl TryFrom<Val> for Option<$ty> {
+                type Error = ConversionError;
+
+                fn try_from(val: Val) -> Result<Self, Self::Error> {
+                    option_try_from(val)
+                }
+            }
+        )*
+    };
+}
+
+impl_option_try_from_val!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_option_try_from_val!(f32, f64, bool, String, ());
+
+impl<T> TryFrom<Val> for Option<Vec<T>>
+where
+    T: TryFrom<Val>,
+    ConversionError: From<T::Error>,
+{
+    type Error = ConversionError;
+
+    fn try_from(val: Val) -> Result<Self, Self::Error> {
+        option_try_from::<Vec<T>>(val)
+    }
+}
+
+impl<T> TryFrom<Val> for Option<HashMap<String, T>>
+where
+    T: TryFrom<Val>,
+    ConversionError: From<T::Error>,
+{
+    type Error = ConversionError;
+
+    fn try_from(val: Val) -> Result<Self, Self::Error> {
+        option_try_from::<HashMap<String, T>>(val)
+    }
+}
+
+impl From<()> for Val {
+    fn from(_: ()) -> Self {
+        Self::Unit
+    }
+}
+
+impl TryFrom<Val> for () {
+    type Error = ConversionError;
+
+    fn try_from(val: Val) -> Result<Self, Self::Error> {
+        match val {
+            Val::Unit => Ok(()),
+            other => Err(wrong_type("Unit", &other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approx_size_includes_owned_memory() {
+        let unit = Val::Unit.approx_size();
+        assert_eq!(Val::Number(1).approx_size(), unit);
+        assert_eq!(Val::Str("abc".into()).approx_size(), unit + 3);
+        assert_eq!(
+            Val::List(vec![Val::Str("ab".into()), Val::Unit].into()).approx_size(),
+            3 * unit + 2
+        );
+    }
+
+    #[test]
+    fn leave_shared_memory_out_of_unshared_size() {
+        let unit = Val::Unit.approx_size();
+        let s = Val::Str("abc".into());
+        assert_eq!(s.unshared_size(), unit + 3);
+
+        let list = Val::List(vec![s.clone(), Val::Unit].into());
+        assert_eq!(list.unshared_size(), 3 * unit);
+        assert_eq!(list.approx_size(), 3 * unit + 3);
+
+        let clone = list.clone();
+        assert_eq!(clone.unshared_size(), unit);
+        assert_eq!(clone.approx_size(), 3 * unit + 3);
+    }
+
+    #[test]
+    fn share_contents_between_clones() {
+        let list = Val::from(vec![1, 2, 3]);
+        match (&list, &list.clone()) {
+            (Val::List(a), Val::List(b)) => assert!(Rc::ptr_eq(a, b)),
+            other => panic!("expected lists, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn convert_number() {
+        assert_eq!(Val::from(5), Val::Number(5));
+        assert_eq!(i32::try_from(Val::Number(5)), Ok(5));
+        assert_eq!(u8::try_from(Val::Number(5)), Ok(5));
+        assert_eq!(Val::try_from(7_u64), Ok(Val::Number(7)));
+    }
+
+    #[test]
+    fn refuse_to_convert_wrong_type() {
+        assert_eq!(
+            i32::try_from(Val::Unit).map_err(|err| err.to_string()),
+            Err("expected Number but found Unit".to_string())
+        );
+    }
+
+    #[test]
+    fn refuse_to_convert_out_of_range_integers() {
+        assert_eq!(
+            u8::try_from(Val::Number(-1)).map_err(|err| err.to_string()),
+            Err("-1 does not fit into u8".to_string())
+        );
+        assert_eq!(
+            Val::try_from(i64::MAX).map_err(|err| err.to_string()),
+            Err("9223372036854775807 does not fit into Number".to_string())
+        );
+    }
+
+    #[test]
+    fn convert_float_bool_and_string() {
+        assert_eq!(f64::try_from(Val::from(1.5)), Ok(1.5));
+        assert_eq!(f64::try_from(Val::Number(2)), Ok(2.0));
+        assert_eq!(bool::try_from(Val::from(true)), Ok(true));
+        assert_eq!(String::try_from(Val::from("hi")), Ok("hi".to_string()));
+    }
+
+    #[test]
+    fn convert_collections() {
+        let list = Val::from(vec![1, 2]);
+        assert_eq!(list, Val::List(vec![Val::Number(1), Val::Number(2)].into()));
+        assert_eq!(Vec::<i32>::try_from(list.clone()), Ok(vec![1, 2]));
+        assert_eq!(Vec::<Val>::try_from(list), Ok(vec![Val::Number(1), Val::Number(2)]));
+
+        let map = Val::from(HashMap::from([("a".to_string(), true)]));
+        assert_eq!(
+            HashMap::<String, bool>::try_from(map),
+            Ok(HashMap::from([("a".to_string(), true)]))
+        );
+
+        assert_eq!(
+            Vec::<i32>::try_from(Val::List(vec![Val::Bool(false)].into())),
+            Err(ConversionError::WrongType {
+                expected: "Number",
+                found: "Bool".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn convert_option_and_unit() {
+        assert_eq!(Val::from(None::<i32>), Val::none());
+        assert_eq!(Val::from(Some(3)), Val::some(3));
+        assert_eq!(Option::<i32>::try_from(Val::Unit), Ok(None));
+        assert_eq!(Option::<i32>::try_from(Val::none()), Ok(None));
+        assert_eq!(Option::<i32>::try_from(Val::some(3)), Ok(Some(3)));
+        assert_eq!(Option::<i32>::try_from(Val::Number(3)), Ok(Some(3)));
+        assert_eq!(<()>::try_from(Val::from(())), Ok(()));
+    }
+
+    #[test]
+    fn convert_none_and_unit_to_none_for_every_type() {
+        fn check<T>(some: Val, expected: T)
+        where
+            T: fmt::Debug + PartialEq,
+            Option<T>: TryFrom<Val, Error = ConversionError>,
+        {
+            assert_eq!(Option::<T>::try_from(Val::Unit), Ok(None));
+            assert_eq!(Option::<T>::try_from(Val::none()), Ok(None));
+            assert_eq!(Option::<T>::try_from(Val::some(some)), Ok(Some(expected)));
+        }
+
+        check(Val::Number(-3), -3_i64);
+        check(Val::Number(3), 3_usize);
+        check(Val::from(1.5), 1.5_f32);
+        check(Val::from(true), true);
+        check(Val::from("hi"), "hi".to_string());
+        check(Val::Unit, ());
+        check(Val::from(vec![1, 2]), vec![1_u8, 2]);
+        check(Val::from(vec![Val::Unit]), vec![Val::Unit]);
+        check(
+            Val::from(HashMap::from([("a".to_string(), 1)])),
+            HashMap::from([("a".to_string(), 1_i32)]),
+        );
+    }
+
+    #[test]
+    fn display_values() {
+        let map = Val::Map(Rc::new(BTreeMap::from([
+            ("b".to_string(), Val::Float(1.0)),
+            ("a".to_string(), Val::List(vec![Val::Str("x".into()), Val::Bool(true)].into())),
+        ])));
+        assert_eq!(map.to_string(), r#"{"a": ["x", true], "b": 1.0}"#);
+    }
+
+    #[test]
+    fn display_and_compare_structs() {
+        let point = |x| {
+            Val::Struct(Rc::new(StructVal {
+                name: "Point".to_string(),
+                fields: vec![("x".to_string(), Val::Number(x)), ("y".to_string(), Val::Unit)],
+            }))
+        };
+        assert_eq!(point(1).to_string(), "Point { x: 1, y: Unit }");
+        assert_eq!(point(1).type_name(), "Point");
+        assert_eq!(point(1), point(1));
+        assert_ne!(point(1), point(2));
+        assert_eq!(
+            i32::try_from(point(1)),
+            Err(ConversionError::WrongType {
+                expected: "Number",
+                found: "Point".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn display_and_compare_enum_variants() {
+        let variant = |variant: &str, fields| {
+            Val::Enum(Rc::new(EnumVal {
+                name: "Shape".to_string(),
+                variant: variant.to_string(),
+                fields,
+            }))
+        };
+        assert_eq!(variant("Rect", vec![Val::Number(1), Val::Unit]).to_string(), "Shape::Rect(1, Unit)");
+        assert_eq!(variant("Empty", vec![]).to_string(), "Shape::Empty");
+        assert_eq!(variant("Empty", vec![]).type_name(), "Shape");
+        assert_eq!(variant("Circle", vec![Val::Number(1)]), variant("Circle", vec![Val::Number(1)]));
+        assert_ne!(variant("Circle", vec![Val::Number(1)]), variant("Circle", vec![Val::Number(2)]));
+        assert_ne!(variant("Circle", vec![]), variant("Empty", vec![]));
+    }
+
+    #[test]
+    fn display_and_convert_prelude_variants() {
+        assert_eq!(Val::some(Val::none()).to_string(), "Some(None)");
+        assert_eq!(Val::from(Ok::<i32, &str>(1)).to_string(), "Ok(1)");
+        assert_eq!(Val::from(Err::<i32, &str>("bad")), Val::err("bad"));
+        assert_eq!(Val::err("bad").type_name(), "Result");
+    }
+}
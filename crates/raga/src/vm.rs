@@ -0,0 +1,510 @@
+//! A stack-based virtual machine running the bytecode from
+//! [`compiler`](crate::compiler).
+//!
+//! Values are kept on a single stack, with each call frame owning the slots
+//! from its base upwards. Calls push a frame instead of recursing, so deep
+//! recursion in a script never grows the native stack.
+
+use crate::bytecode::{Function, Instr, Program};
+use crate::compiler;
+use crate::env::{self, Env, NamedInfo, MAX_CALL_DEPTH};
+use crate::error::EvalError;
+use crate::expr::{match_expr, member, try_expr};
+use crate::val::Val;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::rc::Rc;
+
+struct Frame {
+    program: Rc<Program>,
+    func: usize,
+    ip: usize,
+    /// Index of the first slot of the frame on the stack.
+    base: usize,
+    /// Index of the frame of the function this one was defined in.
+    static_link: Option<usize>,
+    /// How many function calls deep this frame is.
+    depth: usize,
+}
+
+impl Frame {
+    fn function(&self) -> &Function {
+        &self.program.funcs[self.func]
+    }
+}
+
+pub(crate) struct Vm<'a, 'env> {
+    env: &'a mut Env<'env>,
+    /// Global functions compiled before, by name.
+    cache: &'a HashMap<String, Rc<Program>>,
+    /// Global functions defined or compiled while running, by name.
+    compiled: HashMap<String, Rc<Program>>,
+    stack: Vec<Val>,
+    frames: Vec<Frame>,
+}
+
+impl<'a, 'env> Vm<'a, 'env> {
+    /// A machine running code against `env`, which looks up the global
+    /// functions it calls in `cache` before compiling them.
+    pub(crate) fn new(env: &'a mut Env<'env>, cache: &'a HashMap<String, Rc<Program>>) -> Self {
+        Self {
+            env,
+            cache,
+            compiled: HashMap::new(),
+            stack: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Global functions that were defined or compiled while running, which
+    /// can be added to the cache once their definitions are kept.
+    pub(crate) fn into_compiled(self) -> HashMap<String, Rc<Program>> {
+        self.compiled
+    }
+
+    /// Runs a compiled program, returning the value of its last statement.
+    pub(crate) fn run(&mut self, program: Rc<Program>) -> Result<Val, EvalError> {
+        let num_slots = program.funcs[0].num_slots;
+        self.stack.resize(num_slots, Val::Unit);
+        self.frames.push(Frame {
+            program,
+            func: 0,
+            ip: 0,
+            base: 0,
+            static_link: None,
+            depth: 0,
+        });
+        self.execute()
+    }
+
+    /// Calls the global or native function `name`.
+    pub(crate) fn call(&mut self, name: &str, args: Vec<Val>) -> Result<Val, EvalError> {
+        let argc = args.len();
+        self.stack.extend(args);
+        self.call_global(name, argc)?;
+
+        if self.frames.is_empty() {
+            Ok(self.pop())
+        } else {
+            self.execute()
+        }
+    }
+
+    fn execute(&mut self) -> Result<Val, EvalError> {
+        loop {
+            self.env.tick()?;
+
+            let frame = self.frames.last_mut().expect("a frame is running");
+            let instr = frame.program.funcs[frame.func].chunk.code[frame.ip].clone();
+            frame.ip += 1;
+
+            match instr {
+                Instr::Const(idx) => {
+                    let val = self.frame().function().chunk.consts[idx].clone();
+                    self.stack.push(val);
+                }
+                Instr::Unit => self.stack.push(Val::Unit),
+                Instr::Pop => {
+                    self.pop();
+                }
+                Instr::LoadLocal(slot) => {
+                    let val = self.stack[self.frame().base + slot].clone();
+                    self.stack.push(val);
+                }
+                Instr::StoreLocal(slot) => {
+                    let val = self.pop();
+                    self.env.charge(&val)?;
+                    let base = self.frame().base;
+                    self.stack[base + slot] = val;
+                }
+                Instr::LoadOuter { depth, slot } => {
+                    let frame = self.linked_frame(depth);
+                    let val = self.stack[self.frames[frame].base + slot].clone();
+                    self.stack.push(val);
+                }
+                Instr::LoadGlobal(name) => {
+                    let val = self.env.get_binding(self.name(name))?;
+                    self.stack.push(val);
+                }
+                Instr::StoreGlobal(name) => {
+                    let val = self.pop();
+                    let name = self.name(name).to_string();
+                    self.compiled.remove(&name);
+                    self.env.bind(name, val)?;
+                }
+                Instr::DefineGlobal(idx) => {
+                    let program = Rc::clone(&self.frame().program);
+                    let global = &program.globals[idx];
+                    let def = &global.def;
+                    self.env.store_func_def(def);
+                    self.compiled.insert(def.name.clone(), Rc::clone(&global.program));
+                }
+                Instr::DefineStruct(idx) => {
+                    let def = Rc::clone(&self.frame().function().chunk.structs[idx]);
+                    self.compiled.remove(&def.name);
+                    self.env.store_struct(def);
+                }
+                Instr::DefineEnum(idx) => {
+                    let def = Rc::clone(&self.frame().function().chunk.enums[idx]);
+                    self.compiled.remove(&def.name);
+                    self.env.store_enum(def);
+                }
+                Instr::CheckType(idx) => {
+                    let (ty, subject) = &self.frame().function().chunk.checks[idx];
+                    let val = self.stack.last().expect("compiled code keeps the stack balanced");
+                    ty.check(val, || subject.clone())?;
+                }
+                Instr::Op(op) => {
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+                    self.stack.push(op.apply(lhs, rhs)?);
+                }
+                Instr::Call { func, depth, argc } => {
+                    let program = Rc::clone(&self.frame().program);
+                    let static_link = self.linked_frame(depth);
+                    self.push_frame(program, func, argc, Some(static_link))?;
+                }
+                Instr::CallGlobal { name, argc } => {
+                    let name = self.name(name).to_string();
+                    self.call_global(&name, argc)?;
+                }
+                Instr::GetField(name) => {
+                    let target = self.pop();
+                    let val = member::get_field(target, self.name(name), self.env)?;
+                    self.stack.push(val);
+                }
+                Instr::CallMethod { name, argc } => {
+                    let args = self.stack.split_off(self.stack.len() - argc);
+                    let target = self.pop();
+                    let val = member::call_method(target, self.name(name), &args, self.env)?;
+                    self.stack.push(val);
+                }
+                Instr::MakeStruct(idx) => {
+                    let program = Rc::clone(&self.frame().program);
+                    let shape = &program.funcs[self.frame().func].chunk.literals[idx];
+                    let base = shape.has_base.then(|| self.pop());
+                    let vals = self.stack.split_off(self.stack.len() - shape.fields.len());
+                    let fields = shape.fields.iter().cloned().zip(vals).collect();
+
+                    let def = match &shape.def {
+                        Some(def) => Rc::clone(def),
+                        None => self.env.get_struct(&shape.name)?,
+                    };
+                    self.stack.push(def.construct(fields, base)?);
+                }
+                Instr::MakeVariant(idx) => {
+                    let program = Rc::clone(&self.frame().program);
+                    let shape = &program.funcs[self.frame().func].chunk.variants[idx];
+                    let args = self.stack.split_off(self.stack.len() - shape.argc);
+
+                    let def = match &shape.def {
+                        Some(def) => Rc::clone(def),
+                        None => self.env.get_enum(&shape.enum_name)?,
+                    };
+                    self.stack.push(def.construct(&shape.variant, args)?);
+                }
+                Instr::MakeTuple(len) => {
+                    let items = self.stack.split_off(self.stack.len() - len);
+                    let val = Val::Tuple(items.into());
+                    self.env.charge(&val)?;
+                    self.stack.push(val);
+                }
+                Instr::Match { pattern, otherwise } => {
+                    let program = Rc::clone(&self.frame().program);
+                    let pattern = &program.funcs[self.frame().func].chunk.patterns[pattern];
+                    let val = self.stack.last().expect("compiled code keeps the stack balanced");
+
+                    let mut bound = Vec::new();
+                    if pattern.matches(val, &mut bound) {
+                        let base = self.frame().base;
+                        for (binding, val) in bound {
+                            self.env.charge(&val)?;
+                            let slot = binding.slot.expect("the compiler gives bindings slots");
+                            self.stack[base + slot] = val;
+                        }
+                    } else {
+                        self.jump(otherwise);
+                    }
+                }
+                Instr::Guard(otherwise) => {
+                    let val = self.pop();
+                    if !match_expr::guard_holds(val)? {
+                        self.jump(otherwise);
+                    }
+                }
+                Instr::Jump(target) => self.jump(target),
+                Instr::Unwrap(target) => {
+                    let val = self.pop();
+                    match try_expr::unwrap(val)? {
+                        ControlFlow::Continue(val) => {
+                            self.stack.push(val);
+                            self.jump(target);
+                        }
+                        ControlFlow::Break(val) => self.stack.push(val),
+                    }
+                }
+                Instr::NoMatch => {
+                    let val = self.stack.last().expect("compiled code keeps the stack balanced");
+                    return Err(match_expr::no_match(val));
+                }
+                Instr::NotABinding(name) => {
+                    return Err(format!("binding with name '{}' does not exist", self.name(name)).into());
+                }
+                Instr::NotAFunction(name) => {
+                    return Err(format!("function with name '{}' does not exist", self.name(name)).into());
+                }
+                Instr::NotAStruct(name) => {
+                    return Err(format!("struct with name '{}' does not exist", self.name(name)).into());
+                }
+                Instr::NotAnEnum(name) => {
+                    return Err(format!("enum with name '{}' does not exist", self.name(name)).into());
+                }
+                Instr::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("a frame is running");
+                    self.stack.truncate(frame.base);
+
+                    if self.frames.is_empty() {
+                        return Ok(result);
+                    }
+                    self.stack.push(result);
+                }
+            }
+        }
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().expect("a frame is running")
+    }
+
+    fn jump(&mut self, target: usize) {
+        self.frames.last_mut().expect("a frame is running").ip = target;
+    }
+
+    fn pop(&mut self) -> Val {
+        self.stack.pop().expect("compiled code keeps the stack balanced")
+    }
+
+    fn name(&self, idx: usize) -> &str {
+        &self.frame().function().chunk.names[idx]
+    }
+
+    /// The index of the frame `depth` static links up from the current one.
+    fn linked_frame(&self, depth: usize) -> usize {
+        let mut frame = self.frames.len() - 1;
+        for _ in 0..depth {
+            frame = self.frames[frame]
+                .static_link
+                .expect("nested functions are linked to the frame they were defined in");
+        }
+        frame
+    }
+
+    /// Starts running function `func` of `program` with the `argc` values on
+    /// top of the stack as its arguments.
+    fn push_frame(
+        &mut self,
+        program: Rc<Program>,
+        func: usize,
+        argc: usize,
+        static_link: Option<usize>,
+    ) -> Result<(), EvalError> {
+        let function = &program.funcs[func];
+        env::check_arity(Some(&function.name), function.arity, argc)?;
+
+        let depth = self.frames.last().map_or(0, |frame| frame.depth);
+        if depth >= MAX_CALL_DEPTH {
+            return Err(format!("maximum call depth of {} exceeded", MAX_CALL_DEPTH).into());
+        }
+
+        let base = self.stack.len() - argc;
+        for arg in &self.stack[base..] {
+            self.env.charge(arg)?;
+        }
+        self.stack.resize(base + function.num_slots, Val::Unit);
+
+        self.frames.push(Frame {
+            program,
+            func,
+            ip: 0,
+            base,
+            static_link,
+            depth: depth + 1,
+        });
+        Ok(())
+    }
+
+    /// Calls the global function `name`, starting a frame for a script
+    /// function and pushing the result of a native one.
+    fn call_global(&mut self, name: &str, argc: usize) -> Result<(), EvalError> {
+        let program = match self.env.find(name) {
+            Some((_, NamedInfo::Func { params, signature, body })) => {
+                match self.compiled.get(name).or_else(|| self.cache.get(name)) {
+                    Some(program) => Rc::clone(program),
+                    None => {
+                        let program = Rc::new(compiler::compile_func(name, params, signature.as_deref(), body));
+                        self.compiled.insert(name.to_string(), Rc::clone(&program));
+                        program
+                    }
+                }
+            }
+            Some((_, NamedInfo::NativeFunc(func))) => {
+                let args = self.stack.split_off(self.stack.len() - argc);
+                let result = self.env.call_native(name, func, &args)?;
+                self.stack.push(result);
+                return Ok(());
+            }
+            _ => return Err(format!("function with name '{}' does not exist", name).into()),
+        };
+
+        self.push_frame(program, 0, argc, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_gen::{self, Profile};
+    use crate::{Error, NativeFunc};
+
+    fn run(env: &mut Env, src: &str) -> Result<Val, EvalError> {
+        let parse = crate::parse(src);
+        assert!(parse.errors().is_empty(), "{:?}", parse.errors());
+        let program = Rc::new(compiler::compile(parse.stmts()));
+        Vm::new(env, &HashMap::new()).run(program)
+    }
+
+    #[test]
+    fn run_arithmetic() {
+        assert_eq!(run(&mut Env::default(), "1 + 2 * 3 - 8 / 4"), Ok(Val::Number(5)));
+    }
+
+    #[test]
+    fn run_blocks_with_shadowing() {
+        assert_eq!(
+            run(&mut Env::default(), "let a = 1\n{\n    let a = a + 10\n    let b = { let a = a * 2 a }\n    a + b\n}"),
+            Ok(Val::Number(33))
+        );
+    }
+
+    #[test]
+    fn define_globals_in_env() {
+        let mut env = Env::default();
+        assert_eq!(run(&mut env, "let a = 2\nfn double x => x * 2"), Ok(Val::Unit));
+
+        assert_eq!(env.get_binding("a"), Ok(Val::Number(2)));
+        assert_eq!(env.call_func("double", vec![Val::Number(4)]), Ok(Val::Number(8)));
+        assert_eq!(run(&mut env, "double(a)"), Ok(Val::Number(4)));
+    }
+
+    #[test]
+    fn call_nested_functions_using_outer_bindings() {
+        let src = "fn scale factor x => {
+    fn apply n => n * factor
+    fn twice n => apply(apply(n))
+    twice(x)
+}
+scale(3, 2)";
+        assert_eq!(run(&mut Env::default(), src), Ok(Val::Number(18)));
+    }
+
+    #[test]
+    fn call_recursive_functions_without_native_recursion() {
+        let src = "fn count n => {
+    fn down n => down(n)
+    down(n)
+}
+count(1)";
+        assert_eq!(
+            run(&mut Env::default(), src),
+            Err(format!("maximum call depth of {} exceeded", MAX_CALL_DEPTH).into())
+        );
+    }
+
+    #[test]
+    fn call_native_functions() {
+        let mut env = Env::default();
+        env.store_native_func(
+            "count".to_string(),
+            NativeFunc::new(|args| Ok(Val::Number(args.len() as i32))),
+        );
+        env.store_native_func(
+            "fail".to_string(),
+            NativeFunc::new(|_| Err(Error::Eval("no luck".into()))),
+        );
+
+        assert_eq!(run(&mut env, "count(1, 2, count())"), Ok(Val::Number(3)));
+        assert_eq!(run(&mut env, "fail()"), Err("fail: no luck".into()));
+    }
+
+    #[test]
+    fn call_global_function_from_host() {
+        let mut env = Env::default();
+        run(&mut env, "fn add x y => x + y").unwrap();
+
+        let cache = HashMap::new();
+        let mut vm = Vm::new(&mut env, &cache);
+        assert_eq!(vm.call("add", vec![Val::Number(1), Val::Number(2)]), Ok(Val::Number(3)));
+        assert_eq!(
+            vm.call("add", vec![]),
+            Err("function 'add' takes 2 argument(s) but 0 were given".into())
+        );
+        assert_eq!(vm.into_compiled().len(), 1);
+    }
+
+    #[test]
+    fn report_misused_names() {
+        let mut env = Env::default();
+        assert_eq!(
+            run(&mut env, "missing"),
+            Err("binding with name 'missing' does not exist".into())
+        );
+        assert_eq!(
+            run(&mut env, "{\n    let a = 1\n    a()\n}"),
+            Err("function with name 'a' does not exist".into())
+        );
+        assert_eq!(
+            run(&mut env, "{\n    fn f => 1\n    f\n}"),
+            Err("binding with name 'f' does not exist".into())
+        );
+    }
+
+    fn engine_with(backend: crate::Backend) -> crate::Engine {
+        let mut engine = crate::Engine::new();
+        engine.set_backend(backend);
+        engine
+    }
+
+    #[test]
+    fn agree_with_tree_walker() {
+        // Defining a name only once is where name resolution at compile time
+        // and at runtime agree.
+        let profile = Profile {
+            numbers: &["0", "1", "7", "100000"],
+            redefine: false,
+        };
+        test_gen::check_agreement(0x2545_f491_4f6c_dd1d, profile, 100, || {
+            vec![engine_with(crate::Backend::TreeWalker), engine_with(crate::Backend::Vm)]
+        });
+    }
+
+    #[test]
+    fn agree_with_tree_walker_on_recursion() {
+        let srcs = vec![
+            "fn fib n => fib2(n, 0, 1)\nfn fib2 n a b => { fn step => fib2(n - 1, b, a + b) pick(n, a, step) }".to_string(),
+            "fn pick n a step => a".to_string(),
+            "fib(10)".to_string(),
+            "fn loop n => loop(n + 1)\nloop(0)".to_string(),
+        ];
+        let mut vm = engine_with(crate::Backend::Vm);
+        let mut tree_walker = engine_with(crate::Backend::TreeWalker);
+        for src in srcs {
+            assert_eq!(
+                vm.eval(&src).map_err(|err| err.to_string()),
+                tree_walker.eval(&src).map_err(|err| err.to_string()),
+                "source:\n{}",
+                src
+            );
+        }
+    }
+}